@@ -0,0 +1,298 @@
+pub mod expand;
+pub mod generate;
+
+use std::collections::HashSet;
+
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, LitStr, Path, Token, Type, braced, bracketed};
+
+/// HTTP methods supported by routes, named as the corresponding
+/// `axum::routing` method router.
+const METHODS: [&str; 7] = ["get", "post", "put", "patch", "delete", "head", "options"];
+
+/// Configuration for the guest macro.
+///
+/// Parses HTTP routes and messaging topics. For example:
+/// ```ignore
+/// {
+///     http: [
+///         "/jobs/detector": {
+///             method: get,
+///             handler: detection_request,
+///             response: DetectionResponse,
+///         },
+///         "/jobs": {
+///             method: post,
+///             request: Job,
+///             handler: create_job,
+///             response: JobResponse,
+///         }
+///     ],
+///     messaging: [
+///         "realtime-r9k.v1": {
+///             message: R9kMessage,
+///             handler: r9k_message,
+///         }
+///     ]
+/// }
+/// ```
+pub struct GuestInput {
+    pub routes: Vec<Route>,
+    pub topics: Vec<Topic>,
+}
+
+impl Parse for GuestInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        braced!(content in input);
+
+        let mut routes = Vec::<Route>::new();
+        let mut topics = Vec::<Topic>::new();
+
+        // parse 'http' and 'messaging' sections
+        while !content.is_empty() {
+            let section = content.parse::<Ident>()?;
+            content.parse::<Token![:]>()?;
+            let entries;
+            bracketed!(entries in content);
+
+            match section.to_string().as_str() {
+                "http" => {
+                    while !entries.is_empty() {
+                        routes.push(entries.parse()?);
+                        if entries.peek(Token![,]) {
+                            entries.parse::<Token![,]>()?;
+                        }
+                    }
+                }
+                "messaging" => {
+                    while !entries.is_empty() {
+                        topics.push(entries.parse()?);
+                        if entries.peek(Token![,]) {
+                            entries.parse::<Token![,]>()?;
+                        }
+                    }
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        section.span(),
+                        "unknown section, expected `http` or `messaging`",
+                    ));
+                }
+            }
+
+            if content.peek(Token![,]) {
+                content.parse::<Token![,]>()?;
+            }
+        }
+
+        // reject routes and topics that would never be reached
+        let mut seen = HashSet::new();
+        for route in &routes {
+            if !seen.insert((route.method.to_string(), route.path.value())) {
+                return Err(syn::Error::new(
+                    route.path.span(),
+                    format!("duplicate route `{} {}`", route.method, route.path.value()),
+                ));
+            }
+        }
+        let mut seen = HashSet::new();
+        for topic in &topics {
+            if !seen.insert(topic.name.value()) {
+                return Err(syn::Error::new(
+                    topic.name.span(),
+                    format!("duplicate topic `{}`", topic.name.value()),
+                ));
+            }
+        }
+
+        Ok(Self { routes, topics })
+    }
+}
+
+/// An HTTP route handled by the guest.
+pub struct Route {
+    pub path: LitStr,
+    pub method: Ident,
+    /// The type the JSON request body is deserialized into, if any.
+    pub request: Option<Type>,
+    pub handler: Path,
+    pub response: Type,
+}
+
+impl Parse for Route {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse::<LitStr>()?;
+        input.parse::<Token![:]>()?;
+        let fields;
+        braced!(fields in input);
+
+        let mut method = None;
+        let mut request = None;
+        let mut handler = None;
+        let mut response = None;
+
+        while !fields.is_empty() {
+            let key = fields.parse::<Ident>()?;
+            fields.parse::<Token![:]>()?;
+            match key.to_string().as_str() {
+                "method" => {
+                    let value = fields.parse::<Ident>()?;
+                    if !METHODS.contains(&value.to_string().as_str()) {
+                        return Err(syn::Error::new(
+                            value.span(),
+                            format!("unsupported method, expected one of {}", METHODS.join(", ")),
+                        ));
+                    }
+                    method = Some(value);
+                }
+                "request" => request = Some(fields.parse()?),
+                "handler" => handler = Some(fields.parse()?),
+                "response" => response = Some(fields.parse()?),
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        "unknown field, expected `method`, `request`, `handler` or `response`",
+                    ));
+                }
+            }
+            if fields.peek(Token![,]) {
+                fields.parse::<Token![,]>()?;
+            }
+        }
+
+        let missing = |field: &str| syn::Error::new(path.span(), format!("missing `{field}`"));
+        Ok(Self {
+            method: method.ok_or_else(|| missing("method"))?,
+            request,
+            handler: handler.ok_or_else(|| missing("handler"))?,
+            response: response.ok_or_else(|| missing("response"))?,
+            path,
+        })
+    }
+}
+
+/// A messaging topic handled by the guest.
+pub struct Topic {
+    pub name: LitStr,
+    pub message: Type,
+    pub handler: Path,
+}
+
+impl Parse for Topic {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse::<LitStr>()?;
+        input.parse::<Token![:]>()?;
+        let fields;
+        braced!(fields in input);
+
+        let mut message = None;
+        let mut handler = None;
+
+        while !fields.is_empty() {
+            let key = fields.parse::<Ident>()?;
+            fields.parse::<Token![:]>()?;
+            match key.to_string().as_str() {
+                "message" => message = Some(fields.parse()?),
+                "handler" => handler = Some(fields.parse()?),
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        "unknown field, expected `message` or `handler`",
+                    ));
+                }
+            }
+            if fields.peek(Token![,]) {
+                fields.parse::<Token![,]>()?;
+            }
+        }
+
+        let missing = |field: &str| syn::Error::new(name.span(), format!("missing `{field}`"));
+        Ok(Self {
+            message: message.ok_or_else(|| missing("message"))?,
+            handler: handler.ok_or_else(|| missing("handler"))?,
+            name,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guest::generate::Generated;
+
+    fn parse_err(input: &str) -> String {
+        syn::parse_str::<GuestInput>(input).map(drop).expect_err("should not parse").to_string()
+    }
+
+    #[test]
+    fn expand() {
+        let input = syn::parse_str::<GuestInput>(
+            r#"{
+                http: [
+                    "/jobs/detector": {
+                        method: get,
+                        handler: detection_request,
+                        response: DetectionResponse,
+                    },
+                    "/jobs": {
+                        method: post,
+                        request: Job,
+                        handler: create_job,
+                        response: JobResponse,
+                    }
+                ],
+                messaging: [
+                    "realtime-r9k.v1": {
+                        message: R9kMessage,
+                        handler: r9k_message,
+                    }
+                ]
+            }"#,
+        )
+        .expect("should parse");
+        assert_eq!(input.routes.len(), 2);
+        assert_eq!(input.topics.len(), 1);
+
+        let expanded = expand::expand(Generated::from(input));
+        syn::parse2::<syn::File>(expanded).expect("expansion should be valid Rust");
+    }
+
+    #[test]
+    fn duplicate_route() {
+        let err = parse_err(
+            r#"{ http: [
+                "/jobs": { method: get, handler: list, response: Jobs },
+                "/jobs": { method: get, handler: list_again, response: Jobs },
+            ] }"#,
+        );
+        assert_eq!(err, "duplicate route `get /jobs`");
+    }
+
+    #[test]
+    fn duplicate_topic() {
+        let err = parse_err(
+            r#"{ messaging: [
+                "jobs.v1": { message: Job, handler: job },
+                "jobs.v1": { message: Job, handler: job_again },
+            ] }"#,
+        );
+        assert_eq!(err, "duplicate topic `jobs.v1`");
+    }
+
+    #[test]
+    fn unknown_method() {
+        let err =
+            parse_err(r#"{ http: [ "/jobs": { method: fetch, handler: list, response: Jobs } ] }"#);
+        assert!(err.starts_with("unsupported method"), "{err}");
+    }
+
+    #[test]
+    fn missing_field() {
+        let err = parse_err(r#"{ http: [ "/jobs": { method: get, handler: list } ] }"#);
+        assert_eq!(err, "missing `response`");
+
+        let err = parse_err(r#"{ messaging: [ "jobs.v1": { handler: job } ] }"#);
+        assert_eq!(err, "missing `message`");
+    }
+}
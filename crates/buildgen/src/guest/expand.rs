@@ -0,0 +1,72 @@
+//! # Generated Code Expansion
+//!
+//! Expands the generated code into the guest's exports.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::guest::generate::Generated;
+
+pub fn expand(generated: Generated) -> TokenStream {
+    let Generated {
+        routes,
+        route_handlers,
+        topics,
+        topic_handlers,
+    } = generated;
+
+    // `wasi:http` handler export
+    let http = if routes.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            pub struct Http;
+            wasip3::http::proxy::export!(Http);
+
+            impl wasip3::exports::http::handler::Guest for Http {
+                /// Routes incoming HTTP requests to their handlers.
+                async fn handle(
+                    request: wasip3::http::types::Request,
+                ) -> Result<wasip3::http::types::Response, wasip3::http::types::ErrorCode> {
+                    let router = wasi_http::axum::Router::new()#(#routes)*;
+                    wasi_http::serve(router, request).await
+                }
+            }
+
+            #(#route_handlers)*
+        }
+    };
+
+    // `wasi:messaging` incoming-handler export
+    let messaging = if topics.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            pub struct Messaging;
+            wasi_messaging::export!(Messaging with_types_in wasi_messaging);
+
+            impl wasi_messaging::incoming_handler::Guest for Messaging {
+                /// Routes incoming messages to the handler for their topic.
+                async fn handle(
+                    message: wasi_messaging::types::Message,
+                ) -> Result<(), wasi_messaging::types::Error> {
+                    let topic = message.topic().unwrap_or_default();
+                    match topic.as_str() {
+                        #(#topics => { #topic_handlers })*
+                        // messages on other topics are not handled by the guest
+                        _ => Ok(()),
+                    }
+                }
+            }
+        }
+    };
+
+    quote! {
+        mod guest {
+            use super::*;
+
+            #http
+            #messaging
+        }
+    }
+}
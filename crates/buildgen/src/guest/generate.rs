@@ -0,0 +1,73 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::LitStr;
+
+use crate::guest::GuestInput;
+
+pub struct Generated {
+    pub routes: Vec<TokenStream>,
+    pub route_handlers: Vec<TokenStream>,
+    pub topics: Vec<LitStr>,
+    pub topic_handlers: Vec<TokenStream>,
+}
+
+impl From<GuestInput> for Generated {
+    fn from(input: GuestInput) -> Self {
+        let mut routes = Vec::new();
+        let mut route_handlers = Vec::new();
+
+        for (i, route) in input.routes.iter().enumerate() {
+            let path = &route.path;
+            let method = &route.method;
+            let handler = &route.handler;
+            let response = &route.response;
+            let route_fn = format_ident!("route_{i}");
+
+            routes.push(quote! {
+                .route(#path, wasi_http::axum::routing::#method(#route_fn))
+            });
+
+            // wrap the handler in an axum handler (de)serializing JSON bodies
+            let (param, arg) = route.request.as_ref().map_or_else(
+                || (quote! {}, quote! {}),
+                |request| {
+                    let param = quote! {
+                        wasi_http::axum::Json(request): wasi_http::axum::Json<#request>
+                    };
+                    (param, quote! { request })
+                },
+            );
+            route_handlers.push(quote! {
+                async fn #route_fn(#param) -> wasi_http::HttpResult<wasi_http::axum::Json<#response>> {
+                    Ok(wasi_http::axum::Json(#handler(#arg).await?))
+                }
+            });
+        }
+
+        let mut topics = Vec::new();
+        let mut topic_handlers = Vec::new();
+
+        for topic in input.topics {
+            let message = &topic.message;
+            let handler = &topic.handler;
+
+            // deserialize the payload into the declared message type
+            topic_handlers.push(quote! {
+                let payload = serde_json::from_slice::<#message>(&message.data()).map_err(|e| {
+                    wasi_messaging::types::Error::Other(format!("invalid message on `{topic}`: {e}"))
+                })?;
+                #handler(payload)
+                    .await
+                    .map_err(|e| wasi_messaging::types::Error::Other(e.to_string()))
+            });
+            topics.push(topic.name);
+        }
+
+        Self {
+            routes,
+            route_handlers,
+            topics,
+            topic_handlers,
+        }
+    }
+}
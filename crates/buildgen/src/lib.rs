@@ -1,3 +1,4 @@
+mod guest;
 mod runtime;
 
 use proc_macro::TokenStream;
@@ -5,13 +6,31 @@ use syn::parse_macro_input;
 
 /// Generates the runtime infrastructure based on the configuration.
 ///
+/// Hosts opening named resources can map each name to its own backend,
+/// with other names using the `default` backend (or the first listed). Of
+/// the in-tree hosts, `WasiBlobstore`, `WasiKeyValue`, `WasiSql` and
+/// `WasiVault` support named backends; other hosts must implement their
+/// context for `kernel::Named`.
+///
+/// In-tree hosts are named by their type (e.g. `WasiKeyValue`) or by path
+/// within their crate (e.g. `wasi_keyvalue::WasiKeyValue`). Other hosts must
+/// be named by path (e.g. `acme_ledger::Host`), even when imported, and
+/// implement `kernel::WasiHost`, with their interfaces linked using
+/// `kernel::HostView`. Any other unqualified host type is an error.
+///
 /// # Example
 ///
 /// ```ignore
 /// buildgen::runtime!({
-///     wasi_http: WasiHttp,
-///     wasi_otel: DefaultOtel,
-///     wasi_blobstore: MongoDb,
+///     WasiHttp: HttpDefault,
+///     WasiOtel: OtelDefault,
+///     WasiBlobstore: BlobstoreDefault,
+///     WasiKeyValue: {
+///         "cache": KeyValueDefault,
+///         "state": KeyValueSqlite,
+///         default: "cache",
+///     },
+///     acme_ledger::Host: AcmeBackend,
 /// });
 /// ```
 #[proc_macro]
@@ -24,7 +43,21 @@ pub fn runtime(input: TokenStream) -> TokenStream {
     crate::runtime::expand::expand(generated).into()
 }
 
-/// Generates the guest infrastructure based on the configuration.
+/// Generates the guest's `wasi:http` handler and `wasi:messaging`
+/// incoming-handler exports.
+///
+/// Each HTTP route is served by an axum router, deserializing the JSON
+/// request body (when a `request` type is declared) and serializing the
+/// handler's response as JSON. `request` is always the request body, so
+/// routes without a body (such as `get`) omit it and their handler takes no
+/// arguments. Handlers are `async` functions returning
+/// `anyhow::Result<Response>`.
+///
+/// Each messaging topic deserializes the JSON message payload into the
+/// declared type and passes it to its handler, an `async` function returning
+/// `anyhow::Result<()>`. Messages on other topics are ignored.
+///
+/// Duplicate routes (method and path) or topics are rejected at compile time.
 ///
 /// # Example
 ///
@@ -33,19 +66,27 @@ pub fn runtime(input: TokenStream) -> TokenStream {
 ///     http: [
 ///         "/jobs/detector": {
 ///             method: get,
-///             request: String,
 ///             handler: detection_request,
 ///             response: DetectionResponse,
+///         },
+///         "/jobs": {
+///             method: post,
+///             request: Job,
+///             handler: create_job,
+///             response: JobResponse,
 ///         }
 ///     ],
 ///     messaging: [
 ///         "realtime-r9k.v1": {
 ///             message: R9kMessage,
+///             handler: r9k_message,
 ///         }
 ///     ]
 /// });
 /// ```
 #[proc_macro]
-pub fn guest(_input: TokenStream) -> TokenStream {
-    todo!("not implemented")
+pub fn guest(input: TokenStream) -> TokenStream {
+    let parsed = parse_macro_input!(input as guest::GuestInput);
+    let generated = crate::guest::generate::Generated::from(parsed);
+    crate::guest::expand::expand(generated).into()
 }
@@ -1,20 +1,69 @@
 pub mod expand;
 pub mod generate;
 
+use std::collections::HashSet;
+
+use quote::{format_ident, quote};
 use syn::parse::{Parse, ParseStream};
-use syn::{Token, Type};
+use syn::{Ident, LitStr, Token, Type, TypePath};
 
 mod kw {
     syn::custom_keyword!(main);
 }
 
+/// A built-in `wasi-*` host.
+struct BuiltIn {
+    /// The host's type, e.g. `WasiKeyValue`.
+    name: &'static str,
+
+    /// The host's crate, providing its `wasi_view!` macro and `WIT`.
+    module: &'static str,
+
+    /// The context trait implemented for `kernel::Named`, for hosts
+    /// supporting named backends.
+    named_ctx: Option<&'static str>,
+}
+
+const BUILT_IN: [BuiltIn; 10] = [
+    BuiltIn::new("WasiBlobstore", "wasi_blobstore", Some("WasiBlobstoreCtx")),
+    BuiltIn::new("WasiConfig", "wasi_config", None),
+    BuiltIn::new("WasiHttp", "wasi_http", None),
+    BuiltIn::new("WasiIdentity", "wasi_identity", None),
+    BuiltIn::new("WasiKeyValue", "wasi_keyvalue", Some("WasiKeyValueCtx")),
+    BuiltIn::new("WasiMessaging", "wasi_messaging", None),
+    BuiltIn::new("WasiOtel", "wasi_otel", None),
+    BuiltIn::new("WasiSql", "wasi_sql", Some("WasiSqlCtx")),
+    BuiltIn::new("WasiVault", "wasi_vault", Some("WasiVaultCtx")),
+    BuiltIn::new("WasiWebSockets", "wasi_websockets", None),
+];
+
+impl BuiltIn {
+    const fn new(
+        name: &'static str, module: &'static str, named_ctx: Option<&'static str>,
+    ) -> Self {
+        Self {
+            name,
+            module,
+            named_ctx,
+        }
+    }
+}
+
 /// Configuration for the runtime macro.
 ///
-/// Parses input in the form of 'host:backend' pairs. For example:
+/// Parses input in the form of 'host:backend' pairs, where a host can map
+/// named resources to one of several backends. Hosts outside this repo are
+/// named by path. For example:
 /// ```ignore
 /// {
 ///     WasiHttp: HttpDefault,
-///     WasiOtel: DefaultOtel,
+///     WasiOtel: OtelDefault,
+///     WasiKeyValue: {
+///         "cache": KeyValueDefault,
+///         "state": KeyValueSqlite,
+///         default: "cache",
+///     },
+///     acme_ledger::Host: AcmeBackend,
 ///     ...
 /// }
 /// ```
@@ -43,12 +92,18 @@ impl Parse for RuntimeInput {
         // parse 'host:backend' pairs
         while !content.is_empty() {
             let host_type = content.parse::<Type>()?;
+            let kind = HostKind::of(&host_type)?;
             content.parse::<Token![:]>()?;
-            let backend: Type = content.parse()?;
+            let backend = if content.peek(syn::token::Brace) {
+                kind.check_named(&host_type)?;
+                content.parse::<HostBackend>()?
+            } else {
+                let backend = content.parse::<Type>()?;
+                backends.push(backend.clone());
+                HostBackend::Single(backend)
+            };
 
-            let host_config = Host::new(host_type, backend.clone());
-            hosts.push(host_config);
-            backends.push(backend);
+            hosts.push(Host::new(host_type, kind, backend));
 
             if content.peek(Token![,]) {
                 content.parse::<Token![,]>()?;
@@ -66,11 +121,227 @@ impl Parse for RuntimeInput {
 /// Information about a WASI host and its configuration.
 pub struct Host {
     pub type_: Type,
-    pub backend: Type,
+    pub kind: HostKind,
+    pub backend: HostBackend,
 }
 
 impl Host {
-    const fn new(type_: Type, backend: Type) -> Self {
-        Self { type_, backend }
+    const fn new(type_: Type, kind: HostKind, backend: HostBackend) -> Self {
+        Self { type_, kind, backend }
+    }
+}
+
+/// How the runtime links a host.
+pub enum HostKind {
+    /// A built-in `wasi-*` host, named by type (e.g. `WasiKeyValue`) or by
+    /// path within its crate (e.g. `wasi_keyvalue::WasiKeyValue`). The host
+    /// implements its own view using its crate's `wasi_view!` macro.
+    BuiltIn { module: Ident, named_ctx: Option<Ident> },
+
+    /// A host outside this repo, named by path (e.g. `acme_ledger::Host`),
+    /// implementing `kernel::WasiHost`. `StoreCtx` implements
+    /// `kernel::HostView` for the host.
+    External,
+}
+
+impl HostKind {
+    fn of(host_type: &Type) -> syn::Result<Self> {
+        let Type::Path(TypePath { qself: None, path }) = host_type else {
+            return Err(syn::Error::new_spanned(host_type, "expected a host type or path"));
+        };
+        let segments =
+            path.segments.iter().map(|segment| segment.ident.to_string()).collect::<Vec<_>>();
+
+        let built_in = BUILT_IN.iter().find(|host| match segments.as_slice() {
+            [name] => path.leading_colon.is_none() && name == host.name,
+            [module, name] => module == host.module && name == host.name,
+            _ => false,
+        });
+        if let Some(host) = built_in {
+            return Ok(Self::BuiltIn {
+                module: format_ident!("{}", host.module),
+                named_ctx: host.named_ctx.map(|ctx| format_ident!("{ctx}")),
+            });
+        }
+
+        // hosts outside this repo are named by path so that they cannot be
+        // mistaken for (misspelt) built-in hosts
+        if segments.len() == 1 && path.leading_colon.is_none() {
+            let built_in = BUILT_IN.iter().map(|host| host.name).collect::<Vec<_>>();
+            return Err(syn::Error::new_spanned(
+                host_type,
+                format!(
+                    "unknown host `{}`, expected one of {}, or a host outside this repo named \
+                     by path (e.g. `acme_ledger::Host`) implementing `kernel::WasiHost`",
+                    segments[0],
+                    built_in.join(", ")
+                ),
+            ));
+        }
+        Ok(Self::External)
+    }
+
+    // Check the host supports named backends. Hosts outside this repo are
+    // assumed to implement their context for `kernel::Named`, failing to
+    // compile if not.
+    fn check_named(&self, host_type: &Type) -> syn::Result<()> {
+        if let Self::BuiltIn { named_ctx: None, .. } = self {
+            let named = BUILT_IN
+                .iter()
+                .filter(|host| host.named_ctx.is_some())
+                .map(|host| host.name)
+                .collect::<Vec<_>>();
+            return Err(syn::Error::new_spanned(
+                host_type,
+                format!(
+                    "`{}` does not support named backends, expected a single backend (named \
+                     backends are supported by {})",
+                    quote! {#host_type}.to_string().replace(' ', ""),
+                    named.join(", ")
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The backend(s) used by a host.
+pub enum HostBackend {
+    /// A single backend, shared with other hosts using the same backend type.
+    Single(Type),
+
+    /// Several backend instances, each used for the resources with its name.
+    /// Resources without a matching instance use the default instance, listed
+    /// first.
+    Named(Vec<Instance>),
+}
+
+/// A named backend instance.
+pub struct Instance {
+    pub name: LitStr,
+    pub backend: Type,
+}
+
+impl Parse for HostBackend {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        let brace = syn::braced!(content in input);
+
+        let mut instances = Vec::<Instance>::new();
+        let mut default = None::<LitStr>;
+
+        // parse '"name": backend' pairs and the optional default
+        while !content.is_empty() {
+            if content.peek(Token![default]) {
+                content.parse::<Token![default]>()?;
+                content.parse::<Token![:]>()?;
+                default = Some(content.parse()?);
+            } else {
+                let name = content.parse::<LitStr>()?;
+                content.parse::<Token![:]>()?;
+                let backend = content.parse::<Type>()?;
+                instances.push(Instance { name, backend });
+            }
+
+            if content.peek(Token![,]) {
+                content.parse::<Token![,]>()?;
+            }
+        }
+
+        if instances.is_empty() {
+            return Err(syn::Error::new(brace.span.join(), "expected at least one backend"));
+        }
+        let mut seen = HashSet::new();
+        for instance in &instances {
+            if !seen.insert(instance.name.value()) {
+                return Err(syn::Error::new(
+                    instance.name.span(),
+                    format!("duplicate backend `{}`", instance.name.value()),
+                ));
+            }
+        }
+
+        // move the default instance first
+        if let Some(default) = default {
+            let Some(index) =
+                instances.iter().position(|instance| instance.name.value() == default.value())
+            else {
+                return Err(syn::Error::new(
+                    default.span(),
+                    format!("default `{}` is not a listed backend", default.value()),
+                ));
+            };
+            let instance = instances.remove(index);
+            instances.insert(0, instance);
+        }
+
+        Ok(Self::Named(instances))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::generate::Generated;
+
+    #[test]
+    fn host_paths() {
+        let input = syn::parse_str::<RuntimeInput>(
+            r#"{
+                WasiHttp: HttpDefault,
+                wasi_keyvalue::WasiKeyValue: { "cache": KeyValueDefault },
+                acme_ledger::Host: AcmeBackend,
+            }"#,
+        )
+        .expect("should parse");
+        assert!(
+            matches!(&input.hosts[0].kind, HostKind::BuiltIn { module, named_ctx: None } if module == "wasi_http")
+        );
+        assert!(
+            matches!(&input.hosts[1].kind, HostKind::BuiltIn { module, named_ctx: Some(_) } if module == "wasi_keyvalue")
+        );
+        assert!(matches!(input.hosts[2].kind, HostKind::External));
+
+        let generated = Generated::try_from(input).expect("should generate");
+        let expanded = expand::expand(generated);
+        syn::parse2::<syn::File>(expanded.clone()).expect("expansion should be valid Rust");
+
+        let expanded = expanded.to_string();
+        assert!(expanded.contains("wasi_keyvalue :: wasi_view ! (StoreCtx , wasi_keyvalue)"));
+        assert!(expanded.contains("impl kernel :: HostView < acme_ledger :: Host > for StoreCtx"));
+        assert!(expanded.contains("ctx : & mut self . acme_ledger_host"));
+    }
+
+    #[test]
+    fn unknown_host() {
+        let err = syn::parse_str::<RuntimeInput>("{ LedgerHost: AcmeBackend }")
+            .map(drop)
+            .expect_err("should require a path");
+        assert!(err.to_string().starts_with("unknown host `LedgerHost`"), "{err}");
+    }
+
+    #[test]
+    fn named_backends() {
+        let input = syn::parse_str::<RuntimeInput>(
+            r#"{
+                WasiKeyValue: {
+                    "cache": KeyValueDefault,
+                    "state": RedisKv,
+                    default: "state",
+                },
+                acme_ledger::Host: { "eu": AcmeBackend },
+            }"#,
+        )
+        .expect("should parse");
+        let HostBackend::Named(instances) = &input.hosts[0].backend else {
+            panic!("should use named backends");
+        };
+        assert_eq!(instances[0].name.value(), "state");
+
+        let err =
+            syn::parse_str::<RuntimeInput>(r#"{ WasiMessaging: { "events": NatsMessaging } }"#)
+                .map(drop)
+                .expect_err("should not support named backends");
+        assert!(err.to_string().starts_with("`WasiMessaging` does not support named backends"));
     }
 }
@@ -4,122 +4,245 @@
 
 use proc_macro2::TokenStream;
 use quote::quote;
+use syn::Type;
 
 use crate::runtime::generate::Generated;
 
 pub fn expand(generated: Generated) -> TokenStream {
     let Generated {
-        context_fields,
+        backend_fields,
+        backend_types,
+        backend_connections,
         store_ctx_fields,
         store_ctx_values,
         host_trait_impls,
         server_trait_impls,
         wasi_view_impls,
+        host_wits,
         main_fn,
+        harness_fn,
+        builder,
+        entry_attrs,
     } = generated;
+    let commands = commands(&host_trait_impls, &host_wits, &entry_attrs);
+    let store_ctx = store_ctx(&store_ctx_fields, &store_ctx_values, &wasi_view_impls);
 
     quote! {
         mod runtime {
             use super::*;
 
             use std::path::PathBuf;
+            use std::sync::Arc;
 
             use anyhow::Result;
             use kernel::anyhow::Context as _;
             use kernel::futures::future::{BoxFuture, try_join_all};
-            use kernel::tokio;
-            use kernel::wasmtime::component::InstancePre;
-            use kernel::wasmtime_wasi::{ResourceTable, WasiCtx, WasiCtxBuilder, WasiCtxView, WasiView};
-            use kernel::{Backend, Compiled, Server, State};
-
-            /// Run the specified wasm guest using the configured runtime.
-            pub async fn run(wasm: PathBuf) -> Result<()> {
-                let mut compiled = kernel::create(&wasm)
-                    .with_context(|| format!("compiling {}", wasm.display()))?;
-                let run_state = Context::new(&mut compiled)
-                    .await
-                    .context("preparing runtime state")?;
-                run_state.start().await.context("starting runtime services")
-            }
+            use kernel::wasmtime_wasi::{ResourceTable, WasiCtx, WasiCtxView, WasiView};
+            use kernel::{
+                Backends, Capabilities, Compiled, Connection, Guest, Guests, Limiter, LimiterView,
+                Manifest, RuntimeInfo, RuntimeOptions, Server, Shutdown, State,
+            };
+
+            #commands
+
+            #harness_fn
+
+            #builder
 
             /// Initiator state holding pre-instantiated components and backend
             /// connections.
             #[derive(Clone)]
-            struct Context {
-                instance_pre: InstancePre<StoreCtx>,
-                #(pub #context_fields,)*
+            pub struct Context {
+                guests: Guests<StoreCtx>,
+                options: RuntimeOptions,
+                shutdown: Shutdown,
+                backends: Backends,
+                info: RuntimeInfo,
+                #(pub #backend_fields: Connection<#backend_types>,)*
             }
 
             impl Context {
                 /// Creates a new runtime state by linking WASI interfaces and
-                /// connecting to backends.
-                async fn new(compiled: &mut Compiled<StoreCtx>) -> Result<Self> {
+                /// connecting to backends. Backends are connected concurrently
+                /// or, when lazy connection is enabled, on first use.
+                pub async fn new(compiled: &mut Compiled<StoreCtx>) -> Result<Self> {
+                    Self::connect(compiled, Preconnected::default()).await
+                }
+
+                // Link WASI interfaces and connect to backends, using those
+                // already connected where provided.
+                async fn connect(
+                    compiled: &mut Compiled<StoreCtx>, preconnected: Preconnected,
+                ) -> Result<Self> {
                     // link enabled WASI components
                     #(compiled.link(#host_trait_impls)?;)*
 
+                    let options = compiled.options().clone();
+                    #(let #backend_fields = #backend_connections;)*
+                    #(
+                        if let Some(backend) = preconnected.#backend_fields {
+                            #backend_fields.set(backend).await;
+                        }
+                    )*
+                    let backends = Backends::default()#(.with(&#backend_fields))*;
+                    if !options.backend.lazy_connect {
+                        backends.connect().await.context("connecting to backends")?;
+                    }
+
                     Ok(Self {
-                        instance_pre: compiled.pre_instantiate()?,
-                        #(#context_fields::connect().await?,)*
+                        guests: compiled.pre_instantiate()?,
+                        options,
+                        shutdown: Shutdown::new(),
+                        backends,
+                        info: compiled.info(),
+                        #(#backend_fields,)*
                     })
                 }
 
-                /// Start servers
+                /// Start servers, running until each has shut down.
                 /// N.B. for simplicity, all hosts are "servers" with a default
                 /// implementation the does nothing
                 async fn start(&self) -> Result<()> {
+                    self.shutdown.listen();
+
+                    let health_interval = self.options.backend.health_interval();
                     let futures: Vec<BoxFuture<'_, Result<()>>> = vec![
+                        Box::pin(async move {
+                            self.backends.monitor(health_interval, &self.shutdown).await;
+                            Ok(())
+                        }),
+                        Box::pin(kernel::serve_admin(self)),
                         #(Box::pin(#server_trait_impls.run(self)),)*
                     ];
-                    try_join_all(futures).await?;
-                    Ok(())
+                    let result = try_join_all(futures).await;
+
+                    kernel::flush_telemetry();
+                    result.map(|_| ())
                 }
             }
 
-            impl State for Context {
-                type StoreCtx = StoreCtx;
+            /// Backends already connected by the application embedding the
+            /// runtime.
+            #[derive(Default)]
+            struct Preconnected {
+                #(#backend_fields: Option<#backend_types>,)*
+            }
 
-                fn instance_pre(&self) -> &InstancePre<Self::StoreCtx> {
-                    &self.instance_pre
-                }
+            #store_ctx
+        }
 
-                fn store(&self) -> Self::StoreCtx {
-                    let wasi_ctx = WasiCtxBuilder::new()
-                        // .inherit_args()
-                        .inherit_env()
-                        .inherit_stdin()
-                        .stdout(tokio::io::stdout())
-                        .stderr(tokio::io::stderr())
-                        .build();
-
-                    StoreCtx {
-                        table: ResourceTable::new(),
-                        wasi: wasi_ctx,
-                        #(#store_ctx_values,)*
-                    }
-                }
+        #main_fn
+    }
+}
+
+/// Expands the functions implementing the runtime's commands.
+fn commands(
+    host_trait_impls: &[Type], host_wits: &[TokenStream], entry_attrs: &TokenStream,
+) -> TokenStream {
+    quote! {
+        /// Run the specified wasm guest (or manifest of guests) using the
+        /// configured runtime.
+        #entry_attrs
+        pub async fn run(wasm: PathBuf, options: RuntimeOptions) -> Result<()> {
+            let mut compiled = kernel::create(&wasm, &options)
+                .with_context(|| format!("compiling {}", wasm.display()))?;
+            let run_state = Context::new(&mut compiled)
+                .await
+                .context("preparing runtime state")?;
+            if options.watch {
+                kernel::watch(compiled, run_state.guests.clone(), run_state.shutdown.clone())
+                    .context("watching wasm guests")?;
+            }
+            run_state.start().await.context("starting runtime services")
+        }
+
+        /// Report the imports and exports of the specified wasm guest (or
+        /// manifest of guests), checking each import against the
+        /// runtime's hosts.
+        #entry_attrs
+        pub fn inspect(wasm: PathBuf, json: bool) -> Result<()> {
+            let manifest = Manifest::from_path(&wasm)?;
+            let mut compiled = kernel::load::<StoreCtx>(&manifest, &RuntimeOptions::default())
+                .with_context(|| format!("loading {}", wasm.display()))?;
+            #(compiled.link(#host_trait_impls)?;)*
+            compiled.inspect()?.report(json)
+        }
+
+        /// The WIT package describing the interfaces provided by the
+        /// runtime: each host's imports and the exports its servers call.
+        #entry_attrs
+        pub fn wit() -> kernel::WitPackage {
+            kernel::WitPackage::new("credibil:runtime", "runtime")#(.with(#host_wits))*
+        }
+    }
+}
+
+/// Expands the data held in each guest's `Store`, and the views through which
+/// hosts access it.
+fn store_ctx(
+    store_ctx_fields: &[TokenStream], store_ctx_values: &[TokenStream],
+    wasi_view_impls: &[TokenStream],
+) -> TokenStream {
+    quote! {
+        impl State for Context {
+            type StoreCtx = StoreCtx;
+
+            fn guests(&self) -> &Guests<Self::StoreCtx> {
+                &self.guests
             }
 
-            /// Per-guest instance data shared between the runtime and guest
-            pub struct StoreCtx {
-                pub table: ResourceTable,
-                pub wasi: WasiCtx,
-                #(pub #store_ctx_fields,)*
+            fn backends(&self) -> &Backends {
+                &self.backends
             }
 
-            /// WASI View Implementations
-            impl WasiView for StoreCtx {
-                fn ctx(&mut self) -> WasiCtxView<'_> {
-                    WasiCtxView {
-                        ctx: &mut self.wasi,
-                        table: &mut self.table,
-                    }
-                }
+            fn info(&self) -> &RuntimeInfo {
+                &self.info
+            }
+
+            fn options(&self) -> &RuntimeOptions {
+                &self.options
+            }
+
+            fn shutdown(&self) -> &Shutdown {
+                &self.shutdown
             }
 
-            #(#wasi_view_impls)*
+            async fn store(&self, guest: &Guest<Self::StoreCtx>) -> Result<Self::StoreCtx> {
+                Ok(StoreCtx {
+                    table: ResourceTable::new(),
+                    wasi: self.options.wasi.ctx(guest.name()),
+                    limiter: self.options.limiter(),
+                    capabilities: Arc::clone(guest.capabilities()),
+                    #(#store_ctx_values,)*
+                })
+            }
         }
 
-        /// Main function
-        #main_fn
+        /// Per-guest instance data shared between the runtime and guest
+        pub struct StoreCtx {
+            pub table: ResourceTable,
+            pub wasi: WasiCtx,
+            pub limiter: Limiter,
+            pub capabilities: Arc<Capabilities>,
+            #(pub #store_ctx_fields,)*
+        }
+
+        impl LimiterView for StoreCtx {
+            fn limiter(&mut self) -> &mut Limiter {
+                &mut self.limiter
+            }
+        }
+
+        /// WASI View Implementations
+        impl WasiView for StoreCtx {
+            fn ctx(&mut self) -> WasiCtxView<'_> {
+                WasiCtxView {
+                    ctx: &mut self.wasi,
+                    table: &mut self.table,
+                }
+            }
+        }
+
+        #(#wasi_view_impls)*
     }
 }
@@ -4,16 +4,22 @@ use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 use syn::{Ident, Type};
 
-use crate::runtime::RuntimeInput;
+use crate::runtime::{HostBackend, HostKind, Instance, RuntimeInput};
 
 pub struct Generated {
-    pub context_fields: Vec<TokenStream>,
+    pub backend_fields: Vec<Ident>,
+    pub backend_types: Vec<Type>,
+    pub backend_connections: Vec<TokenStream>,
     pub store_ctx_fields: Vec<TokenStream>,
     pub store_ctx_values: Vec<TokenStream>,
     pub host_trait_impls: Vec<Type>,
     pub server_trait_impls: Vec<TokenStream>,
     pub wasi_view_impls: Vec<TokenStream>,
+    pub host_wits: Vec<TokenStream>,
     pub main_fn: TokenStream,
+    pub harness_fn: TokenStream,
+    pub builder: TokenStream,
+    pub entry_attrs: TokenStream,
 }
 
 impl TryFrom<RuntimeInput> for Generated {
@@ -21,101 +27,414 @@ impl TryFrom<RuntimeInput> for Generated {
 
     fn try_from(input: RuntimeInput) -> Result<Self, Self::Error> {
         // `Context` struct
-        let mut context_fields = Vec::new();
-        let mut seen_backends = HashSet::new();
-
-        for backend in input.backends {
-            // Deduplicate backends based on their string representation
-            let backend_str = quote! {#backend}.to_string();
-            if seen_backends.contains(&backend_str) {
-                continue;
-            }
-            seen_backends.insert(backend_str);
-
-            let field = field_ident(&backend);
-            context_fields.push(quote! {#field: #backend});
-        }
+        let (mut backend_fields, mut backend_types, mut backend_connections) =
+            shared_backends(input.backends);
 
         let mut store_ctx_fields = Vec::new();
         let mut store_ctx_values = Vec::new();
         let mut host_trait_impls = Vec::new();
         let mut server_trait_impls = Vec::new();
         let mut wasi_view_impls = Vec::new();
+        let mut host_wits = Vec::new();
 
         for host in &input.hosts {
             let host_type = &host.type_;
-            let host_ident = wasi_ident(host_type);
-            let backend_type = &host.backend;
-            let backend_ident = field_ident(backend_type);
+            let paths = host_paths(host_type, &host.kind);
+            let host_ident = &paths.field;
 
             host_trait_impls.push(host_type.clone());
-            store_ctx_fields.push(quote! {#host_ident: #backend_type});
-            store_ctx_values.push(quote! {#host_ident: self.#backend_ident.clone()});
+            match &host.backend {
+                HostBackend::Single(backend_type) => {
+                    let backend_ident = field_ident(backend_type);
+                    store_ctx_fields.push(quote! {#host_ident: #backend_type});
+                    store_ctx_values.push(quote! {
+                        #host_ident: kernel::Backend::for_guest(
+                            self.#backend_ident.get().await?,
+                            guest.capabilities(),
+                        )
+                    });
+                }
+                HostBackend::Named(instances) => {
+                    // named backend instances are connected separately, each
+                    // with its own connection options
+                    for instance in instances {
+                        let field = instance_ident(host_ident, &instance.name.value());
+                        if backend_fields.contains(&field) {
+                            return Err(syn::Error::new(
+                                instance.name.span(),
+                                format!("backend name conflicts with `{field}`"),
+                            ));
+                        }
+                        let (name, backend) = (field.to_string(), &instance.backend);
+                        let instance_name = &instance.name;
+                        backend_connections.push(quote! {
+                            Connection::<#backend>::instance(#name, #instance_name, &options.backend)
+                        });
+                        backend_fields.push(field);
+                        backend_types.push(backend.clone());
+                    }
 
-            // servers
-            server_trait_impls.push(quote! {#host_type});
+                    // hosts without a context for named backends are
+                    // rejected when parsed
+                    let Some(ctx) = &paths.ctx else {
+                        return Err(syn::Error::new_spanned(
+                            host_type,
+                            "host does not support named backends",
+                        ));
+                    };
+                    let (field, value) = named_ctx(host_ident, ctx, instances);
+                    store_ctx_fields.push(field);
+                    store_ctx_values.push(value);
+                }
+            }
 
-            // WasiViewXxx implementations
-            // HACK: derive module name from WASI type
-            let module = wasi_ident(host_type);
-            let view = quote! {
-                #module::wasi_view!(StoreCtx, #host_ident);
-            };
-            wasi_view_impls.push(view);
+            // servers, views and WIT
+            server_trait_impls.push(quote! {#host_type});
+            wasi_view_impls.push(paths.view);
+            host_wits.push(paths.wit);
         }
 
-        // main function
-        let main_fn = if input.gen_main {
-            quote! {
-                use kernel::tokio;
-
-                #[tokio::main]
-                async fn main() -> anyhow::Result<()> {
-                    use kernel::Parser;
-                    match kernel::Cli::parse().command {
-                        kernel::Command::Run { wasm } => runtime::run(wasm).await,
-                        _ => unreachable!(),
-                    }
-                }
-            }
+        // test harness and builder, for runtimes used in tests or embedded in
+        // an application rather than as a binary
+        let (main_fn, harness_fn, builder, entry_attrs) = if input.gen_main {
+            (main_fn(), quote! {}, quote! {}, quote! {})
         } else {
-            quote! {}
+            // the application (or test) calls whichever entry points it needs
+            let entry_attrs = quote! {
+                #[allow(dead_code, reason = "embedding applications use a subset of entry points")]
+            };
+            let builder =
+                builder(&backend_fields, &backend_types, &server_trait_impls, &entry_attrs);
+            (quote! {}, harness_fn(&entry_attrs), builder, entry_attrs)
         };
 
         Ok(Self {
-            context_fields,
+            backend_fields,
+            backend_types,
+            backend_connections,
             store_ctx_fields,
             store_ctx_values,
             host_trait_impls,
             server_trait_impls,
             wasi_view_impls,
+            host_wits,
             main_fn,
+            harness_fn,
+            builder,
+            entry_attrs,
         })
     }
 }
 
+/// Generates the `main` function of a runtime built as a binary.
+fn main_fn() -> TokenStream {
+    quote! {
+        use kernel::tokio;
+
+        /// Main function
+        #[tokio::main]
+        async fn main() -> anyhow::Result<()> {
+            use kernel::Parser;
+            match kernel::Cli::parse().command {
+                kernel::Command::Run { wasm, options } => runtime::run(wasm, options).await,
+                kernel::Command::Inspect { wasm, json } => runtime::inspect(wasm, json),
+                kernel::Command::Wit { dir } => runtime::wit().write(&dir),
+                command => command.execute(),
+            }
+        }
+    }
+}
+
+/// Generates the `harness` function loading guests for testing.
+fn harness_fn(entry_attrs: &TokenStream) -> TokenStream {
+    quote! {
+        /// Load the wasm guest (or manifest of guests) and connect to
+        /// backends without starting the runtime's servers, so guests
+        /// can be invoked from tests.
+        #entry_attrs
+        pub async fn harness(
+            wasm: impl AsRef<std::path::Path>,
+        ) -> Result<kernel::testing::Harness<Context>> {
+            let wasm = wasm.as_ref();
+            let mut compiled =
+                kernel::testing::load::<StoreCtx>(wasm, &RuntimeOptions::default())
+                    .with_context(|| format!("loading {}", wasm.display()))?;
+            let state = Context::new(&mut compiled)
+                .await
+                .context("preparing runtime state")?;
+            Ok(kernel::testing::Harness::new(state))
+        }
+    }
+}
+
 /// Generates a field name for a backend type.
 fn field_ident(field_type: &Type) -> Ident {
     let type_str = quote! {#field_type}.to_string();
+    format_ident!("{}", snake_case(&type_str))
+}
 
-    // convert the type string to a snake_case
-    let mut field_str = String::new();
-    for char in type_str.chars() {
+/// Converts a type name to `snake_case`.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for char in name.chars() {
         if char.is_uppercase() {
-            if !field_str.is_empty() {
-                field_str.push('_');
+            if !snake.is_empty() {
+                snake.push('_');
             }
-            field_str.push_str(&char.to_lowercase().to_string());
+            snake.push_str(&char.to_lowercase().to_string());
         } else {
-            field_str.push(char);
+            snake.push(char);
+        }
+    }
+    snake
+}
+
+/// Generates the `Builder` used to embed the runtime in an application, and
+/// the means to start and stop each of the runtime's servers.
+fn builder(
+    backend_fields: &[Ident], backend_types: &[Type], server_trait_impls: &[TokenStream],
+    entry_attrs: &TokenStream,
+) -> TokenStream {
+    // servers are named for their host, e.g. `WasiHttp` or `acme_ledger::Host`
+    let server_names = server_trait_impls
+        .iter()
+        .map(|server| server.to_string().replace(' ', ""))
+        .collect::<Vec<_>>();
+
+    quote! {
+        /// Builds a runtime embedded in an existing tokio application, using
+        /// backends already connected by the application where provided.
+        #entry_attrs
+        #[derive(Default)]
+        pub struct Builder {
+            options: RuntimeOptions,
+            component: Option<kernel::ComponentSource>,
+            backends: Preconnected,
+        }
+
+        #entry_attrs
+        impl Builder {
+            /// Create a builder using the default runtime options.
+            #[must_use]
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            /// The options applied to the runtime and its guests.
+            #[must_use]
+            pub fn options(mut self, options: RuntimeOptions) -> Self {
+                self.options = options;
+                self
+            }
+
+            /// The component run by the runtime.
+            #[must_use]
+            pub fn component(mut self, component: kernel::ComponentSource) -> Self {
+                self.component = Some(component);
+                self
+            }
+
+            #(
+                /// Use the backend, already connected, rather than connecting.
+                #[must_use]
+                pub fn #backend_fields(mut self, backend: #backend_types) -> Self {
+                    self.backends.#backend_fields = Some(backend);
+                    self
+                }
+            )*
+
+            /// Load the component, link the runtime's hosts and connect to
+            /// backends not already connected. Servers are started using
+            /// [`Context::start_servers`].
+            pub async fn build(self) -> Result<Context> {
+                let component =
+                    self.component.ok_or_else(|| anyhow::anyhow!("no component to run"))?;
+                let mut compiled = kernel::embed::<StoreCtx>(component, &self.options)
+                    .context("loading component")?;
+                Context::connect(&mut compiled, self.backends)
+                    .await
+                    .context("preparing runtime state")
+            }
+        }
+
+        #entry_attrs
+        impl Context {
+            /// Start the named servers, or every server when `servers` is
+            /// empty, returning a handle to stop each. Servers are named for
+            /// their host (e.g. `WasiHttp`), or `admin` for the admin server.
+            /// Backend health is monitored until the runtime is stopped.
+            ///
+            /// Unlike `run`, the process's shutdown signals are left to the
+            /// application, which stops every server by triggering the
+            /// runtime's shutdown.
+            pub fn start_servers(&self, servers: &[&str]) -> Result<Vec<kernel::ServerHandle>> {
+                let names = [#(#server_names,)* "admin"];
+                if let Some(unknown) = servers.iter().find(|server| !names.contains(server)) {
+                    anyhow::bail!("unknown server `{unknown}`, expected one of {}", names.join(", "));
+                }
+                let selected = |name: &str| servers.is_empty() || servers.contains(&name);
+
+                let state = self.child();
+                let mut handles = vec![kernel::ServerHandle::spawn(
+                    "backends",
+                    state.shutdown.clone(),
+                    async move {
+                        let interval = state.options.backend.health_interval();
+                        state.backends.monitor(interval, &state.shutdown).await;
+                        Ok(())
+                    },
+                )];
+                if selected("admin") {
+                    let state = self.child();
+                    handles.push(kernel::ServerHandle::spawn(
+                        "admin",
+                        state.shutdown.clone(),
+                        async move { kernel::serve_admin(&state).await },
+                    ));
+                }
+                #(
+                    if selected(#server_names) {
+                        let state = self.child();
+                        handles.push(kernel::ServerHandle::spawn(
+                            #server_names,
+                            state.shutdown.clone(),
+                            async move { #server_trait_impls.run(&state).await },
+                        ));
+                    }
+                )*
+                Ok(handles)
+            }
+
+            // A copy of the state with its own shutdown signal, triggered
+            // with the runtime's.
+            fn child(&self) -> Self {
+                Self {
+                    shutdown: self.shutdown.child(),
+                    ..self.clone()
+                }
+            }
+        }
+    }
+}
+
+/// Generates the fields, types and connections of backends shared between
+/// hosts.
+fn shared_backends(backends: Vec<Type>) -> (Vec<Ident>, Vec<Type>, Vec<TokenStream>) {
+    let mut backend_fields = Vec::new();
+    let mut backend_types = Vec::new();
+    let mut backend_connections = Vec::new();
+    let mut seen_backends = HashSet::new();
+
+    for backend in backends {
+        // Deduplicate backends based on their string representation
+        let backend_str = quote! {#backend}.to_string();
+        if seen_backends.contains(&backend_str) {
+            continue;
         }
+        seen_backends.insert(backend_str);
+
+        let field = field_ident(&backend);
+        let name = field.to_string();
+        backend_connections.push(quote! {
+            Connection::<#backend>::new(#name, &options.backend)
+        });
+        backend_fields.push(field);
+        backend_types.push(backend);
     }
 
-    format_ident!("{field_str}")
+    (backend_fields, backend_types, backend_connections)
+}
+
+/// How the generated code refers to a host.
+struct HostPaths {
+    /// The `StoreCtx` field holding the host's context.
+    field: Ident,
+
+    /// The host's context type, for hosts supporting named backends.
+    ctx: Option<TokenStream>,
+
+    /// Implements the view through which the host accesses its context.
+    view: TokenStream,
+
+    /// The host's WIT.
+    wit: TokenStream,
+}
+
+/// Resolves the paths used for a host. Hosts outside this repo implement
+/// `kernel::WasiHost`, with `StoreCtx` implementing `kernel::HostView` for
+/// the host. Built-in `wasi-*` hosts implement their own view using the
+/// crate's `wasi_view!` macro.
+fn host_paths(host_type: &Type, kind: &HostKind) -> HostPaths {
+    match kind {
+        HostKind::BuiltIn { module, named_ctx } => HostPaths {
+            field: module.clone(),
+            ctx: named_ctx.as_ref().map(|ctx| quote! {dyn #module::#ctx}),
+            view: quote! {#module::wasi_view!(StoreCtx, #module);},
+            wit: quote! {#module::WIT},
+        },
+        HostKind::External => {
+            // the field is named for the host's path, e.g. `acme_ledger_host`
+            let field = match host_type {
+                Type::Path(type_path) => {
+                    let segments = type_path
+                        .path
+                        .segments
+                        .iter()
+                        .map(|segment| snake_case(&segment.ident.to_string()));
+                    format_ident!("{}", segments.collect::<Vec<_>>().join("_"))
+                }
+                _ => field_ident(host_type),
+            };
+            let view = quote! {
+                impl kernel::HostView<#host_type> for StoreCtx {
+                    fn host(&mut self) -> kernel::HostCtxView<'_, #host_type> {
+                        kernel::HostCtxView {
+                            ctx: &mut self.#field,
+                            table: &mut self.table,
+                            capabilities: &self.capabilities,
+                        }
+                    }
+                }
+            };
+            HostPaths {
+                field,
+                ctx: Some(quote! {<#host_type as kernel::WasiHost>::Ctx}),
+                view,
+                wit: quote! {<#host_type as kernel::WasiHost>::WIT},
+            }
+        }
+    }
+}
+
+/// Generates the `StoreCtx` field and value routing a host's resources to its
+/// named backend instances.
+fn named_ctx(
+    host_ident: &Ident, ctx_type: &TokenStream, instances: &[Instance],
+) -> (TokenStream, TokenStream) {
+    // the default instance is listed first
+    let names = instances.iter().map(|instance| &instance.name);
+    let ctxs = instances.iter().map(|instance| {
+        let field = instance_ident(host_ident, &instance.name.value());
+        quote! {
+            Arc::new(kernel::Backend::for_guest(
+                self.#field.get().await?,
+                guest.capabilities(),
+            )) as Arc<#ctx_type>
+        }
+    });
+    let mut entries = names.zip(ctxs).map(|(name, ctx)| quote! {#name, #ctx});
+    let default = entries.next();
+
+    let field = quote! {#host_ident: kernel::Named<#ctx_type>};
+    let value = quote! {#host_ident: kernel::Named::new(#default)#(.with(#entries))*};
+    (field, value)
 }
 
-fn wasi_ident(wasi_type: &Type) -> Ident {
-    let name = quote! {#wasi_type}.to_string();
-    let name = name.replace("Wasi", "wasi_").to_lowercase();
-    format_ident!("{name}")
+/// Generates a field name for a host's named backend instance.
+fn instance_ident(host_ident: &Ident, name: &str) -> Ident {
+    let name = name
+        .chars()
+        .map(|char| if char.is_ascii_alphanumeric() { char.to_ascii_lowercase() } else { '_' })
+        .collect::<String>();
+    format_ident!("{host_ident}_{name}")
 }
@@ -7,7 +7,7 @@
 //! ## Example Usage
 //!
 //! ```rust,ignore
-//! use fabric::{Body, Client, Headers};
+//! use fabric::{Body, Client, Headers, Router};
 //!
 //! // Create a client (typestate builder)
 //! let client = Client::new("alice").provider(provider);
@@ -17,18 +17,25 @@
 //!
 //! // Request with headers
 //! let response = client.request(my_request).headers(my_headers).await?;
+//!
+//! // Mount request types onto an HTTP router
+//! let router = Router::new(client).route::<MyRequest>(Method::POST, "/my-request");
 //! ```
 
 mod into_http;
+mod message_router;
 mod reply;
 mod request;
+mod router;
 
 use std::fmt::Debug;
 use std::sync::Arc;
 
 pub use self::into_http::*;
+pub use self::message_router::*;
 pub use self::reply::*;
 pub use self::request::*;
+pub use self::router::*;
 
 pub trait Provider: Send + Sync {}
 
@@ -103,6 +110,24 @@ pub trait Headers: Debug + Send + Sync {
 pub struct NoHeaders;
 impl Headers for NoHeaders {}
 
+/// Builds typed request [`Headers`] from the headers of an HTTP request. Used
+/// by [`Router`] to provide each handler with its headers.
+pub trait FromHeaders: Headers + Sized {
+    /// Build the typed headers from a concrete HTTP header map.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error (typically [`crate::Error::BadRequest`]) if a required
+    /// header is missing or invalid.
+    fn from_headers(headers: &http::HeaderMap) -> crate::Result<Self>;
+}
+
+impl FromHeaders for NoHeaders {
+    fn from_headers(_: &http::HeaderMap) -> crate::Result<Self> {
+        Ok(Self)
+    }
+}
+
 /// The `Body` trait is used to restrict the types able to implement
 /// request body. It is implemented by all `xxxRequest` types.
 pub trait Body: Debug + Send + Sync {}
@@ -0,0 +1,242 @@
+//! Typed message routing.
+//!
+//! [`MessageRouter`] routes messages received on a topic to a [`Handler`]
+//! request type. The message payload is decoded into the request type using
+//! the message's content type, and the handler's [`Reply`] body is returned as
+//! a JSON message for publishing to the message's reply topic (if any).
+//!
+//! ## Example Usage
+//!
+//! ```rust,ignore
+//! let router = MessageRouter::new(client)
+//!     .on::<OrderPlaced>("orders.v1")
+//!     .on::<OrderShipped>("shipments.v1");
+//!
+//! wasi_messaging::serve(&router, message).await
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::pin::Pin;
+use std::sync::Arc;
+
+use anyhow::Result;
+use http::header::CONTENT_TYPE;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::api::reply::Reply;
+use crate::api::request::Handler;
+use crate::api::{Body, Client, Provider};
+use crate::capabilities::Message;
+use crate::{Error, bad_request};
+
+type Route<P> = Box<
+    dyn Fn(&Client<Arc<P>>, &Message) -> Pin<Box<dyn Future<Output = Result<Message>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// Message router dispatching messages to [`Handler`] request types by topic.
+pub struct MessageRouter<P: Provider> {
+    client: Client<Arc<P>>,
+    routes: HashMap<String, Route<P>>,
+}
+
+impl<P: Provider + 'static> MessageRouter<P> {
+    /// Create a router handling messages with `client`.
+    #[must_use]
+    pub fn new(client: Client<Arc<P>>) -> Self {
+        Self {
+            client,
+            routes: HashMap::new(),
+        }
+    }
+
+    /// Route messages received on `topic` to the request type `R`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `topic` is already routed.
+    #[must_use]
+    pub fn on<R>(mut self, topic: impl Into<String>) -> Self
+    where
+        R: Handler<P> + DeserializeOwned + Send + 'static,
+        R::Output: Serialize,
+    {
+        let topic = topic.into();
+        let route: Route<P> = Box::new(|client, message| {
+            let request = decode::<R>(message);
+            let client = client.clone();
+            Box::pin(async move {
+                let reply = client.request(request?).await?;
+                into_message(&reply)
+            })
+        });
+        assert!(
+            self.routes.insert(topic.clone(), route).is_none(),
+            "topic `{topic}` is already routed"
+        );
+        self
+    }
+
+    /// The topics routed by the router.
+    pub fn topics(&self) -> impl Iterator<Item = &str> {
+        self.routes.keys().map(String::as_str)
+    }
+
+    /// Handle a message received on `topic`, returning the handler's reply.
+    /// Messages on topics without a route are ignored, returning `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message cannot be decoded into the topic's
+    /// request type, the handler fails, or the reply cannot be encoded.
+    pub async fn handle(&self, topic: &str, message: &Message) -> Result<Option<Message>> {
+        let Some(route) = self.routes.get(topic) else {
+            tracing::debug!("no route for topic: {topic}");
+            return Ok(None);
+        };
+        route(&self.client, message).await.map(Some)
+    }
+}
+
+impl<P: Provider> Debug for MessageRouter<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageRouter").field("topics", &self.routes.keys()).finish_non_exhaustive()
+    }
+}
+
+// Decode a message payload using its content type, defaulting to JSON. An
+// empty payload is decoded as `null`, so requests without fields can be
+// routed. Header names are matched case-insensitively, as message metadata
+// need not be lowercase.
+fn decode<R: DeserializeOwned>(message: &Message) -> Result<R, Error> {
+    let content_type = message
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(CONTENT_TYPE.as_str()))
+        .map(|(_, value)| value.as_str());
+    let essence = content_type.and_then(|ct| ct.split(';').next()).map(str::trim);
+    let payload = if message.payload.is_empty() { b"null".as_slice() } else { &message.payload };
+
+    match essence {
+        None | Some("application/json") => Ok(serde_json::from_slice(payload)?),
+        Some(ct) if ct.ends_with("+json") => Ok(serde_json::from_slice(payload)?),
+        Some("text/plain") => {
+            let text = String::from_utf8(message.payload.clone())
+                .map_err(|e| bad_request!("payload is not UTF-8: {e}"))?;
+            Ok(serde_json::from_value(serde_json::Value::String(text))?)
+        }
+        Some(ct) => Err(bad_request!("unsupported content type: {ct}")),
+    }
+}
+
+// Convert a reply into a JSON message. Reply headers are added to the
+// message's headers, while the status is discarded.
+fn into_message<B: Body + Serialize>(reply: &Reply<B>) -> Result<Message> {
+    let mut message = Message::new(&serde_json::to_vec(&reply.body)?);
+    for (name, value) in &reply.headers {
+        if let Ok(value) = value.to_str() {
+            message.headers.insert(name.to_string(), value.to_string());
+        }
+    }
+    message
+        .headers
+        .entry(CONTENT_TYPE.to_string())
+        .or_insert_with(|| "application/json".to_string());
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future;
+
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::api::{Context, Headers};
+
+    struct Mock;
+
+    #[derive(Debug, Deserialize)]
+    struct OrderPlaced {
+        id: String,
+    }
+
+    #[derive(Debug, Serialize)]
+    struct Receipt {
+        owner: String,
+        id: String,
+    }
+
+    impl Handler<Mock> for OrderPlaced {
+        type Error = Error;
+        type Output = Receipt;
+
+        fn handle<H: Headers>(
+            self, ctx: Context<'_, Mock, H>,
+        ) -> impl Future<Output = Result<Reply<Receipt>, Self::Error>> + Send {
+            future::ready(Ok(Reply::ok(Receipt {
+                owner: ctx.owner.to_string(),
+                id: self.id,
+            })))
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Note(String);
+
+    impl Handler<Mock> for Note {
+        type Error = Error;
+        type Output = String;
+
+        fn handle<H: Headers>(
+            self, _: Context<'_, Mock, H>,
+        ) -> impl Future<Output = Result<Reply<String>, Self::Error>> + Send {
+            future::ready(Ok(Reply::ok(self.0)))
+        }
+    }
+
+    fn message(payload: &str, content_type: Option<&str>) -> Message {
+        let mut message = Message::new(payload.as_bytes());
+        if let Some(content_type) = content_type {
+            message.headers.insert("content-type".to_string(), content_type.to_string());
+        }
+        message
+    }
+
+    #[tokio::test]
+    async fn route() {
+        let client = Client::new("alice").provider(Mock);
+        let router =
+            MessageRouter::new(client).on::<OrderPlaced>("orders.v1").on::<Note>("notes.v1");
+
+        let json = message(r#"{"id": "o-1"}"#, Some("application/json; charset=utf-8"));
+        let reply = router.handle("orders.v1", &json).await.expect("handle").expect("reply");
+        assert_eq!(reply.payload, br#"{"owner":"alice","id":"o-1"}"#);
+        assert_eq!(reply.headers["content-type"], "application/json");
+
+        // content type defaults to JSON
+        let untyped = message(r#"{"id": "o-2"}"#, None);
+        assert!(router.handle("orders.v1", &untyped).await.expect("handle").is_some());
+
+        // other topics are ignored
+        assert!(router.handle("orders.v2", &json).await.expect("handle").is_none());
+
+        let text = message("hello", Some("text/plain"));
+        let reply = router.handle("notes.v1", &text).await.expect("handle").expect("reply");
+        assert_eq!(reply.payload, br#""hello""#);
+        router.handle("orders.v1", &text).await.expect_err("text is not an order");
+
+        // header names are case-insensitive
+        let mut mixed = Message::new(b"hello");
+        mixed.headers.insert("Content-Type".to_string(), "text/plain".to_string());
+        let reply = router.handle("notes.v1", &mixed).await.expect("handle").expect("reply");
+        assert_eq!(reply.payload, br#""hello""#);
+
+        let xml = message("<id>o-4</id>", Some("application/xml"));
+        let err = router.handle("orders.v1", &xml).await.expect_err("unsupported");
+        assert!(err.to_string().contains("unsupported content type: application/xml"));
+    }
+}
@@ -0,0 +1,240 @@
+//! Typed HTTP routing.
+//!
+//! [`Router`] mounts [`Handler`] request types directly onto an axum router.
+//! Each route deserializes the JSON request body into the request type, builds
+//! the handler's typed [`Headers`] using [`FromHeaders`], and handles the
+//! request with the router's shared [`Client`]. The handler's [`Reply`] is
+//! returned as JSON, and errors are converted to a response using
+//! [`HttpError`].
+//!
+//! ## Example Usage
+//!
+//! ```rust,ignore
+//! let router = Router::new(client)
+//!     .route::<CreateOrder>(Method::POST, "/orders")
+//!     .route_with_headers::<GetOrder, TenantHeaders>(Method::GET, "/order");
+//!
+//! wasi_http::serve(router.into(), request).await
+//! ```
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{self, MethodFilter};
+use bytes::Bytes;
+use http::header::CONTENT_TYPE;
+use http::{HeaderMap, HeaderValue, Method};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::api::into_http::HttpError;
+use crate::api::reply::Reply;
+use crate::api::request::Handler;
+use crate::api::{Body, Client, FromHeaders, NoHeaders, Provider};
+
+/// HTTP router mounting [`Handler`] request types as routes.
+///
+/// Convert into an [`axum::Router`] to serve requests.
+#[derive(Debug)]
+pub struct Router<P: Provider> {
+    client: Client<Arc<P>>,
+    router: axum::Router,
+}
+
+impl<P: Provider + 'static> Router<P> {
+    /// Create a router handling requests with `client`.
+    #[must_use]
+    pub fn new(client: Client<Arc<P>>) -> Self {
+        Self {
+            client,
+            router: axum::Router::new(),
+        }
+    }
+
+    /// Route requests for `method` and `path` to the request type `R`,
+    /// without headers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `method` cannot be routed or the route conflicts with an
+    /// existing route.
+    #[must_use]
+    pub fn route<R>(self, method: Method, path: &str) -> Self
+    where
+        R: Handler<P> + DeserializeOwned + Send + 'static,
+        R::Output: Serialize,
+    {
+        self.route_with_headers::<R, NoHeaders>(method, path)
+    }
+
+    /// Route requests for `method` and `path` to the request type `R`, with
+    /// headers of type `H` built from the request's headers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `method` cannot be routed or the route conflicts with an
+    /// existing route.
+    #[must_use]
+    pub fn route_with_headers<R, H>(self, method: Method, path: &str) -> Self
+    where
+        R: Handler<P> + DeserializeOwned + Send + 'static,
+        R::Output: Serialize,
+        H: FromHeaders + 'static,
+    {
+        let filter = MethodFilter::try_from(method).unwrap_or_else(|e| panic!("{e}"));
+        let client = self.client.clone();
+        let handler = move |headers: HeaderMap, body: Bytes| {
+            let client = client.clone();
+            async move {
+                handle::<P, R, H>(client, &headers, &body)
+                    .await
+                    .unwrap_or_else(|e| HttpError::from(e).into_response())
+            }
+        };
+
+        Self {
+            client: self.client,
+            router: self.router.route(path, routing::on(filter, handler)),
+        }
+    }
+}
+
+impl<P: Provider> From<Router<P>> for axum::Router {
+    fn from(router: Router<P>) -> Self {
+        router.router
+    }
+}
+
+// Handle a request using the request type `R`. An empty body is
+// deserialized as `null`, so requests without fields can be routed.
+async fn handle<P, R, H>(
+    client: Client<Arc<P>>, headers: &HeaderMap, body: &Bytes,
+) -> Result<Response>
+where
+    P: Provider + 'static,
+    R: Handler<P> + DeserializeOwned + Send + 'static,
+    R::Output: Serialize,
+    H: FromHeaders + 'static,
+{
+    let headers = H::from_headers(headers)?;
+    let body = if body.is_empty() { b"null".as_slice() } else { body };
+    let request: R = serde_json::from_slice(body).map_err(crate::Error::from)?;
+    let reply = client.request(request).headers(headers).await?;
+    into_json(reply)
+}
+
+// Convert a reply into a JSON response.
+fn into_json<B: Body + Serialize>(reply: Reply<B>) -> Result<Response> {
+    let body = serde_json::to_vec(&reply.body)?;
+    let mut headers = reply.headers;
+    headers.entry(CONTENT_TYPE).or_insert(HeaderValue::from_static("application/json"));
+    Ok((reply.status, headers, body).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future;
+
+    use http::{Request, StatusCode};
+    use http_body_util::BodyExt;
+    use serde::Deserialize;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::api::{Context, Headers};
+    use crate::bad_request;
+
+    struct Mock;
+
+    #[derive(Debug)]
+    struct Tenant;
+
+    impl Headers for Tenant {}
+
+    impl FromHeaders for Tenant {
+        fn from_headers(headers: &HeaderMap) -> crate::Result<Self> {
+            if !headers.contains_key("x-tenant") {
+                return Err(bad_request!("missing x-tenant header"));
+            }
+            Ok(Self)
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct CreateOrder {
+        item: String,
+    }
+
+    #[derive(Debug, Serialize)]
+    struct Order {
+        owner: String,
+        item: String,
+    }
+
+    impl Handler<Mock> for CreateOrder {
+        type Error = crate::Error;
+        type Output = Order;
+
+        fn handle<H: Headers>(
+            self, ctx: Context<'_, Mock, H>,
+        ) -> impl Future<Output = Result<Reply<Order>, Self::Error>> + Send {
+            let reply = if self.item.is_empty() {
+                Err(bad_request!("no item"))
+            } else {
+                Ok(Reply::created(Order {
+                    owner: ctx.owner.to_string(),
+                    item: self.item,
+                }))
+            };
+            future::ready(reply)
+        }
+    }
+
+    async fn send(router: axum::Router, request: Request<String>) -> (StatusCode, String) {
+        let response = router.oneshot(request).await.expect("response");
+        let status = response.status();
+        let body = response.into_body().collect().await.expect("body").to_bytes();
+        (status, String::from_utf8(body.to_vec()).expect("utf8"))
+    }
+
+    #[tokio::test]
+    async fn route() {
+        let client = Client::new("alice").provider(Mock);
+        let router: axum::Router = Router::new(client)
+            .route::<CreateOrder>(Method::POST, "/orders")
+            .route_with_headers::<CreateOrder, Tenant>(Method::PUT, "/orders")
+            .into();
+
+        let request =
+            Request::post("/orders").body(r#"{"item": "tea"}"#.to_string()).expect("request");
+        let (status, body) = send(router.clone(), request).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body, r#"{"owner":"alice","item":"tea"}"#);
+
+        // handler errors are converted to responses
+        let request =
+            Request::post("/orders").body(r#"{"item": ""}"#.to_string()).expect("request");
+        let (status, _) = send(router.clone(), request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // as are invalid bodies and headers
+        let request =
+            Request::post("/orders").body(r#"{"items": []}"#.to_string()).expect("request");
+        let (status, _) = send(router.clone(), request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let request = Request::put("/orders")
+            .header("x-tenant", "acme")
+            .body(r#"{"item": "tea"}"#.to_string())
+            .expect("request");
+        let (status, _) = send(router.clone(), request).await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        let request =
+            Request::put("/orders").body(r#"{"item": "tea"}"#.to_string()).expect("request");
+        let (status, body) = send(router, request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("missing x-tenant header"));
+    }
+}
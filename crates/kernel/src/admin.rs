@@ -0,0 +1,327 @@
+//! # Admin Server
+//!
+//! A lightweight HTTP listener, on a port separate from the runtime's
+//! servers, used to probe the runtime without instantiating a guest:
+//!
+//! - `/healthz`: liveness, `200 OK` while the process is serving.
+//! - `/readyz`: readiness, `503 Service Unavailable` when a backend is
+//!   unhealthy or the runtime is shutting down.
+//! - `/info`: the runtime's components, their wasm digests, the linked
+//!   hosts and the runtime version, as JSON.
+//! - `/metrics`: runtime metrics in the Prometheus text format.
+//!
+//! The admin server is started when the runtime is run with `--admin-addr`.
+
+use std::convert::Infallible;
+use std::pin::pin;
+use std::sync::Arc;
+
+use anyhow::Result;
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::header::{CONTENT_TYPE, HeaderValue};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::Serialize;
+use tokio::net::TcpListener;
+
+use crate::backends::Health;
+use crate::metrics::metrics;
+use crate::traits::State;
+
+const PROMETHEUS_TEXT: &str = "text/plain; version=0.0.4";
+
+/// Information about the runtime reported by the admin server.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct RuntimeInfo {
+    /// The runtime's name (the manifest name).
+    pub name: String,
+
+    /// The runtime version.
+    pub version: String,
+
+    /// The WASI hosts linked to the runtime.
+    pub hosts: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Info<'a> {
+    #[serde(flatten)]
+    runtime: &'a RuntimeInfo,
+    components: Vec<ComponentInfo>,
+}
+
+#[derive(Serialize)]
+struct ComponentInfo {
+    name: String,
+    sha256: String,
+}
+
+/// Serve the admin endpoints until shutdown is triggered. Does nothing when
+/// no admin address is configured.
+///
+/// # Errors
+///
+/// Returns an error if the admin address cannot be bound.
+pub async fn serve_admin<S: State>(state: &S) -> Result<()> {
+    let Some(addr) = state.options().admin_addr else {
+        return Ok(());
+    };
+
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!("admin server listening on: {addr}");
+
+    let state = Arc::new(state.clone());
+    let shutdown = state.shutdown().clone();
+
+    loop {
+        let (stream, _) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            () = shutdown.triggered() => break,
+        };
+        let state = Arc::clone(&state);
+        let shutdown = shutdown.clone();
+
+        tokio::spawn(async move {
+            let service = service_fn(move |request| {
+                let response = respond(state.as_ref(), &request);
+                async move { Ok::<_, Infallible>(response) }
+            });
+            let connection = http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+            let mut connection = pin!(connection);
+
+            let result = tokio::select! {
+                result = connection.as_mut() => result,
+                () = shutdown.triggered() => {
+                    connection.as_mut().graceful_shutdown();
+                    connection.await
+                }
+            };
+            if let Err(e) = result {
+                tracing::debug!("admin connection error: {e}");
+            }
+        });
+    }
+
+    tracing::info!("admin server shutting down");
+    Ok(())
+}
+
+fn respond<S: State, B>(state: &S, request: &Request<B>) -> Response<Full<Bytes>> {
+    if request.method() != Method::GET {
+        return text(StatusCode::METHOD_NOT_ALLOWED, "method not allowed\n");
+    }
+
+    match request.uri().path() {
+        "/healthz" => text(StatusCode::OK, "ok\n"),
+        "/readyz" => readiness(state),
+        "/info" => info(state),
+        "/metrics" => {
+            let body = metrics().render(state.backends());
+            response(StatusCode::OK, PROMETHEUS_TEXT, body)
+        }
+        _ => text(StatusCode::NOT_FOUND, "not found\n"),
+    }
+}
+
+fn readiness<S: State>(state: &S) -> Response<Full<Bytes>> {
+    if state.shutdown().is_triggered() {
+        return text(StatusCode::SERVICE_UNAVAILABLE, "shutting down\n");
+    }
+    if state.backends().is_ready() {
+        return text(StatusCode::OK, "ready\n");
+    }
+
+    let unhealthy = state
+        .backends()
+        .health()
+        .into_iter()
+        .filter_map(|(name, health)| match health {
+            Health::Unhealthy(reason) => Some(format!("{name}: {reason}\n")),
+            Health::Pending | Health::Healthy => None,
+        })
+        .collect::<String>();
+    text(StatusCode::SERVICE_UNAVAILABLE, unhealthy)
+}
+
+fn info<S: State>(state: &S) -> Response<Full<Bytes>> {
+    let info = Info {
+        runtime: state.info(),
+        components: state
+            .guests()
+            .iter()
+            .map(|guest| ComponentInfo {
+                name: guest.name().to_string(),
+                sha256: guest.digest(),
+            })
+            .collect(),
+    };
+    match serde_json::to_string(&info) {
+        Ok(body) => response(StatusCode::OK, "application/json", body),
+        Err(e) => text(StatusCode::INTERNAL_SERVER_ERROR, format!("{e}\n")),
+    }
+}
+
+fn text(status: StatusCode, body: impl Into<Bytes>) -> Response<Full<Bytes>> {
+    response(status, "text/plain", body)
+}
+
+fn response(
+    status: StatusCode, content_type: &'static str, body: impl Into<Bytes>,
+) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(body.into()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future;
+
+    use anyhow::anyhow;
+    use http_body_util::BodyExt;
+    use wasmtime::Engine;
+    use wasmtime::component::{Component, Linker};
+
+    use super::*;
+    use crate::backends::{BackendOptions, Backends, Connection};
+    use crate::guests::{Guest, Guests, Routes};
+    use crate::limits::{Limiter, LimiterView};
+    use crate::options::RuntimeOptions;
+    use crate::shutdown::Shutdown;
+    use crate::traits::{Backend, FromEnv};
+
+    struct Ctx(Limiter);
+
+    impl LimiterView for Ctx {
+        fn limiter(&mut self) -> &mut Limiter {
+            &mut self.0
+        }
+    }
+
+    #[derive(Clone)]
+    struct Runtime {
+        guests: Guests<Ctx>,
+        backends: Backends,
+        info: RuntimeInfo,
+        options: RuntimeOptions,
+        shutdown: Shutdown,
+    }
+
+    impl State for Runtime {
+        type StoreCtx = Ctx;
+
+        fn store(&self, _: &Guest<Ctx>) -> impl Future<Output = Result<Ctx>> + Send {
+            future::ready(Ok(Ctx(self.options.limiter())))
+        }
+
+        fn guests(&self) -> &Guests<Ctx> {
+            &self.guests
+        }
+
+        fn backends(&self) -> &Backends {
+            &self.backends
+        }
+
+        fn info(&self) -> &RuntimeInfo {
+            &self.info
+        }
+
+        fn options(&self) -> &RuntimeOptions {
+            &self.options
+        }
+
+        fn shutdown(&self) -> &Shutdown {
+            &self.shutdown
+        }
+    }
+
+    struct Options;
+
+    impl FromEnv for Options {
+        fn from_env() -> Result<Self> {
+            Ok(Self)
+        }
+    }
+
+    // A backend that cannot be connected.
+    #[derive(Clone)]
+    struct Down;
+
+    impl Backend for Down {
+        type ConnectOptions = Options;
+
+        fn connect_with(_: Options) -> impl Future<Output = Result<Self>> + Send {
+            future::ready(Err(anyhow!("connection refused")))
+        }
+    }
+
+    fn runtime(backends: Backends) -> Runtime {
+        let engine = Engine::default();
+        let wasm = wat::parse_str("(component)").expect("should parse");
+        let component = Component::new(&engine, wasm).expect("should compile");
+        let instance_pre =
+            Linker::new(&engine).instantiate_pre(&component).expect("should pre-instantiate");
+        let guest = Guest::new("orders", instance_pre, "3a7bd3e2", Routes::default());
+
+        Runtime {
+            guests: Guests::new(vec![guest]).expect("should create guests"),
+            backends,
+            info: RuntimeInfo {
+                name: "orders".to_string(),
+                version: "0.1.0".to_string(),
+                hosts: vec!["WasiHttp".to_string()],
+            },
+            options: RuntimeOptions::default(),
+            shutdown: Shutdown::default(),
+        }
+    }
+
+    async fn get(runtime: &Runtime, path: &str) -> (StatusCode, String) {
+        let request = Request::get(path).body(()).expect("should build request");
+        let response = respond(runtime, &request);
+        let status = response.status();
+        let body = response.into_body().collect().await.expect("should read body").to_bytes();
+        (status, String::from_utf8(body.to_vec()).expect("should be UTF-8"))
+    }
+
+    #[tokio::test]
+    async fn readiness() {
+        let options = BackendOptions {
+            connect_retries: Some(0),
+            ..BackendOptions::default()
+        };
+        let connection = Connection::<Down>::new("ledger", &options);
+        let runtime = runtime(Backends::default().with(&connection));
+
+        // backends not yet connected do not affect readiness
+        assert_eq!(get(&runtime, "/readyz").await, (StatusCode::OK, "ready\n".to_string()));
+
+        connection.get().await.map(|_| ()).expect_err("should fail to connect");
+        let (status, body) = get(&runtime, "/readyz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(body.starts_with("ledger: "));
+
+        runtime.shutdown.trigger();
+        let (status, body) = get(&runtime, "/readyz").await;
+        assert_eq!((status, body.as_str()), (StatusCode::SERVICE_UNAVAILABLE, "shutting down\n"));
+    }
+
+    #[tokio::test]
+    async fn runtime_info() {
+        let runtime = runtime(Backends::default());
+
+        let (status, body) = get(&runtime, "/info").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            r#"{"name":"orders","version":"0.1.0","hosts":["WasiHttp"],"components":[{"name":"orders","sha256":"3a7bd3e2"}]}"#
+        );
+
+        let (status, _) = get(&runtime, "/unknown").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
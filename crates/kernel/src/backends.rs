@@ -0,0 +1,589 @@
+//! # Backend Connections
+//!
+//! Manages the runtime's connections to backend resources. Connection
+//! attempts are retried with exponential backoff, so a transient failure
+//! does not prevent the runtime from starting, and backends can optionally
+//! be connected lazily, on first use.
+//!
+//! Once connected, each backend's health is checked periodically. An
+//! unhealthy backend is reported (and the runtime reported as not ready)
+//! while reconnection is attempted in the background.
+//!
+//! A host can use several instances of a backend, each connected
+//! separately, routing the resources a guest opens to an instance by name.
+
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::Duration;
+
+use anyhow::Result;
+use clap::Args;
+use futures::FutureExt;
+use futures::future::{BoxFuture, join_all, try_join_all};
+
+use crate::shutdown::Shutdown;
+use crate::traits::Backend;
+
+/// The default number of times a failed connection attempt is retried.
+pub const DEFAULT_CONNECT_RETRIES: u32 = 5;
+
+/// The default delay before the first connection retry. The delay doubles
+/// with each subsequent retry.
+pub const DEFAULT_CONNECT_BACKOFF: Duration = Duration::from_millis(200);
+
+/// The default maximum delay between connection retries.
+pub const DEFAULT_CONNECT_BACKOFF_MAX: Duration = Duration::from_secs(10);
+
+/// The default interval at which backend health is checked.
+pub const DEFAULT_HEALTH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Backend connection options.
+#[derive(Args, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BackendOptions {
+    /// The number of times a failed backend connection attempt is retried
+    /// before giving up. Defaults to 5.
+    #[arg(long, env = "CONNECT_RETRIES")]
+    pub connect_retries: Option<u32>,
+
+    /// The delay, in milliseconds, before the first connection retry. The
+    /// delay doubles with each subsequent retry. Defaults to 200ms.
+    #[arg(long, env = "CONNECT_BACKOFF_MS")]
+    pub connect_backoff_ms: Option<u64>,
+
+    /// The maximum delay, in milliseconds, between connection retries.
+    /// Defaults to 10 seconds.
+    #[arg(long, env = "CONNECT_BACKOFF_MAX_MS")]
+    pub connect_backoff_max_ms: Option<u64>,
+
+    /// Connect to backends on first use rather than when the runtime starts.
+    #[arg(long, env = "LAZY_CONNECT")]
+    pub lazy_connect: bool,
+
+    /// The interval, in milliseconds, at which backend health is checked.
+    /// Defaults to 10 seconds.
+    #[arg(long, env = "HEALTH_INTERVAL_MS")]
+    pub health_interval_ms: Option<u64>,
+}
+
+impl BackendOptions {
+    /// The runtime's connection retry policy.
+    #[must_use]
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.connect_retries.unwrap_or(DEFAULT_CONNECT_RETRIES),
+            backoff: self.connect_backoff_ms.map_or(DEFAULT_CONNECT_BACKOFF, Duration::from_millis),
+            max_backoff: self
+                .connect_backoff_max_ms
+                .map_or(DEFAULT_CONNECT_BACKOFF_MAX, Duration::from_millis),
+        }
+    }
+
+    /// The interval at which backend health is checked.
+    #[must_use]
+    pub fn health_interval(&self) -> Duration {
+        self.health_interval_ms.map_or(DEFAULT_HEALTH_INTERVAL, Duration::from_millis)
+    }
+}
+
+/// How failed connection attempts are retried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The number of times a failed attempt is retried.
+    pub max_retries: u32,
+
+    /// The delay before the first retry.
+    pub backoff: Duration,
+
+    /// The maximum delay between retries.
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// A policy that does not retry.
+    #[must_use]
+    pub const fn none() -> Self {
+        Self {
+            max_retries: 0,
+            backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        }
+    }
+
+    /// The delay before retrying a failed attempt, where `attempt` is the
+    /// number of attempts already retried. Returns `None` once retries are
+    /// exhausted.
+    #[must_use]
+    pub fn delay(&self, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+        let factor = 2_u32.saturating_pow(attempt);
+        Some(self.backoff.saturating_mul(factor).min(self.max_backoff))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        BackendOptions::default().retry_policy()
+    }
+}
+
+/// The health of a backend connection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Health {
+    /// Not yet connected.
+    Pending,
+
+    /// Connected and healthy.
+    Healthy,
+
+    /// Connecting failed or the last health check failed.
+    Unhealthy(String),
+}
+
+/// A connection to a backend, established when the runtime starts or on
+/// first use, and re-established when the backend becomes unhealthy.
+pub struct Connection<B: Backend> {
+    inner: Arc<Inner<B>>,
+}
+
+struct Inner<B> {
+    name: String,
+    instance: Option<String>,
+    policy: RetryPolicy,
+    backend: RwLock<Option<B>>,
+    connecting: tokio::sync::Mutex<()>,
+    health: Mutex<Health>,
+}
+
+impl<B: Backend + Clone + 'static> Connection<B> {
+    /// Create an unconnected connection to the backend, identified by
+    /// `name` in logs and health reports.
+    #[must_use]
+    pub fn new(name: impl Into<String>, options: &BackendOptions) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                name: name.into(),
+                instance: None,
+                policy: B::retry_policy(options.retry_policy()),
+                backend: RwLock::new(None),
+                connecting: tokio::sync::Mutex::new(()),
+                health: Mutex::new(Health::Pending),
+            }),
+        }
+    }
+
+    /// Create an unconnected connection to the backend instance named
+    /// `instance`, connected using the instance's options (see
+    /// [`Backend::connect_instance`]).
+    #[must_use]
+    pub fn instance(
+        name: impl Into<String>, instance: impl Into<String>, options: &BackendOptions,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                name: name.into(),
+                instance: Some(instance.into()),
+                policy: B::retry_policy(options.retry_policy()),
+                backend: RwLock::new(None),
+                connecting: tokio::sync::Mutex::new(()),
+                health: Mutex::new(Health::Pending),
+            }),
+        }
+    }
+
+    /// The name identifying the backend.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// The backend's current health.
+    #[must_use]
+    pub fn health(&self) -> Health {
+        self.inner.health.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// The connected backend, connecting first if the backend is not yet
+    /// connected. Concurrent callers wait for a single connection attempt,
+    /// while callers of a connected backend never wait.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the backend cannot be connected once retries are
+    /// exhausted.
+    pub async fn get(&self) -> Result<B> {
+        if let Some(backend) = self.current() {
+            return Ok(backend);
+        }
+
+        let connecting = self.inner.connecting.lock().await;
+        // connected by another caller while waiting
+        if let Some(backend) = self.current() {
+            return Ok(backend);
+        }
+        let connected = self.connect().await?;
+        self.replace(connected.clone());
+        drop(connecting);
+        Ok(connected)
+    }
+
+    /// Use `backend`, already connected by the caller, rather than
+    /// connecting. The backend is reconnected as usual should it become
+    /// unhealthy.
+    pub async fn set(&self, backend: B) {
+        let connecting = self.inner.connecting.lock().await;
+        self.replace(backend);
+        self.set_health(Health::Healthy);
+        drop(connecting);
+    }
+
+    /// Check the backend's health, attempting to reconnect when it is
+    /// unhealthy.
+    pub async fn check(&self) {
+        let Some(backend) = self.current() else {
+            // not yet connected backends are connected on first use, unless
+            // an earlier attempt failed
+            if matches!(self.health(), Health::Unhealthy(_)) {
+                self.reconnect().await;
+            }
+            return;
+        };
+
+        match backend.health().await {
+            Ok(()) => {
+                if matches!(self.health(), Health::Unhealthy(_)) {
+                    tracing::info!("{} is healthy", self.name());
+                }
+                self.set_health(Health::Healthy);
+            }
+            Err(e) => {
+                tracing::warn!("{} is unhealthy: {e:#}", self.name());
+                self.set_health(Health::Unhealthy(format!("{e:#}")));
+                self.reconnect().await;
+            }
+        }
+    }
+
+    // Connect, retrying failed attempts using the backend's retry policy.
+    async fn connect(&self) -> Result<B> {
+        let mut attempt = 0;
+        loop {
+            match self.attempt().await {
+                Ok(backend) => {
+                    self.set_health(Health::Healthy);
+                    return Ok(backend);
+                }
+                Err(e) => {
+                    self.set_health(Health::Unhealthy(format!("{e:#}")));
+                    let Some(delay) = self.inner.policy.delay(attempt) else {
+                        return Err(e.context(format!("connecting to {}", self.name())));
+                    };
+                    tracing::warn!(
+                        "failed to connect to {}, retrying in {}ms: {e:#}",
+                        self.name(),
+                        delay.as_millis()
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    // Make a single attempt to replace the current connection. Invocations
+    // already using the previous connection are unaffected.
+    async fn reconnect(&self) {
+        match self.attempt().await {
+            Ok(backend) => {
+                self.replace(backend);
+                self.set_health(Health::Healthy);
+                tracing::info!("reconnected to {}", self.name());
+            }
+            Err(e) => {
+                tracing::warn!("failed to reconnect to {}: {e:#}", self.name());
+                self.set_health(Health::Unhealthy(format!("{e:#}")));
+            }
+        }
+    }
+
+    // Make a single connection attempt.
+    async fn attempt(&self) -> Result<B> {
+        match &self.inner.instance {
+            Some(instance) => B::connect_instance(instance).await,
+            None => B::connect().await,
+        }
+    }
+
+    fn current(&self) -> Option<B> {
+        self.inner.backend.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    fn replace(&self, backend: B) {
+        *self.inner.backend.write().unwrap_or_else(PoisonError::into_inner) = Some(backend);
+    }
+
+    fn set_health(&self, health: Health) {
+        *self.inner.health.lock().unwrap_or_else(PoisonError::into_inner) = health;
+    }
+}
+
+impl<B: Backend> Clone for Connection<B> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+// Type-erased access to a `Connection`.
+trait Monitored: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn health(&self) -> Health;
+
+    fn connect(&self) -> BoxFuture<'_, Result<()>>;
+
+    fn check(&self) -> BoxFuture<'_, ()>;
+}
+
+impl<B: Backend + Clone + 'static> Monitored for Connection<B> {
+    fn name(&self) -> &str {
+        Self::name(self)
+    }
+
+    fn health(&self) -> Health {
+        Self::health(self)
+    }
+
+    fn connect(&self) -> BoxFuture<'_, Result<()>> {
+        self.get().map(|result| result.map(|_| ())).boxed()
+    }
+
+    fn check(&self) -> BoxFuture<'_, ()> {
+        Self::check(self).boxed()
+    }
+}
+
+/// The runtime's backend connections, used to connect backends and report
+/// the runtime's readiness.
+#[derive(Clone, Default)]
+pub struct Backends {
+    connections: Vec<Arc<dyn Monitored>>,
+}
+
+impl Backends {
+    /// Add a connection to the set monitored.
+    #[must_use]
+    pub fn with<B: Backend + Clone + 'static>(mut self, connection: &Connection<B>) -> Self {
+        self.connections.push(Arc::new(connection.clone()));
+        self
+    }
+
+    /// Connect to all backends concurrently.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any backend cannot be connected once retries are
+    /// exhausted.
+    pub async fn connect(&self) -> Result<()> {
+        try_join_all(self.connections.iter().map(|connection| connection.connect())).await?;
+        Ok(())
+    }
+
+    /// The health of each backend, by name.
+    #[must_use]
+    pub fn health(&self) -> Vec<(String, Health)> {
+        self.connections
+            .iter()
+            .map(|connection| (connection.name().to_string(), connection.health()))
+            .collect()
+    }
+
+    /// Returns `true` when no backend is unhealthy. Backends not yet
+    /// connected are connected on first use, so do not affect readiness.
+    #[must_use]
+    pub fn is_ready(&self) -> bool {
+        self.connections
+            .iter()
+            .all(|connection| !matches!(connection.health(), Health::Unhealthy(_)))
+    }
+
+    /// Check the health of each backend every `interval`, reconnecting
+    /// unhealthy backends, until shutdown is triggered.
+    pub async fn monitor(&self, interval: Duration, shutdown: &Shutdown) {
+        if self.connections.is_empty() {
+            return;
+        }
+        loop {
+            tokio::select! {
+                () = tokio::time::sleep(interval) => {}
+                () = shutdown.triggered() => return,
+            }
+            join_all(self.connections.iter().map(|connection| connection.check())).await;
+        }
+    }
+}
+
+/// Several named instances of a host's context.
+///
+/// Hosts route the resources a guest opens to the instance with the
+/// resource's name. Resources without a matching instance use the default
+/// instance.
+#[derive(Debug)]
+pub struct Named<C: ?Sized> {
+    default: String,
+    instances: Vec<(String, Arc<C>)>,
+}
+
+impl<C: ?Sized> Named<C> {
+    /// Create a set of instances with the default instance, named `name`.
+    #[must_use]
+    pub fn new(name: impl Into<String>, default: Arc<C>) -> Self {
+        let name = name.into();
+        Self {
+            instances: vec![(name.clone(), default)],
+            default: name,
+        }
+    }
+
+    /// Add the instance named `name`.
+    #[must_use]
+    pub fn with(mut self, name: impl Into<String>, instance: Arc<C>) -> Self {
+        self.instances.push((name.into(), instance));
+        self
+    }
+
+    /// The name of the default instance.
+    #[must_use]
+    pub fn default_name(&self) -> &str {
+        &self.default
+    }
+
+    /// The instance for the resource named `name`, falling back to the
+    /// default instance.
+    #[must_use]
+    pub fn get(&self, name: &str) -> &Arc<C> {
+        let found = self.instances.iter().find(|(instance, _)| instance == name);
+        let (_, instance) = found.unwrap_or(&self.instances[0]);
+        instance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+    use anyhow::anyhow;
+
+    use super::*;
+    use crate::traits::FromEnv;
+
+    static FAILURES: AtomicU32 = AtomicU32::new(0);
+    static CONNECTIONS: AtomicU32 = AtomicU32::new(0);
+    static HEALTHY: AtomicBool = AtomicBool::new(true);
+
+    struct Options;
+
+    impl FromEnv for Options {
+        fn from_env() -> Result<Self> {
+            Ok(Self)
+        }
+    }
+
+    // A backend that fails the next `FAILURES` connection attempts, and is
+    // healthy while `HEALTHY` is set. Each connection is numbered.
+    #[derive(Clone)]
+    struct Flaky(u32);
+
+    impl Backend for Flaky {
+        type ConnectOptions = Options;
+
+        fn connect_with(_: Options) -> impl Future<Output = Result<Self>> + Send {
+            let failures = FAILURES.load(Ordering::SeqCst);
+            let result = if failures > 0 {
+                FAILURES.store(failures - 1, Ordering::SeqCst);
+                Err(anyhow!("connection refused"))
+            } else {
+                Ok(Self(CONNECTIONS.fetch_add(1, Ordering::SeqCst) + 1))
+            };
+            future::ready(result)
+        }
+
+        fn health(&self) -> impl Future<Output = Result<()>> + Send {
+            let healthy = HEALTHY.load(Ordering::SeqCst);
+            future::ready(if healthy { Ok(()) } else { Err(anyhow!("connection lost")) })
+        }
+    }
+
+    #[tokio::test]
+    async fn connect_and_reconnect() {
+        let options = BackendOptions {
+            connect_retries: Some(2),
+            connect_backoff_ms: Some(1),
+            ..BackendOptions::default()
+        };
+        let connection = Connection::<Flaky>::new("flaky", &options);
+        let backends = Backends::default().with(&connection);
+
+        // connected lazily, on first use
+        connection.check().await;
+        assert_eq!(connection.health(), Health::Pending);
+        assert!(backends.is_ready());
+
+        // failed attempts are retried
+        FAILURES.store(2, Ordering::SeqCst);
+        assert_eq!(connection.get().await.expect("should connect").0, 1);
+        assert_eq!(connection.health(), Health::Healthy);
+        assert_eq!(connection.get().await.expect("should be connected").0, 1);
+
+        // an unhealthy backend is kept until reconnected
+        HEALTHY.store(false, Ordering::SeqCst);
+        FAILURES.store(1, Ordering::SeqCst);
+        connection.check().await;
+        assert!(matches!(connection.health(), Health::Unhealthy(_)));
+        assert!(!backends.is_ready());
+        assert_eq!(connection.get().await.expect("should be connected").0, 1);
+
+        connection.check().await;
+        assert_eq!(connection.health(), Health::Healthy);
+        assert_eq!(connection.get().await.expect("should be reconnected").0, 2);
+
+        // retries are exhausted
+        FAILURES.store(3, Ordering::SeqCst);
+        let connection = Connection::<Flaky>::new("flaky", &options);
+        connection.get().await.map(|_| ()).expect_err("should fail to connect");
+        assert!(matches!(connection.health(), Health::Unhealthy(_)));
+    }
+
+    #[test]
+    fn retry_delay() {
+        let policy = RetryPolicy {
+            max_retries: 4,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+        };
+        let delays = (0..5).map(|attempt| policy.delay(attempt)).collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [
+                Some(Duration::from_millis(100)),
+                Some(Duration::from_millis(200)),
+                Some(Duration::from_millis(400)),
+                Some(Duration::from_millis(500)),
+                None,
+            ]
+        );
+        assert_eq!(RetryPolicy::none().delay(0), None);
+    }
+
+    #[test]
+    fn named_instances() {
+        let named =
+            Named::<str>::new("cache", Arc::from("memory")).with("state", Arc::from("redis"));
+        assert_eq!(named.default_name(), "cache");
+        assert_eq!(&**named.get("cache"), "memory");
+        assert_eq!(&**named.get("state"), "redis");
+        assert_eq!(&**named.get("orders"), "memory");
+    }
+}
@@ -0,0 +1,310 @@
+//! # Compile Cache
+//!
+//! A content-addressed cache of compiled components. Entries are keyed by a
+//! hash of the component's bytes and the engine's compatibility hash (which
+//! covers the engine's compilation settings and the wasmtime version), so a
+//! cached artifact is only reused by an engine able to run it.
+
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use serde::{Deserialize, Serialize};
+
+/// Compile cache options.
+#[derive(Args, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheOptions {
+    /// The directory used to cache compiled components. Defaults to
+    /// `$XDG_CACHE_HOME/wrt` or `$HOME/.cache/wrt`.
+    #[arg(long, env = "CACHE_DIR")]
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl CacheOptions {
+    /// The compile cache, if a cache directory is configured or can be
+    /// derived from the environment.
+    #[must_use]
+    pub fn cache(&self) -> Option<Cache> {
+        let dir = self.cache_dir.clone().or_else(|| {
+            let cache_home = env::var_os("XDG_CACHE_HOME")
+                .map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+            Some(cache_home.join("wrt"))
+        })?;
+        Some(Cache::new(dir))
+    }
+}
+
+/// Compile cache commands.
+#[derive(Subcommand, Clone, Debug, PartialEq, Eq)]
+pub enum CacheCommand {
+    /// List cached components.
+    List {
+        /// Cache options.
+        #[command(flatten)]
+        options: CacheOptions,
+    },
+    /// Remove all cached components.
+    Clean {
+        /// Cache options.
+        #[command(flatten)]
+        options: CacheOptions,
+    },
+}
+
+impl CacheCommand {
+    /// Execute the command, writing its output to stdout.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cache directory cannot be read or cleaned.
+    pub fn execute(&self) -> Result<()> {
+        let (Self::List { options } | Self::Clean { options }) = self;
+        let Some(cache) = options.cache() else {
+            println!("no cache directory configured");
+            return Ok(());
+        };
+
+        match self {
+            Self::List { .. } => {
+                for entry in cache.entries()? {
+                    let key = entry.key.get(..16).unwrap_or(&entry.key);
+                    println!("{key}  {:>10}  {}", entry.size, entry.source.display());
+                }
+            }
+            Self::Clean { .. } => {
+                let removed = cache.clean()?;
+                println!("removed {removed} cached component(s) from {}", cache.dir.display());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A cached component.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    /// The cache key.
+    pub key: String,
+
+    /// The wasm file the component was compiled from.
+    pub source: PathBuf,
+
+    /// The size, in bytes, of the compiled component.
+    pub size: u64,
+
+    /// When the component was compiled, in seconds since the Unix epoch.
+    pub created: u64,
+}
+
+/// A directory of compiled components.
+#[derive(Clone, Debug)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// Create a cache using the specified directory.
+    #[must_use]
+    pub const fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The cache directory.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Load the component compiled from `bytes`, read from `wasm`, compiling
+    /// and caching it if there is no compatible cached artifact.
+    ///
+    /// Cached artifacts are not signed, so should not be loaded when
+    /// components are required to be signed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the component cannot be compiled. Failing to read
+    /// or write the cache is logged rather than returned.
+    #[cfg(feature = "jit")]
+    pub fn load(
+        &self, engine: &wasmtime::Engine, wasm: &Path, bytes: &[u8],
+    ) -> Result<wasmtime::component::Component> {
+        use wasmtime::component::Component;
+
+        let key = key(engine, bytes);
+        let artifact = self.artifact(&key);
+
+        if artifact.exists() {
+            // SAFETY: artifacts are written by `Cache::store` from components
+            // compiled by this runtime. The key includes the engine's
+            // compatibility hash, and wasmtime validates the artifact's
+            // header against the engine before loading it.
+            match unsafe { Component::deserialize_file(engine, &artifact) } {
+                Ok(component) => {
+                    tracing::debug!("loaded {} from compile cache", wasm.display());
+                    return Ok(component);
+                }
+                Err(e) => {
+                    tracing::warn!("discarding incompatible cached component {key}: {e}");
+                }
+            }
+        }
+
+        tracing::info!("compiling {}", wasm.display());
+        let component = Component::new(engine, bytes)?;
+        if let Err(e) = self.store(&key, wasm, &component.serialize()?) {
+            tracing::warn!("failed to cache compiled component: {e}");
+        }
+        Ok(component)
+    }
+
+    /// List cached components, most recently created first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cache directory cannot be read.
+    pub fn entries(&self) -> Result<Vec<Entry>> {
+        if !self.dir.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut entries = Vec::new();
+        for file in fs::read_dir(&self.dir)? {
+            let path = file?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let entry = fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|metadata| Ok(serde_json::from_slice::<Entry>(&metadata)?));
+            match entry {
+                Ok(entry) => entries.push(entry),
+                Err(e) => tracing::warn!("skipping invalid cache entry {}: {e}", path.display()),
+            }
+        }
+        entries.sort_by_key(|entry| Reverse(entry.created));
+
+        Ok(entries)
+    }
+
+    /// Remove all cached components, returning the number removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if cached files cannot be removed.
+    pub fn clean(&self) -> Result<usize> {
+        if !self.dir.exists() {
+            return Ok(0);
+        }
+
+        let mut removed = 0;
+        for file in fs::read_dir(&self.dir)? {
+            let path = file?.path();
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("bin") => {
+                    fs::remove_file(&path)?;
+                    removed += 1;
+                }
+                Some("json" | "tmp") => fs::remove_file(&path)?,
+                _ => {}
+            }
+        }
+
+        Ok(removed)
+    }
+
+    // Write the artifact and its metadata. Files are written to a temporary
+    // path then renamed so concurrent runtimes never read a partial artifact.
+    #[cfg(feature = "jit")]
+    fn store(&self, key: &str, wasm: &Path, serialized: &[u8]) -> Result<()> {
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        fs::create_dir_all(&self.dir)?;
+
+        let entry = Entry {
+            key: key.to_string(),
+            source: wasm.canonicalize().unwrap_or_else(|_| wasm.to_path_buf()),
+            size: serialized.len() as u64,
+            created: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+        };
+
+        write(&self.artifact(key), serialized)?;
+        write(&self.dir.join(format!("{key}.json")), &serde_json::to_vec_pretty(&entry)?)
+    }
+
+    #[cfg(feature = "jit")]
+    fn artifact(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.bin"))
+    }
+}
+
+#[cfg(feature = "jit")]
+fn write(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+// The cache key: a SHA-256 hash of the engine's compatibility hash and the
+// component's bytes.
+#[cfg(feature = "jit")]
+fn key(engine: &wasmtime::Engine, wasm: &[u8]) -> String {
+    use std::hash::{Hash, Hasher};
+
+    use sha2::{Digest, Sha256};
+
+    struct Sha256Hasher(Sha256);
+
+    impl Hasher for Sha256Hasher {
+        fn finish(&self) -> u64 {
+            unreachable!("only the SHA-256 digest is used")
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            self.0.update(bytes);
+        }
+    }
+
+    let mut hasher = Sha256Hasher(Sha256::new());
+    engine.precompile_compatibility_hash().hash(&mut hasher);
+    hasher.0.update(wasm);
+    format!("{:x}", hasher.0.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_and_clean() {
+        let dir = env::temp_dir().join(format!("wrt-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("should create cache dir");
+
+        let entry = Entry {
+            key: "0123456789abcdef0123".into(),
+            source: "guest.wasm".into(),
+            size: 4,
+            created: 1,
+        };
+        fs::write(dir.join("0123456789abcdef0123.bin"), b"test").expect("should write artifact");
+        fs::write(
+            dir.join("0123456789abcdef0123.json"),
+            serde_json::to_vec(&entry).expect("should serialize entry"),
+        )
+        .expect("should write metadata");
+
+        let cache = Cache::new(dir.clone());
+        let entries = cache.entries().expect("should list entries");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].source, PathBuf::from("guest.wasm"));
+
+        assert_eq!(cache.clean().expect("should clean cache"), 1);
+        assert!(cache.entries().expect("should list entries").is_empty());
+
+        fs::remove_dir_all(dir).expect("should remove cache dir");
+    }
+}
@@ -0,0 +1,282 @@
+//! # Capability Policy
+//!
+//! A policy file lists, for each component, the interfaces it may import and
+//! the named resources (buckets, lockers, containers, databases, topics and
+//! outgoing HTTP hosts) it may open. A component importing an interface the
+//! policy does not allow fails to load. Opening a resource the policy does
+//! not allow fails with the interface's permission error, and is logged.
+//!
+//! ```json
+//! {
+//!     "components": {
+//!         "api": {
+//!             "imports": ["wasi:cli/*", "wasi:io/*", "wasi:http/*", "wasi:keyvalue/*"],
+//!             "buckets": ["orders", "cache-*"],
+//!             "hosts": ["*.stripe.com"]
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Names are matched exactly, or by a pattern with a leading or trailing `*`
+//! matching any suffix or prefix. Imports are matched without their version.
+//! Every component must be listed, and a component is only granted what is
+//! listed for it. Without a policy, components are unrestricted.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use wasmtime::Engine;
+use wasmtime::component::Component;
+
+/// The capabilities granted to each component.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    /// The grants for each component, by component name.
+    pub components: BTreeMap<String, Grants>,
+}
+
+impl Policy {
+    /// Load the policy at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the policy cannot be read or is invalid.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let contents =
+            fs::read(path).with_context(|| format!("reading policy {}", path.display()))?;
+        serde_json::from_slice(&contents)
+            .with_context(|| format!("parsing policy {}", path.display()))
+    }
+
+    /// The capabilities of the component named `component`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the component is not listed in the policy.
+    pub fn capabilities(&self, component: &str) -> Result<Capabilities> {
+        let grants = self
+            .components
+            .get(component)
+            .ok_or_else(|| anyhow!("component `{component}` is not listed in the policy"))?;
+        Ok(Capabilities {
+            component: component.to_string(),
+            grants: Some(grants.clone()),
+        })
+    }
+}
+
+/// The interfaces and named resources granted to a component.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Grants {
+    /// Interfaces the component may import, e.g. `wasi:keyvalue/store` or
+    /// `wasi:keyvalue/*`.
+    #[serde(default)]
+    pub imports: Vec<String>,
+
+    /// Key-value buckets the component may open.
+    #[serde(default)]
+    pub buckets: Vec<String>,
+
+    /// Vault lockers the component may open.
+    #[serde(default)]
+    pub lockers: Vec<String>,
+
+    /// Blobstore containers the component may create, open or delete.
+    #[serde(default)]
+    pub containers: Vec<String>,
+
+    /// SQL databases the component may connect to.
+    #[serde(default)]
+    pub databases: Vec<String>,
+
+    /// Messaging topics the component may publish, send requests or
+    /// subscribe to.
+    #[serde(default)]
+    pub topics: Vec<String>,
+
+    /// Hosts (without port) the component may send HTTP requests to.
+    #[serde(default)]
+    pub hosts: Vec<String>,
+}
+
+impl Grants {
+    fn patterns(&self, kind: ResourceKind) -> &[String] {
+        match kind {
+            ResourceKind::Bucket => &self.buckets,
+            ResourceKind::Locker => &self.lockers,
+            ResourceKind::Container => &self.containers,
+            ResourceKind::Database => &self.databases,
+            ResourceKind::Topic => &self.topics,
+            ResourceKind::Host => &self.hosts,
+        }
+    }
+}
+
+/// A kind of named resource opened by guests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResourceKind {
+    /// A key-value bucket.
+    Bucket,
+
+    /// A vault locker.
+    Locker,
+
+    /// A blobstore container.
+    Container,
+
+    /// A SQL database.
+    Database,
+
+    /// A messaging topic.
+    Topic,
+
+    /// An outgoing HTTP host.
+    Host,
+}
+
+impl Display for ResourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            Self::Bucket => "bucket",
+            Self::Locker => "locker",
+            Self::Container => "container",
+            Self::Database => "database",
+            Self::Topic => "topic",
+            Self::Host => "host",
+        };
+        f.write_str(kind)
+    }
+}
+
+/// The capabilities of a single component, checked by hosts before a guest
+/// opens a named resource.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    component: String,
+    grants: Option<Grants>,
+}
+
+impl Capabilities {
+    /// Capabilities allowing `component` every import and resource.
+    #[must_use]
+    pub fn unrestricted(component: impl Into<String>) -> Self {
+        Self {
+            component: component.into(),
+            grants: None,
+        }
+    }
+
+    /// The name of the component the capabilities are granted to.
+    #[must_use]
+    pub fn component(&self) -> &str {
+        &self.component
+    }
+
+    /// Returns `true` if the component may open the resource of `kind` named
+    /// `name`. Denials are logged.
+    #[must_use]
+    pub fn allows(&self, kind: ResourceKind, name: &str) -> bool {
+        let allowed = self.permits(kind, name);
+        if !allowed {
+            tracing::warn!(component = %self.component, "{kind} `{name}` denied by policy");
+        }
+        allowed
+    }
+
+    /// Returns `true` if the component may open the resource of `kind` named
+    /// `name`. Unlike [`Capabilities::allows`], denials are not logged.
+    #[must_use]
+    pub fn permits(&self, kind: ResourceKind, name: &str) -> bool {
+        self.granted(kind)
+            .is_none_or(|patterns| patterns.iter().any(|pattern| matches(pattern, name)))
+    }
+
+    /// The names (or patterns) of the resources of `kind` the component may
+    /// open, or `None` if the component is unrestricted.
+    #[must_use]
+    pub fn granted(&self, kind: ResourceKind) -> Option<&[String]> {
+        self.grants.as_ref().map(|grants| grants.patterns(kind))
+    }
+
+    /// Returns `true` if the component may import the interface `name`.
+    #[must_use]
+    pub fn allows_import(&self, name: &str) -> bool {
+        let Some(grants) = &self.grants else {
+            return true;
+        };
+        let name = name.split_once('@').map_or(name, |(name, _)| name);
+        grants.imports.iter().any(|pattern| matches(pattern, name))
+    }
+
+    /// Check the component's imports are allowed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error listing the imports the policy does not allow.
+    pub fn check_imports(&self, engine: &Engine, component: &Component) -> Result<()> {
+        let denied = component
+            .component_type()
+            .imports(engine)
+            .map(|(name, _)| name)
+            .filter(|name| !self.allows_import(name))
+            .collect::<Vec<_>>();
+        if !denied.is_empty() {
+            bail!("imports not allowed by policy: {}", denied.join(", "));
+        }
+        Ok(())
+    }
+}
+
+// Match `name` exactly, or by a pattern with a leading or trailing `*`.
+pub(crate) fn matches(pattern: &str, name: &str) -> bool {
+    match (pattern.strip_suffix('*'), pattern.strip_prefix('*')) {
+        (Some(prefix), _) => name.starts_with(prefix),
+        (None, Some(suffix)) => name.ends_with(suffix),
+        (None, None) => name == pattern,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_capabilities() {
+        let policy: Policy = serde_json::from_str(
+            r#"{
+                "components": {
+                    "api": {
+                        "imports": ["wasi:cli/*", "wasi:keyvalue/store"],
+                        "buckets": ["orders", "cache-*"],
+                        "hosts": ["*.example.com"]
+                    }
+                }
+            }"#,
+        )
+        .expect("should parse policy");
+        policy.capabilities("worker").expect_err("should require components to be listed");
+
+        let api = policy.capabilities("api").expect("should list api");
+        assert!(api.allows_import("wasi:cli/environment@0.2.0"));
+        assert!(api.allows_import("wasi:keyvalue/store@0.2.0-draft"));
+        assert!(!api.allows_import("wasi:keyvalue/atomics@0.2.0-draft"));
+
+        assert!(api.allows(ResourceKind::Bucket, "orders"));
+        assert!(api.allows(ResourceKind::Bucket, "cache-users"));
+        assert!(!api.allows(ResourceKind::Bucket, "payments"));
+        assert!(api.allows(ResourceKind::Host, "api.example.com"));
+        assert!(!api.allows(ResourceKind::Host, "example.org"));
+        assert!(!api.allows(ResourceKind::Topic, "orders"));
+
+        let unrestricted = Capabilities::unrestricted("api");
+        assert!(unrestricted.allows(ResourceKind::Topic, "orders"));
+        assert!(unrestricted.allows_import("wasi:sql/readwrite"));
+    }
+}
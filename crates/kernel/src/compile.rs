@@ -4,10 +4,86 @@ use std::fs::{self, File};
 use std::io::{self, Write};
 use std::path::PathBuf;
 
-use anyhow::{Result, anyhow};
-use wasmtime::component::Component;
+use anyhow::{Context, Result, anyhow};
+use clap::{Args, ValueEnum};
 use wasmtime::{Config, Engine};
 
+use crate::engine;
+
+/// Options used to compile a component ahead of time.
+///
+/// Compiled components can only be run by a runtime whose engine matches the
+/// options used to compile them.
+#[derive(Args, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompileOptions {
+    /// The target triple to compile for, for example
+    /// `aarch64-unknown-linux-gnu`. Defaults to the host.
+    #[arg(long)]
+    pub target: Option<String>,
+
+    /// The Cranelift optimization level.
+    #[arg(long, value_enum, default_value_t)]
+    pub opt_level: OptLevel,
+
+    /// A Cranelift CPU feature to enable for the target, for example
+    /// `has_avx2` or `has_lse`. May be repeated.
+    #[arg(long = "cpu-feature", value_name = "FEATURE")]
+    pub cpu_features: Vec<String>,
+
+    /// Compile with fuel metering. Required for components run with
+    /// `--fuel`.
+    #[arg(long)]
+    pub consume_fuel: bool,
+}
+
+/// Cranelift optimization level.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OptLevel {
+    /// No optimizations.
+    None,
+
+    /// Optimize for speed.
+    #[default]
+    Speed,
+
+    /// Optimize for speed and code size.
+    SpeedAndSize,
+}
+
+impl From<OptLevel> for wasmtime::OptLevel {
+    fn from(level: OptLevel) -> Self {
+        match level {
+            OptLevel::None => Self::None,
+            OptLevel::Speed => Self::Speed,
+            OptLevel::SpeedAndSize => Self::SpeedAndSize,
+        }
+    }
+}
+
+impl CompileOptions {
+    /// The engine configuration used to compile components.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the target triple is not supported.
+    pub fn config(&self) -> Result<Config> {
+        let mut config = engine::config(self.consume_fuel);
+        if let Some(target) = &self.target {
+            config.target(target).with_context(|| format!("unsupported target {target}"))?;
+        }
+        config.cranelift_opt_level(self.opt_level.into());
+        for feature in &self.cpu_features {
+            // SAFETY: CPU features only affect generated code. Wasmtime
+            // verifies the host supports each feature before running a
+            // component compiled with it.
+            unsafe {
+                config.cranelift_flag_enable(feature);
+            }
+        }
+        Ok(config)
+    }
+}
+
 /// Compile `wasm32-wasip2` component.
 ///
 /// For example, to compile the `http` component, run:
@@ -21,18 +97,15 @@ use wasmtime::{Config, Engine};
 /// Returns an error if the wasm component cannot be loaded from the specified
 /// path, cannot be compiled, or cannot be serialized to the specified output
 /// directory.
-pub fn compile(wasm: &PathBuf, output: Option<PathBuf>) -> Result<()> {
+pub fn compile(wasm: &PathBuf, output: Option<PathBuf>, options: &CompileOptions) -> Result<()> {
     let Some(file_name) = wasm.file_name() else {
         return Err(anyhow!("invalid file name"));
     };
 
     // compile component
-    let mut config = Config::new();
-    config.async_support(true);
-    config.wasm_component_model_async(true);
-    let engine = Engine::new(&config)?;
-    let component = Component::from_file(&engine, wasm)?;
-    let serialized = component.serialize()?;
+    let engine = Engine::new(&options.config()?).context("configuring engine")?;
+    let bytes = fs::read(wasm)?;
+    let serialized = engine.precompile_component(&bytes)?;
 
     // output to file or stdout
     if let Some(mut out_path) = output {
@@ -61,3 +134,43 @@ pub fn compile(wasm: &PathBuf, output: Option<PathBuf>) -> Result<()> {
 }
 
 //
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use wasmtime::component::Component;
+
+    use super::*;
+
+    #[cfg(feature = "jit")]
+    #[test]
+    fn compile_for_target() {
+        let target = if cfg!(target_arch = "aarch64") {
+            "x86_64-unknown-linux-gnu"
+        } else {
+            "aarch64-unknown-linux-gnu"
+        };
+        let dir = env::temp_dir().join(format!("wrt-compile-{}", process::id()));
+        fs::create_dir_all(&dir).expect("create dir");
+        let wasm = dir.join("guest.wasm");
+        fs::write(&wasm, wat::parse_str("(component)").expect("wat")).expect("write wasm");
+
+        let options = CompileOptions {
+            target: Some(target.to_string()),
+            ..CompileOptions::default()
+        };
+        compile(&wasm, Some(dir.clone()), &options).expect("compile");
+        let compiled = fs::read(dir.join("guest.bin")).expect("read compiled");
+
+        // compiled for another architecture, so the host cannot run it
+        let engine =
+            Engine::new(&CompileOptions::default().config().expect("config")).expect("engine");
+        // SAFETY: the artifact was compiled by wasmtime above.
+        unsafe { Component::deserialize(&engine, &compiled) }
+            .map(drop)
+            .expect_err("foreign target");
+
+        fs::remove_dir_all(&dir).expect("remove dir");
+    }
+}
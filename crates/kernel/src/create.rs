@@ -1,62 +1,355 @@
 //! # WebAssembly Initiator
 
-use std::env;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::{any, env, fs, thread};
 
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result, anyhow, bail};
 use credibil_otel::Telemetry;
+use sha2::{Digest, Sha256};
 use tracing::instrument;
+use wasmtime::Engine;
 use wasmtime::component::{Component, InstancePre, Linker};
-use wasmtime::{Config, Engine};
 use wasmtime_wasi::WasiView;
 
+use crate::admin::RuntimeInfo;
+use crate::capabilities::{Capabilities, Policy};
+use crate::engine;
+use crate::guests::{Guest, Guests, Routes};
+use crate::inspect::{self, Inspections};
+use crate::manifest::{ComponentSpec, Manifest};
+use crate::options::{EPOCH_TICK, RuntimeOptions};
 use crate::traits::Host;
 
 /// Build the Wasmtime `Engine` and `Linker` for this runtime.
 ///
+/// The `path` is either a single wasm file or a [`Manifest`] listing the
+/// components to run.
+///
 /// # Errors
 ///
-/// Will fail if the provided `wasm` file cannot be compiled/deserialized
-/// as a `Component` or the `Linker` cannot be initialized with WASI
-/// support.
+/// Will fail if a wasm file cannot be compiled/deserialized as a
+/// `Component` or the `Linker` cannot be initialized with WASI support.
 #[instrument]
-pub fn create<T: WasiView + 'static>(wasm: &PathBuf) -> Result<Compiled<T>> {
-    init_env(wasm)?;
+pub fn create<T: WasiView + 'static>(
+    path: &PathBuf, options: &RuntimeOptions,
+) -> Result<Compiled<T>> {
+    let manifest = Manifest::from_path(path)?;
+    init_env(&manifest.name)?;
     tracing::info!("initializing runtime");
+    options.record.install()?;
+
+    let compiled = load(&manifest, options)?;
+    start_epoch_ticker(compiled.linker.engine()).context("starting epoch ticker")?;
+
+    tracing::info!("runtime intialized");
+
+    Ok(compiled)
+}
+
+/// The wasm component run by a runtime embedded in another application.
+pub enum ComponentSource {
+    /// A wasm file, pre-compiled component or manifest of components.
+    Path(PathBuf),
+
+    /// A wasm32 or pre-compiled component's bytes.
+    Bytes {
+        /// The name identifying the component.
+        name: String,
+
+        /// The component's bytes.
+        wasm: Vec<u8>,
+    },
+
+    /// A component compiled using an engine created by [`new_engine`].
+    Component {
+        /// The name identifying the component.
+        name: String,
+
+        /// The compiled component.
+        component: Component,
+
+        /// The digest identifying the version compiled, typically the
+        /// SHA-256 digest of the bytes it was compiled from.
+        digest: String,
+    },
+}
 
-    let mut config = Config::new();
-    config.async_support(true);
-    config.wasm_component_model_async(true);
-    let engine = Engine::new(&config)?;
-
-    // TODO: cause executing WebAssembly to periodically yield
-    //  1. enable `Config::epoch_interruption`
-    //  2. Set `Store::epoch_deadline_async_yield_and_update`
-    //  3. Call `Engine::increment_epoch` periodically
-
-    // SAFETY: The caller should ensure only valid pre-compiled wasm files are provided.
-    let component = unsafe { Component::deserialize_file(&engine, wasm) }.or_else(|e| {
-        if cfg!(feature = "jit") {
-            Component::from_file(&engine, wasm)
-        } else {
-            Err(anyhow!("Issue loading component: {e}. Enable `jit` feature to load wasm32 files."))
+impl ComponentSource {
+    /// The wasm file (or manifest) at `path`.
+    #[must_use]
+    pub fn path(path: impl Into<PathBuf>) -> Self {
+        Self::Path(path.into())
+    }
+
+    /// The component named `name`, from its bytes.
+    #[must_use]
+    pub fn bytes(name: impl Into<String>, wasm: impl Into<Vec<u8>>) -> Self {
+        Self::Bytes {
+            name: name.into(),
+            wasm: wasm.into(),
         }
-    })?;
+    }
+
+    /// The component named `name`, already compiled, with the `digest` of
+    /// the bytes it was compiled from.
+    #[must_use]
+    pub fn component(
+        name: impl Into<String>, component: Component, digest: impl Into<String>,
+    ) -> Self {
+        Self::Component {
+            name: name.into(),
+            component,
+            digest: digest.into(),
+        }
+    }
+}
+
+/// Load a component for a runtime embedded in another application.
+///
+/// Unlike [`create`], telemetry is left to the application, so is not
+/// initialized. Components loaded from bytes or compiled by the application
+/// cannot be signature verified or reloaded, so are refused when trusted keys
+/// are configured unless unsigned components are allowed.
+///
+/// # Errors
+///
+/// Will fail if the component cannot be compiled/deserialized, or signature
+/// verification is required for a component not loaded from a file.
+pub fn embed<T: WasiView + 'static>(
+    source: ComponentSource, options: &RuntimeOptions,
+) -> Result<Compiled<T>> {
+    options.record.install()?;
 
-    // register services with runtime's Linker
-    let mut linker = Linker::new(&engine);
+    let (name, component, digest) = match source {
+        ComponentSource::Path(path) => {
+            let compiled = load(&Manifest::from_path(&path)?, options)?;
+            start_epoch_ticker(compiled.engine()).context("starting epoch ticker")?;
+            return Ok(compiled);
+        }
+        ComponentSource::Bytes { name, wasm } => {
+            let engine = new_engine(options)?;
+            let component = from_bytes(&engine, &wasm, options)
+                .with_context(|| format!("loading component `{name}`"))?;
+            let digest = format!("{:x}", Sha256::digest(&wasm));
+            (name, component, digest)
+        }
+        ComponentSource::Component {
+            name,
+            component,
+            digest,
+        } => {
+            require_unsigned_allowed(options)
+                .with_context(|| format!("loading component `{name}`"))?;
+            (name, component, digest)
+        }
+    };
+
+    let engine = component.engine().clone();
+    options.wasi.validate()?;
+    let capabilities = capabilities(&[name.clone()], options)?;
+    capabilities[0]
+        .check_imports(&engine, &component)
+        .with_context(|| format!("loading component `{name}`"))?;
+    start_epoch_ticker(&engine).context("starting epoch ticker")?;
+
+    let spec = ComponentSpec {
+        name: name.clone(),
+        wasm: PathBuf::new(),
+        routes: Routes::default(),
+    };
+    Ok(Compiled {
+        manifest: Manifest {
+            name,
+            components: vec![spec],
+        },
+        components: vec![component],
+        digests: vec![digest],
+        capabilities,
+        linker: linker(&engine)?,
+        hosts: Vec::new(),
+        options: options.clone(),
+    })
+}
+
+/// Create the `Engine` used to compile and run components with `options`.
+/// Components compiled by an application embedding the runtime must use an
+/// engine created by this function.
+///
+/// # Errors
+///
+/// Will fail if the engine cannot be created using the options.
+pub fn new_engine(options: &RuntimeOptions) -> Result<Engine> {
+    let mut config = engine::config(options.fuel.is_some());
+    config.allocation_strategy(options.allocation_strategy());
+    config.coredump_on_trap(options.coredump_dir.is_some());
+    Engine::new(&config)
+}
+
+/// Load the manifest's components and build their `Linker` without
+/// initializing telemetry or starting runtime services. Used to inspect,
+/// rather than run, components.
+///
+/// Components share a single `Engine` and `Linker`.
+///
+/// # Errors
+///
+/// Will fail if a wasm file cannot be compiled/deserialized as a
+/// `Component` or the `Linker` cannot be initialized with WASI support.
+pub fn load<T: WasiView + 'static>(
+    manifest: &Manifest, options: &RuntimeOptions,
+) -> Result<Compiled<T>> {
+    let engine = new_engine(options)?;
+    options.wasi.validate()?;
+
+    let names = manifest.components.iter().map(|spec| spec.name.clone()).collect::<Vec<_>>();
+    let capabilities = capabilities(&names, options)?;
+
+    let (components, digests) = manifest
+        .components
+        .iter()
+        .zip(&capabilities)
+        .map(|(spec, capabilities)| {
+            component(&engine, &spec.wasm, options)
+                .and_then(|(component, digest)| {
+                    capabilities.check_imports(&engine, &component)?;
+                    Ok((component, digest))
+                })
+                .with_context(|| format!("loading component `{}`", spec.name))
+        })
+        .collect::<Result<(Vec<_>, Vec<_>)>>()?;
+
+    Ok(Compiled {
+        manifest: manifest.clone(),
+        components,
+        digests,
+        capabilities,
+        linker: linker(&engine)?,
+        hosts: Vec::new(),
+        options: options.clone(),
+    })
+}
+
+/// The capabilities of each named component, granted by the policy when one
+/// is configured.
+fn capabilities(names: &[String], options: &RuntimeOptions) -> Result<Vec<Capabilities>> {
+    let policy = options.policy.as_deref().map(Policy::from_path).transpose()?;
+    names
+        .iter()
+        .map(|name| {
+            policy.as_ref().map_or_else(
+                || Ok(Capabilities::unrestricted(name)),
+                |policy| policy.capabilities(name),
+            )
+        })
+        .collect()
+}
+
+/// Create a `Linker` with WASI support, to which the runtime's services are
+/// added.
+fn linker<T: WasiView + 'static>(engine: &Engine) -> Result<Linker<T>> {
+    let mut linker = Linker::new(engine);
     wasmtime_wasi::p2::add_to_linker_async(&mut linker)?;
     wasmtime_wasi::p3::add_to_linker(&mut linker)?;
+    Ok(linker)
+}
 
-    tracing::info!("runtime intialized");
+/// Read the wasm file and verify it is trusted, then deserialize
+/// (pre-compiled) or compile the bytes verified. Returns the component and
+/// the SHA-256 digest of the bytes, identifying the version loaded.
+fn component(
+    engine: &Engine, wasm: &Path, options: &RuntimeOptions,
+) -> Result<(Component, String)> {
+    let bytes = fs::read(wasm).with_context(|| format!("reading {}", wasm.display()))?;
+    options.trust.verify(wasm, &bytes)?;
+    let digest = format!("{:x}", Sha256::digest(&bytes));
 
-    Ok(Compiled { component, linker })
+    let component = if Engine::detect_precompiled(&bytes).is_some() {
+        // SAFETY: The caller should ensure only valid pre-compiled wasm files are provided.
+        // Configuring trusted keys ensures the file was signed by a trusted party.
+        unsafe { Component::deserialize(engine, &bytes) }.with_context(|| {
+            format!(
+                "{} was compiled for an incompatible engine, recompile it for this host \
+                 (and with `--consume-fuel` when running with `--fuel`)",
+                wasm.display()
+            )
+        })
+    } else {
+        compile(engine, wasm, &bytes, options)
+    }?;
+    Ok((component, digest))
 }
 
-/// A compiled WebAssembly component with its associated Linker.
+/// Deserialize (pre-compiled) or compile a component's bytes. Without a
+/// file, its signature cannot be verified.
+fn from_bytes(engine: &Engine, wasm: &[u8], options: &RuntimeOptions) -> Result<Component> {
+    require_unsigned_allowed(options)?;
+
+    if Engine::detect_precompiled(wasm).is_some() {
+        // SAFETY: The caller should ensure only valid pre-compiled components are provided.
+        unsafe { Component::deserialize(engine, wasm) }.context(
+            "component was compiled for an incompatible engine, recompile it for this host",
+        )
+    } else {
+        compile_bytes(engine, wasm)
+    }
+}
+
+/// Components not loaded from a file (bytes, or an already compiled
+/// `Component`) carry no signature, so are only allowed when components need
+/// not be signed.
+fn require_unsigned_allowed(options: &RuntimeOptions) -> Result<()> {
+    if !options.trust.trusted_keys.is_empty() && !options.trust.allow_unsigned {
+        bail!("components must be loaded from a signed file when trusted keys are configured");
+    }
+    Ok(())
+}
+
+#[cfg(feature = "jit")]
+fn compile_bytes(engine: &Engine, wasm: &[u8]) -> Result<Component> {
+    Component::new(engine, wasm)
+}
+
+#[cfg(not(feature = "jit"))]
+fn compile_bytes(_: &Engine, _: &[u8]) -> Result<Component> {
+    Err(anyhow!("component is not pre-compiled. Enable `jit` feature to load wasm32 components."))
+}
+
+/// Compile a wasm32 component, reusing a previously compiled artifact from
+/// the compile cache when one is available.
+///
+/// Cached artifacts are not signed, so the cache is bypassed when components
+/// must be signed by a trusted key.
+#[cfg(feature = "jit")]
+fn compile(
+    engine: &Engine, wasm: &Path, bytes: &[u8], options: &RuntimeOptions,
+) -> Result<Component> {
+    if !options.no_cache
+        && options.trust.trusted_keys.is_empty()
+        && let Some(cache) = options.cache.cache()
+    {
+        return cache.load(engine, wasm, bytes);
+    }
+    Component::new(engine, bytes)
+}
+
+#[cfg(not(feature = "jit"))]
+fn compile(_: &Engine, wasm: &Path, _: &[u8], _: &RuntimeOptions) -> Result<Component> {
+    Err(anyhow!(
+        "{} is not a pre-compiled component. Enable `jit` feature to load wasm32 files.",
+        wasm.display()
+    ))
+}
+
+/// Compiled WebAssembly components with their associated Linker.
 pub struct Compiled<T: WasiView + 'static> {
-    component: Component,
+    manifest: Manifest,
+    components: Vec<Component>,
+    digests: Vec<String>,
+    capabilities: Vec<Capabilities>,
     linker: Linker<T>,
+    hosts: Vec<String>,
+    options: RuntimeOptions,
 }
 
 impl<T: WasiView> Compiled<T> {
@@ -66,26 +359,153 @@ impl<T: WasiView> Compiled<T> {
     ///
     /// Will fail if the host cannot be added to the Linker.
     pub fn link<H: Host<T>>(&mut self, _: H) -> Result<()> {
-        H::add_to_linker(&mut self.linker)
+        H::add_to_linker(&mut self.linker)?;
+        let name = any::type_name::<H>();
+        self.hosts.push(name.rsplit("::").next().unwrap_or(name).to_string());
+        Ok(())
+    }
+
+    /// Pre-instantiate each component.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if a component cannot be pre-instantiated.
+    pub fn pre_instantiate(&mut self) -> Result<Guests<T>> {
+        let guests = self
+            .manifest
+            .components
+            .iter()
+            .zip(self.components.iter().zip(&self.digests).zip(&self.capabilities))
+            .map(|(spec, ((component, digest), capabilities))| {
+                let instance_pre = self
+                    .linker
+                    .instantiate_pre(component)
+                    .with_context(|| format!("pre-instantiating component `{}`", spec.name))?;
+                Ok(Guest::new(&spec.name, instance_pre, digest, spec.routes.clone())
+                    .with_capabilities(capabilities.clone()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Guests::new(guests)
+    }
+
+    /// Recompile the named component from its wasm file and pre-instantiate
+    /// it using the existing engine and linker, returning the
+    /// `InstancePre` and the wasm file's digest.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if the component cannot be compiled or pre-instantiated, or
+    /// imports interfaces not allowed by the capability policy, in which case
+    /// the previously loaded component is kept.
+    pub fn reload(&mut self, name: &str) -> Result<(InstancePre<T>, String)> {
+        let Some(index) = self.manifest.components.iter().position(|spec| spec.name == name) else {
+            return Err(anyhow!("unknown component `{name}`"));
+        };
+        let wasm = &self.manifest.components[index].wasm;
+
+        let (component, digest) = component(self.linker.engine(), wasm, &self.options)?;
+        self.capabilities[index].check_imports(self.linker.engine(), &component)?;
+        let instance_pre = self.linker.instantiate_pre(&component)?;
+        self.components[index] = component;
+        self.digests[index].clone_from(&digest);
+        Ok((instance_pre, digest))
     }
 
-    /// Pre-instantiate component.
+    /// Report each component's imports and exports, checking each import
+    /// against the hosts linked so far.
     ///
     /// # Errors
     ///
-    /// Will fail if the component cannot be pre-instantiated.
-    pub fn pre_instantiate(&mut self) -> Result<InstancePre<T>> {
-        self.linker.instantiate_pre(&self.component)
+    /// Will fail if a component's imports cannot be checked.
+    pub fn inspect(&self) -> Result<Inspections> {
+        let inspections = self
+            .manifest
+            .components
+            .iter()
+            .zip(&self.components)
+            .map(|(spec, component)| inspect::inspect(&spec.name, component, &self.linker))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Inspections(inspections))
+    }
+
+    /// Returns `true` if any component imports an interface named with
+    /// `prefix`, e.g. `wasi:keyvalue/`.
+    #[must_use]
+    pub fn imports(&self, prefix: &str) -> bool {
+        let engine = self.engine();
+        self.components.iter().any(|component| {
+            component.component_type().imports(engine).any(|(name, _)| name.starts_with(prefix))
+        })
+    }
+
+    /// Returns `true` if any component exports an interface named with
+    /// `prefix`, e.g. `wasi:http/handler`.
+    #[must_use]
+    pub fn exports(&self, prefix: &str) -> bool {
+        let engine = self.engine();
+        self.components.iter().any(|component| {
+            component.component_type().exports(engine).any(|(name, _)| name.starts_with(prefix))
+        })
+    }
+
+    /// Information about the runtime, reported by the admin server.
+    #[must_use]
+    pub fn info(&self) -> RuntimeInfo {
+        RuntimeInfo {
+            name: self.manifest.name.clone(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            hosts: self.hosts.clone(),
+        }
+    }
+
+    /// The manifest the components were loaded from.
+    #[must_use]
+    pub const fn manifest(&self) -> &Manifest {
+        &self.manifest
     }
+
+    /// The options the runtime was created with.
+    #[must_use]
+    pub const fn options(&self) -> &RuntimeOptions {
+        &self.options
+    }
+
+    pub(crate) fn engine(&self) -> &Engine {
+        self.linker.engine()
+    }
+}
+
+/// Increment the engine's epoch on a dedicated thread so that guests stuck in
+/// a hot loop are still interrupted when every executor thread is busy.
+///
+/// The thread exits once the engine has been dropped.
+pub(crate) fn start_epoch_ticker(engine: &Engine) -> Result<()> {
+    let weak = engine.weak();
+    thread::Builder::new().name("epoch-ticker".into()).spawn(move || {
+        while let Some(engine) = weak.upgrade() {
+            engine.increment_epoch();
+            drop(engine);
+            thread::sleep(EPOCH_TICK);
+        }
+    })?;
+    Ok(())
 }
 
 /// Initialize telemetry for the runtime.
 ///
+/// Telemetry is process-wide so is only initialized for the first runtime
+/// created. Individual components are identified by their name.
+///
 /// # Errors
 ///
 /// Will fail if the telemetry cannot be initialized.
-fn init_env(wasm: &Path) -> Result<()> {
-    let name = wasm.file_stem().and_then(|s| s.to_str()).unwrap_or("unknown");
+fn init_env(name: &str) -> Result<()> {
+    static INITIALIZED: Mutex<bool> = Mutex::new(false);
+    let mut initialized =
+        INITIALIZED.lock().map_err(|e| anyhow!("telemetry initialization lock: {e}"))?;
+    if *initialized {
+        return Ok(());
+    }
 
     if env::var("COMPONENT").is_err() {
         // SAFETY: Environment variable modification is safe here because:
@@ -101,5 +521,8 @@ fn init_env(wasm: &Path) -> Result<()> {
     if let Ok(endpoint) = env::var("OTEL_GRPC_URL") {
         builder = builder.endpoint(endpoint);
     }
-    builder.build().context("initializing telemetry")
+    builder.build().context("initializing telemetry")?;
+    *initialized = true;
+
+    Ok(())
 }
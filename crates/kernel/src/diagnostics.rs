@@ -0,0 +1,199 @@
+//! # Invocation Diagnostics
+//!
+//! Optional diagnostics captured for individual guest invocations:
+//!
+//! - a guest profile, written in the Firefox profiler's format, when the
+//!   runtime is run with `--profile`.
+//! - a Wasm core dump, written when the guest traps, when the runtime is run
+//!   with `--coredump-dir`.
+//!
+//! Files are named using the component and invocation ID. Each invocation is
+//! also recorded in the runtime's [`metrics`].
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use wasmtime::component::InstancePre;
+use wasmtime::{AsContext, GuestProfiler, Store, WasmCoreDump};
+
+use crate::metrics::{Invocation, metrics};
+use crate::options::RuntimeOptions;
+
+/// Diagnostics for a single guest invocation.
+pub struct Diagnostics {
+    id: String,
+    component: String,
+    profiler: Option<Profiler>,
+    profile_dir: Option<PathBuf>,
+    coredump_dir: Option<PathBuf>,
+    invocation: Invocation,
+}
+
+impl Diagnostics {
+    /// Start capturing diagnostics for an invocation of `component` using
+    /// `store`. The invocation is identified by the caller's `id` (such as a
+    /// request ID), when set, or a random ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the guest profiler cannot be created.
+    pub fn start<T: 'static>(
+        options: &RuntimeOptions, store: &mut Store<T>, instance_pre: &InstancePre<T>,
+        component: &str, id: Option<&str>,
+    ) -> Result<Self> {
+        let id = invocation_id(id);
+
+        let profiler = if options.profile_dir.is_some() && options.profile_sampled() {
+            let interval = options.profile_interval();
+            let profiler = GuestProfiler::new_component(
+                store.engine(),
+                component,
+                interval,
+                instance_pre.component().clone(),
+                [],
+            )?;
+            let profiler = Profiler::new(profiler, interval);
+            options.set_epoch_callback(store, Some(profiler.clone()));
+            Some(profiler)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            id,
+            component: component.to_string(),
+            profiler,
+            profile_dir: options.profile_dir.clone(),
+            coredump_dir: options.coredump_dir.clone(),
+            invocation: metrics().invocation(component),
+        })
+    }
+
+    /// The invocation ID. A caller's ID is sanitized for use in file names
+    /// and suffixed with a random ID, so invocations reusing an ID do not
+    /// overwrite each other's files.
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Finish the invocation, recording its metrics and writing the guest
+    /// profile and, when `result` is a trap, the guest's core dump. Failures
+    /// are logged rather than returned so they do not mask the invocation's
+    /// result.
+    pub fn finish<T: 'static>(self, store: &mut Store<T>, result: &Result<()>) {
+        if let (Some(profiler), Some(dir)) = (&self.profiler, &self.profile_dir) {
+            let path = self.path(dir, "json");
+            match profiler.write(&path) {
+                Ok(()) => tracing::info!("guest profile written to {}", path.display()),
+                Err(e) => tracing::error!("failed to write guest profile: {e:#}"),
+            }
+        }
+
+        if let (Some(dir), Err(e)) = (&self.coredump_dir, result)
+            && let Some(coredump) = e.downcast_ref::<WasmCoreDump>()
+        {
+            let path = self.path(dir, "coredump");
+            let bytes = coredump.serialize(&mut *store, &self.component);
+            match fs::create_dir_all(dir).and_then(|()| fs::write(&path, bytes)) {
+                Ok(()) => tracing::info!("guest core dump written to {}", path.display()),
+                Err(e) => tracing::error!("failed to write guest core dump: {e}"),
+            }
+        }
+
+        self.invocation.finish(result);
+    }
+
+    fn path(&self, dir: &Path, extension: &str) -> PathBuf {
+        dir.join(format!("{}-{}.{extension}", self.component, self.id))
+    }
+}
+
+/// A guest profiler shared with the store's epoch callback, which samples the
+/// guest's stack at the configured interval.
+#[derive(Clone)]
+pub(crate) struct Profiler {
+    inner: Arc<Mutex<Option<Sampler>>>,
+}
+
+struct Sampler {
+    profiler: GuestProfiler,
+    interval: Duration,
+    last_sample: Instant,
+}
+
+impl Profiler {
+    fn new(profiler: GuestProfiler, interval: Duration) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Some(Sampler {
+                profiler,
+                interval,
+                last_sample: Instant::now(),
+            }))),
+        }
+    }
+
+    /// Sample the guest's stack if the sampling interval has elapsed.
+    pub(crate) fn sample(&self, store: impl AsContext) {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(sampler) = inner.as_mut() else {
+            return;
+        };
+        let elapsed = sampler.last_sample.elapsed();
+        if elapsed >= sampler.interval {
+            sampler.profiler.sample(store, elapsed);
+            sampler.last_sample = Instant::now();
+        }
+    }
+
+    // Finish profiling, writing the profile to `path`.
+    fn write(&self, path: &Path) -> Result<()> {
+        let Some(sampler) = self.inner.lock().unwrap_or_else(PoisonError::into_inner).take() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = File::create(path).with_context(|| format!("creating {}", path.display()))?;
+        sampler.profiler.finish(BufWriter::new(file))
+    }
+}
+
+// The ID of an invocation, derived from the caller's ID when provided.
+fn invocation_id(id: Option<&str>) -> String {
+    let random = format!("{:016x}", rand::random::<u64>());
+    match id {
+        Some(id) => format!("{}-{random}", sanitize(id)),
+        None => random,
+    }
+}
+
+// Restrict a caller-provided ID to characters that are safe in a file name.
+fn sanitize(id: &str) -> String {
+    id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_id() {
+        assert_eq!(sanitize("req-123_abc"), "req-123_abc");
+        assert_eq!(sanitize("../etc/passwd"), "___etc_passwd");
+    }
+
+    #[test]
+    fn unique_id() {
+        let id = invocation_id(Some("../req-1"));
+        assert!(id.starts_with("___req-1-"));
+        assert_ne!(id, invocation_id(Some("../req-1")));
+        assert_eq!(invocation_id(None).len(), 16);
+    }
+}
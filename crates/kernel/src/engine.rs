@@ -0,0 +1,23 @@
+//! # Engine Configuration
+//!
+//! The Wasmtime engine configuration shared by `compile` and `create`, so
+//! pre-compiled components always match the runtime that loads them.
+
+use wasmtime::Config;
+
+/// The base engine configuration used to compile and run components.
+///
+/// Settings that affect generated code (and so artifact compatibility) must
+/// be made here rather than in `compile` or `create`.
+pub(crate) fn config(consume_fuel: bool) -> Config {
+    let mut config = Config::new();
+    config.async_support(true);
+    config.wasm_component_model_async(true);
+
+    // epoch interruption causes executing guests to periodically yield (see
+    // `RuntimeOptions::configure_store`)
+    config.epoch_interruption(true);
+    config.consume_fuel(consume_fuel);
+
+    config
+}
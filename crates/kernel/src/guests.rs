@@ -0,0 +1,311 @@
+//! # Guests
+//!
+//! The components hosted by the runtime, and the routes used to select the
+//! component that handles each HTTP request or message.
+
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use wasmtime::component::InstancePre;
+
+use crate::capabilities::{self, Capabilities, ResourceKind};
+use crate::reload::SharedInstancePre;
+
+/// The HTTP requests and messages routed to a component.
+///
+/// A component without HTTP routes handles requests for any host and path,
+/// and a component without topics handles messages on any topic.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Routes {
+    /// Host names (without port) of the HTTP requests handled.
+    #[serde(default)]
+    pub hosts: Vec<String>,
+
+    /// Path prefixes of the HTTP requests handled. A prefix matches whole
+    /// path segments, so `/orders` matches `/orders/1` but not `/ordersx`.
+    #[serde(default)]
+    pub paths: Vec<String>,
+
+    /// Topics of the messages handled. A leading or trailing `*` matches any
+    /// topic ending or starting with the remaining characters.
+    #[serde(default)]
+    pub topics: Vec<String>,
+}
+
+impl Routes {
+    // How specifically a request for `host` and `path` is matched: routes
+    // naming the host take precedence, then the longest path prefix.
+    fn http_match(&self, host: &str, path: &str) -> Option<(bool, usize)> {
+        let host_matched = !self.hosts.is_empty();
+        if host_matched && !self.hosts.iter().any(|h| h.eq_ignore_ascii_case(host)) {
+            return None;
+        }
+        if self.paths.is_empty() {
+            return Some((host_matched, 0));
+        }
+        let prefix = self
+            .paths
+            .iter()
+            .filter(|prefix| path_matches(prefix, path))
+            .max_by_key(|prefix| prefix.len())?;
+        Some((host_matched, prefix.len()))
+    }
+}
+
+fn path_matches(prefix: &str, path: &str) -> bool {
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/'))
+}
+
+/// A component hosted by the runtime.
+pub struct Guest<T: 'static> {
+    name: String,
+    instance_pre: SharedInstancePre<T>,
+    routes: Routes,
+    capabilities: Arc<Capabilities>,
+    topics: Option<Vec<String>>,
+}
+
+impl<T: 'static> Guest<T> {
+    /// Create a guest from its pre-instantiated component, compiled from
+    /// the wasm file with SHA-256 `digest`.
+    #[must_use]
+    pub fn new(
+        name: impl Into<String>, instance_pre: InstancePre<T>, digest: impl Into<String>,
+        routes: Routes,
+    ) -> Self {
+        let name = name.into();
+        let capabilities = Capabilities::unrestricted(&name);
+        Self {
+            topics: subscriptions(&routes, &capabilities),
+            capabilities: Arc::new(capabilities),
+            name,
+            instance_pre: SharedInstancePre::new(instance_pre, digest),
+            routes,
+        }
+    }
+
+    /// Restrict the guest to `capabilities`. Guests are unrestricted by
+    /// default.
+    ///
+    /// The guest's topic routes are checked against the capabilities here,
+    /// rather than as each message is delivered: routes the capabilities do
+    /// not allow are logged and ignored.
+    #[must_use]
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.topics = subscriptions(&self.routes, &capabilities);
+        self.capabilities = Arc::new(capabilities);
+        self
+    }
+
+    /// The name identifying the guest in telemetry.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The pre-instantiated guest. The guest may be reloaded at any time, so
+    /// the returned `InstancePre` should be used for the duration of an
+    /// invocation rather than fetched again.
+    #[must_use]
+    pub fn instance_pre(&self) -> InstancePre<T> {
+        self.instance_pre.get()
+    }
+
+    /// The SHA-256 digest of the guest's wasm file, updated when the guest
+    /// is reloaded.
+    #[must_use]
+    pub fn digest(&self) -> String {
+        self.instance_pre.digest()
+    }
+
+    /// The requests and messages routed to the guest.
+    #[must_use]
+    pub const fn routes(&self) -> &Routes {
+        &self.routes
+    }
+
+    /// The interfaces and named resources the guest may use.
+    #[must_use]
+    pub const fn capabilities(&self) -> &Arc<Capabilities> {
+        &self.capabilities
+    }
+
+    /// Returns `true` if messages published to `topic` are delivered to the
+    /// guest.
+    #[must_use]
+    pub fn subscribes_to(&self, topic: &str) -> bool {
+        self.topics
+            .as_ref()
+            .is_none_or(|topics| topics.iter().any(|pattern| capabilities::matches(pattern, topic)))
+    }
+
+    pub(crate) const fn shared(&self) -> &SharedInstancePre<T> {
+        &self.instance_pre
+    }
+}
+
+// The topics of the messages delivered to a guest: its topic routes allowed
+// by `capabilities` or, without topic routes, the topics the capabilities
+// allow. `None` when messages on any topic are delivered.
+fn subscriptions(routes: &Routes, capabilities: &Capabilities) -> Option<Vec<String>> {
+    let Some(granted) = capabilities.granted(ResourceKind::Topic) else {
+        return (!routes.topics.is_empty()).then(|| routes.topics.clone());
+    };
+    if routes.topics.is_empty() {
+        return Some(granted.to_vec());
+    }
+
+    let topics = routes
+        .topics
+        .iter()
+        .filter(|topic| {
+            let allowed = capabilities.permits(ResourceKind::Topic, topic);
+            if !allowed {
+                tracing::warn!(
+                    component = %capabilities.component(),
+                    "topic route `{topic}` is not allowed by policy and is ignored"
+                );
+            }
+            allowed
+        })
+        .cloned()
+        .collect();
+    Some(topics)
+}
+
+impl<T: 'static> Clone for Guest<T> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            instance_pre: self.instance_pre.clone(),
+            routes: self.routes.clone(),
+            capabilities: Arc::clone(&self.capabilities),
+            topics: self.topics.clone(),
+        }
+    }
+}
+
+/// The components hosted by the runtime.
+pub struct Guests<T: 'static> {
+    guests: Vec<Guest<T>>,
+}
+
+impl<T: 'static> Guests<T> {
+    /// Create from one or more guests.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `guests` is empty.
+    pub fn new(guests: Vec<Guest<T>>) -> Result<Self> {
+        if guests.is_empty() {
+            bail!("at least one guest is required");
+        }
+        Ok(Self { guests })
+    }
+
+    /// The first guest listed. Used when work is not specific to a guest,
+    /// such as connecting to a backend.
+    #[must_use]
+    pub fn primary(&self) -> &Guest<T> {
+        &self.guests[0]
+    }
+
+    /// The guest named `name`.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Guest<T>> {
+        self.guests.iter().find(|guest| guest.name == name)
+    }
+
+    /// Iterate over the guests.
+    pub fn iter(&self) -> impl Iterator<Item = &Guest<T>> {
+        self.guests.iter()
+    }
+
+    /// The guest handling HTTP requests for `host` and `path`.
+    ///
+    /// When several guests match, a guest naming the host is preferred, then
+    /// the guest with the longest matching path prefix, then the guest listed
+    /// first.
+    #[must_use]
+    pub fn route(&self, host: &str, path: &str) -> Option<&Guest<T>> {
+        // `max_by_key` returns the last maximum, so search in reverse to
+        // prefer the guest listed first
+        self.guests
+            .iter()
+            .rev()
+            .filter_map(|guest| Some((guest.routes.http_match(host, path)?, guest)))
+            .max_by_key(|(specificity, _)| *specificity)
+            .map(|(_, guest)| guest)
+    }
+
+    /// The guests handling messages published to `topic`. Guests whose
+    /// capabilities do not allow the topic are skipped.
+    pub fn subscribed(&self, topic: &str) -> impl Iterator<Item = &Guest<T>> {
+        self.guests.iter().filter(move |guest| guest.subscribes_to(topic))
+    }
+}
+
+impl<T: 'static> Clone for Guests<T> {
+    fn clone(&self) -> Self {
+        Self {
+            guests: self.guests.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capabilities::Policy;
+
+    fn routes(hosts: &[&str], paths: &[&str]) -> Routes {
+        Routes {
+            hosts: hosts.iter().map(ToString::to_string).collect(),
+            paths: paths.iter().map(ToString::to_string).collect(),
+            topics: vec![],
+        }
+    }
+
+    #[test]
+    fn http_match() {
+        let any = Routes::default();
+        assert_eq!(any.http_match("example.com", "/"), Some((false, 0)));
+
+        let orders = routes(&[], &["/orders", "/orders/archive"]);
+        assert_eq!(orders.http_match("example.com", "/orders"), Some((false, 7)));
+        assert_eq!(orders.http_match("example.com", "/orders/archive/1"), Some((false, 15)));
+        assert_eq!(orders.http_match("example.com", "/ordersx"), None);
+
+        let admin = routes(&["Admin.example.com"], &[]);
+        assert_eq!(admin.http_match("admin.example.com", "/orders"), Some((true, 0)));
+        assert_eq!(admin.http_match("example.com", "/orders"), None);
+
+        assert!(routes(&[], &["/api/"]).http_match("example.com", "/api/v1").is_some());
+    }
+
+    #[test]
+    fn topic_subscriptions() {
+        let policy: Policy = serde_json::from_str(
+            r#"{ "components": { "worker": { "topics": ["orders.*", "payments"] } } }"#,
+        )
+        .expect("should parse policy");
+        let capabilities = policy.capabilities("worker").expect("should list worker");
+
+        let routes = Routes {
+            topics: vec!["orders.*".into(), "refunds".into()],
+            ..Routes::default()
+        };
+        assert_eq!(subscriptions(&routes, &capabilities), Some(vec!["orders.*".into()]));
+        assert_eq!(
+            subscriptions(&Routes::default(), &capabilities),
+            Some(vec!["orders.*".into(), "payments".into()])
+        );
+
+        let unrestricted = Capabilities::unrestricted("worker");
+        assert_eq!(subscriptions(&routes, &unrestricted), Some(routes.topics.clone()));
+        assert_eq!(subscriptions(&Routes::default(), &unrestricted), None);
+    }
+}
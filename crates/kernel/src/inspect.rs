@@ -0,0 +1,390 @@
+//! # Component Inspection
+//!
+//! Reports the imports and exports of each component loaded by a runtime,
+//! and whether each import is satisfied by the hosts linked into the runtime.
+
+use std::fmt::{self, Display};
+
+use anyhow::{Result, anyhow, bail};
+use serde::Serialize;
+use wasmtime::Engine;
+use wasmtime::component::types::ComponentItem;
+use wasmtime::component::{Component, Linker, LinkerInstance, ResourceType};
+
+/// The inspection of each component loaded by a runtime.
+#[derive(Clone, Debug, Serialize)]
+#[serde(transparent)]
+pub struct Inspections(pub Vec<Inspection>);
+
+impl Inspections {
+    /// Returns `true` when every component's imports are satisfied by the
+    /// linked hosts.
+    #[must_use]
+    pub fn is_satisfied(&self) -> bool {
+        self.0.iter().all(Inspection::is_satisfied)
+    }
+
+    /// Write the report to stdout, formatted as JSON when `json` is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error listing any unsatisfied imports, so the command can
+    /// be used to gate CI builds.
+    pub fn report(&self, json: bool) -> Result<()> {
+        if json {
+            println!("{}", serde_json::to_string_pretty(self)?);
+        } else {
+            print!("{self}");
+        }
+
+        let missing = self
+            .0
+            .iter()
+            .flat_map(|inspection| {
+                let imports = inspection
+                    .missing()
+                    .map(|import| format!("{}: {}", inspection.component, import.name));
+                let error = inspection
+                    .error
+                    .as_ref()
+                    .map(|error| format!("{}: {error}", inspection.component));
+                imports.chain(error)
+            })
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            bail!("unsatisfied imports: {}", missing.join(", "));
+        }
+        Ok(())
+    }
+}
+
+impl Display for Inspections {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for inspection in &self.0 {
+            write!(f, "{inspection}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The imports and exports of a component.
+#[derive(Clone, Debug, Serialize)]
+pub struct Inspection {
+    /// The component's name.
+    pub component: String,
+
+    /// The component's imports.
+    pub imports: Vec<Import>,
+
+    /// The component's exports.
+    pub exports: Vec<WitName>,
+
+    /// Why the component cannot be instantiated with the linked hosts, when
+    /// the failure is not attributed to one of its imports.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Inspection {
+    /// Returns `true` when every import is satisfied by the linked hosts.
+    #[must_use]
+    pub fn is_satisfied(&self) -> bool {
+        self.error.is_none() && self.imports.iter().all(|import| import.satisfied)
+    }
+
+    /// Imports that are not satisfied by the linked hosts.
+    pub fn missing(&self) -> impl Iterator<Item = &Import> {
+        self.imports.iter().filter(|import| !import.satisfied)
+    }
+}
+
+impl Display for Inspection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "component: {}", self.component)?;
+        writeln!(f, "imports:")?;
+        for import in &self.imports {
+            if import.satisfied {
+                writeln!(f, "  [ok]      {}", import.name)?;
+            } else {
+                writeln!(f, "  [missing] {}", import.name)?;
+            }
+        }
+        if let Some(error) = &self.error {
+            writeln!(f, "error: {error}")?;
+        }
+        writeln!(f, "exports:")?;
+        for export in &self.exports {
+            writeln!(f, "  {export}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A component import.
+#[derive(Clone, Debug, Serialize)]
+pub struct Import {
+    /// The import's name.
+    #[serde(flatten)]
+    pub name: WitName,
+
+    /// Whether the import is satisfied by the linked hosts.
+    pub satisfied: bool,
+
+    /// Why the import could not be satisfied, when it is missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The name of a component import or export, split into its WIT parts.
+///
+/// For example, `wasi:http/handler@0.3.0` has the package `wasi:http`, the
+/// interface `handler` and the version `0.3.0`. Plain names (such as `run`)
+/// have no package, interface or version.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct WitName {
+    /// The full name.
+    pub name: String,
+
+    /// The WIT package, including its namespace.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+
+    /// The interface within the package.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interface: Option<String>,
+
+    /// The package version.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl WitName {
+    /// Parse a component import or export name.
+    #[must_use]
+    pub fn parse(name: &str) -> Self {
+        let (path, version) = match name.split_once('@') {
+            Some((path, version)) => (path, Some(version.to_string())),
+            None => (name, None),
+        };
+        let (package, interface) = match path.split_once('/') {
+            Some((package, interface)) if package.contains(':') => {
+                (Some(package.to_string()), Some(interface.to_string()))
+            }
+            _ if path.contains(':') => (Some(path.to_string()), None),
+            _ => (None, None),
+        };
+
+        Self {
+            name: name.to_string(),
+            package,
+            interface,
+            version,
+        }
+    }
+}
+
+impl Display for WitName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+/// Inspect the component named `name`, checking its imports against
+/// `linker`.
+///
+/// Defining an item the linker already defines is an error, so each import
+/// is checked by stubbing it out in a copy of the linker: the items that can
+/// be stubbed are those not provided by the linked hosts. Instances the hosts
+/// define are not opened, so an instance missing some of its functions, like
+/// an import with a different type, is only caught when the stubbed linker
+/// is type-checked against the component. Each import failing the type check
+/// is marked missing and replaced with stubs, so the imports after it are
+/// checked too.
+pub(crate) fn inspect<T>(
+    name: &str, component: &Component, linker: &Linker<T>,
+) -> Result<Inspection> {
+    let engine = component.engine();
+    let ty = component.component_type();
+
+    let mut probe = linker.clone();
+    probe.allow_shadowing(false);
+
+    let mut imports = ty
+        .imports(engine)
+        .map(|(name, item)| {
+            let mut stubbed = Stubbed::default();
+            stub(&mut probe.root(), engine, name, &item, &mut stubbed)?;
+            let error = (!stubbed.missing.is_empty()).then(|| stubbed.error(name));
+            Ok(Import {
+                name: WitName::parse(name),
+                satisfied: error.is_none(),
+                error,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let exports = ty.exports(engine).map(|(name, _)| WitName::parse(name)).collect();
+
+    // with every missing item stubbed out, any remaining error is in an
+    // import the hosts define
+    probe.allow_shadowing(true);
+    let error = loop {
+        let Err(e) = probe.instantiate_pre(component) else {
+            break None;
+        };
+        let failed = e.to_string();
+        let Some(import) = imports
+            .iter_mut()
+            .find(|import| import.satisfied && failed.contains(&format!("`{}`", import.name)))
+        else {
+            break Some(format!("{e:#}"));
+        };
+
+        let cause = e.chain().skip(1).map(ToString::to_string).collect::<Vec<_>>();
+        import.satisfied = false;
+        import.error = Some(format!("`{}` does not match: {}", import.name, cause.join(": ")));
+
+        let (name, item) = ty
+            .imports(engine)
+            .find(|(name, _)| *name == import.name.name)
+            .ok_or_else(|| anyhow!("no import named `{}`", import.name))?;
+        stub(&mut probe.root(), engine, name, &item, &mut Stubbed::default())?;
+    };
+
+    Ok(Inspection {
+        component: name.to_string(),
+        imports,
+        exports,
+        error,
+    })
+}
+
+/// The items of an import stubbed out because they are not linked.
+#[derive(Default)]
+struct Stubbed {
+    missing: Vec<String>,
+    linked: usize,
+}
+
+impl Stubbed {
+    fn error(&self, import: &str) -> String {
+        if self.linked == 0 {
+            format!("`{import}` is not linked")
+        } else {
+            format!("`{import}` is missing {}", self.missing.join(", "))
+        }
+    }
+}
+
+// Define `item` in the linker using functions that trap when called, unless
+// it is already defined.
+fn stub<T>(
+    linker: &mut LinkerInstance<'_, T>, engine: &Engine, name: &str, item: &ComponentItem,
+    stubbed: &mut Stubbed,
+) -> Result<()> {
+    let defined = match item {
+        ComponentItem::ComponentFunc(_) => {
+            let func = name.to_string();
+            linker.func_new(name, move |_, _, _, _| Err(anyhow!("`{func}` is not linked")))
+        }
+        ComponentItem::ComponentInstance(instance) => {
+            // an instance the hosts define cannot be reopened
+            let Ok(mut linker) = linker.instance(name) else {
+                stubbed.linked += 1;
+                return Ok(());
+            };
+            for (name, item) in instance.exports(engine) {
+                stub(&mut linker, engine, name, &item, stubbed)?;
+            }
+            return Ok(());
+        }
+        ComponentItem::Resource(_) => {
+            linker.resource(name, ResourceType::host::<()>(), |_, _| Ok(()))
+        }
+        ComponentItem::Type(_) => return Ok(()),
+        ComponentItem::CoreFunc(_) | ComponentItem::Module(_) | ComponentItem::Component(_) => {
+            bail!("unable to stub import `{name}`")
+        }
+    };
+
+    if defined.is_ok() {
+        stubbed.missing.push(format!("`{name}`"));
+    } else {
+        stubbed.linked += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_imports() {
+        let engine = Engine::default();
+        let wasm = wat::parse_str(
+            r#"(component
+                (import "acme:ledger/accounts@0.1.0" (instance
+                    (export "open" (func))
+                    (export "close" (func))
+                ))
+                (import "acme:ledger/audit@0.1.0" (instance (export "log" (func))))
+                (import "run" (func))
+            )"#,
+        )
+        .expect("wat");
+        let component = Component::new(&engine, wasm).expect("component");
+
+        let mut linker = Linker::<()>::new(&engine);
+        let mut accounts = linker.instance("acme:ledger/accounts@0.1.0").expect("instance");
+        accounts.func_wrap("open", |_, (): ()| Ok(())).expect("open");
+        linker.root().func_wrap("run", |_, (): ()| Ok(())).expect("run");
+
+        let inspection = inspect("ledger", &component, &linker).expect("inspect");
+        let errors = inspection
+            .imports
+            .iter()
+            .map(|import| (import.name.name.as_str(), import.error.as_deref()))
+            .collect::<Vec<_>>();
+
+        // the accounts instance is linked, but without `close`
+        assert_eq!(
+            errors,
+            [
+                (
+                    "acme:ledger/accounts@0.1.0",
+                    Some(
+                        "`acme:ledger/accounts@0.1.0` does not match: instance export `close` has \
+                         the wrong type: function implementation is missing"
+                    )
+                ),
+                ("acme:ledger/audit@0.1.0", Some("`acme:ledger/audit@0.1.0` is not linked")),
+                ("run", None),
+            ]
+        );
+        assert_eq!(inspection.error, None);
+        assert!(!inspection.is_satisfied());
+
+        let mut audit = linker.instance("acme:ledger/audit@0.1.0").expect("instance");
+        audit.func_wrap("log", |_, (): ()| Ok(())).expect("log");
+        let inspection = inspect("ledger", &component, &linker).expect("inspect");
+        let missing = inspection.missing().map(|import| import.name.name.as_str());
+        assert_eq!(missing.collect::<Vec<_>>(), ["acme:ledger/accounts@0.1.0"]);
+        assert!(!inspection.is_satisfied());
+    }
+
+    #[test]
+    fn parse_name() {
+        let name = WitName::parse("wasi:http/handler@0.3.0-rc-2025-09-16");
+        assert_eq!(name.package.as_deref(), Some("wasi:http"));
+        assert_eq!(name.interface.as_deref(), Some("handler"));
+        assert_eq!(name.version.as_deref(), Some("0.3.0-rc-2025-09-16"));
+
+        let name = WitName::parse("wasi:cli/environment");
+        assert_eq!(name.package.as_deref(), Some("wasi:cli"));
+        assert_eq!(name.version, None);
+
+        let name = WitName::parse("run");
+        assert_eq!(name.package, None);
+        assert_eq!(name.interface, None);
+    }
+}
@@ -2,10 +2,29 @@
 
 //! # WebAssembly Initiator
 
+mod admin;
+mod backends;
+mod cache;
+mod capabilities;
 #[cfg(feature = "jit")]
 mod compile;
 mod create;
+mod diagnostics;
+mod engine;
+mod guests;
+mod inspect;
+mod limits;
+mod manifest;
+mod metrics;
+mod options;
+mod reload;
+mod replay;
+mod shutdown;
+mod signing;
+pub mod testing;
 mod traits;
+mod wasi;
+mod wit;
 
 use std::path::PathBuf;
 
@@ -13,10 +32,27 @@ pub use clap::Parser;
 use clap::Subcommand;
 pub use {anyhow, futures, tokio, wasmtime, wasmtime_wasi};
 
+pub use self::admin::*;
+pub use self::backends::*;
+pub use self::cache::*;
+pub use self::capabilities::*;
 #[cfg(feature = "jit")]
 pub use self::compile::*;
 pub use self::create::*;
+pub use self::diagnostics::*;
+pub use self::guests::*;
+pub use self::inspect::*;
+pub use self::limits::*;
+pub use self::manifest::*;
+pub use self::metrics::*;
+pub use self::options::*;
+pub use self::reload::*;
+pub use self::replay::*;
+pub use self::shutdown::*;
+pub use self::signing::*;
 pub use self::traits::*;
+pub use self::wasi::*;
+pub use self::wit::*;
 
 #[derive(Parser, PartialEq, Eq)]
 #[command(version, about, long_about = None)]
@@ -32,8 +68,54 @@ pub enum Command {
     Run {
         /// The path to the wasm file to run. The file can either be a
         /// serialized (pre-compiled) wasmtime `Component` or standard
-        /// WASI component
+        /// WASI component. A JSON manifest (`.json`) can be used to run
+        /// several components in one runtime.
         wasm: PathBuf,
+
+        /// Runtime options.
+        #[command(flatten)]
+        options: RuntimeOptions,
+    },
+    /// List the imports and exports of the specified wasm guest (or
+    /// manifest of guests), showing which imports are satisfied by the
+    /// runtime's hosts.
+    Inspect {
+        /// The path to the wasm file (or JSON manifest) to inspect.
+        wasm: PathBuf,
+
+        /// Output the report as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write the WIT package describing the interfaces provided by the
+    /// runtime, for guests to generate bindings against.
+    Wit {
+        /// The directory to write the package to.
+        #[arg(default_value = "wit")]
+        dir: PathBuf,
+    },
+    /// Manage the compile cache.
+    Cache {
+        /// The cache command to execute.
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    /// Sign a wasm component or compiled artifact, writing a detached
+    /// signature (`<file>.sig`) alongside it.
+    Sign {
+        /// The path to the file to sign.
+        file: PathBuf,
+
+        /// The ed25519 signing key, created using `keygen`.
+        #[arg(long, env = "SIGNING_KEY")]
+        key: PathBuf,
+    },
+    /// Generate an ed25519 signing key, writing its public key to
+    /// `<key>.pub` for use as a trusted key.
+    Keygen {
+        /// The path to write the signing key to. Existing keys are not
+        /// overwritten.
+        key: PathBuf,
     },
     /// Compile the specified wasm32-wasip2 component.
     #[cfg(feature = "jit")]
@@ -45,5 +127,43 @@ pub enum Command {
         /// will be written to the same location as the input file.
         #[arg(short, long)]
         output: Option<PathBuf>,
+
+        /// Compile options.
+        #[command(flatten)]
+        options: CompileOptions,
     },
 }
+
+impl Command {
+    /// Execute commands that do not depend on the runtime's hosts.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command fails, or is a command (such as `run`)
+    /// that must be executed by the runtime.
+    pub fn execute(self) -> anyhow::Result<()> {
+        match self {
+            Self::Cache { command } => command.execute(),
+            Self::Sign { file, key } => {
+                let signature = sign(&file, &key)?;
+                println!("signature written to {}", signature.display());
+                Ok(())
+            }
+            Self::Keygen { key } => {
+                let public_key = generate_key(&key)?;
+                println!("signing key written to {}", key.display());
+                println!("public key written to {}", public_key.display());
+                Ok(())
+            }
+            #[cfg(feature = "jit")]
+            Self::Compile {
+                wasm,
+                output,
+                options,
+            } => compile(&wasm, output, &options),
+            Self::Run { .. } | Self::Inspect { .. } | Self::Wit { .. } => {
+                Err(anyhow::anyhow!("command must be executed by the runtime"))
+            }
+        }
+    }
+}
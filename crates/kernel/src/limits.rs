@@ -0,0 +1,200 @@
+//! # Resource Limits
+//!
+//! Per-store resource limits applied to each guest invocation.
+
+use std::fmt::{self, Display};
+
+use anyhow::Result;
+use wasmtime::component::{Instance, InstancePre};
+use wasmtime::{DEFAULT_INSTANCE_LIMIT, ResourceLimiter, Store, Trap};
+
+/// Implemented by the `StoreCtx` to give the `Store` access to its resource
+/// [`Limiter`].
+pub trait LimiterView {
+    /// Return a mutable reference to the store's [`Limiter`].
+    fn limiter(&mut self) -> &mut Limiter;
+}
+
+/// Enforces resource limits for a single `Store`.
+///
+/// Unlike `wasmtime::StoreLimits`, exceeding a limit fails with a
+/// [`LimitExceeded`] error rather than a failed `memory.grow` or
+/// `table.grow`, so breaches can be distinguished from other guest errors.
+#[derive(Clone, Debug, Default)]
+pub struct Limiter {
+    max_memory_bytes: Option<usize>,
+    max_table_elements: Option<usize>,
+    max_instances: Option<usize>,
+}
+
+impl Limiter {
+    /// Create a new limiter. Unset limits are not enforced.
+    #[must_use]
+    pub const fn new(
+        max_memory_bytes: Option<usize>, max_table_elements: Option<usize>,
+        max_instances: Option<usize>,
+    ) -> Self {
+        Self {
+            max_memory_bytes,
+            max_table_elements,
+            max_instances,
+        }
+    }
+}
+
+impl ResourceLimiter for Limiter {
+    fn memory_growing(
+        &mut self, _current: usize, desired: usize, maximum: Option<usize>,
+    ) -> Result<bool> {
+        if let Some(limit) = self.max_memory_bytes
+            && desired > limit
+        {
+            let err = LimitExceeded::Memory { desired, limit };
+            tracing::warn!("{err}");
+            return Err(err.into());
+        }
+        Ok(maximum.is_none_or(|max| desired <= max))
+    }
+
+    fn table_growing(
+        &mut self, _current: usize, desired: usize, maximum: Option<usize>,
+    ) -> Result<bool> {
+        if let Some(limit) = self.max_table_elements
+            && desired > limit
+        {
+            let err = LimitExceeded::Table { desired, limit };
+            tracing::warn!("{err}");
+            return Err(err.into());
+        }
+        Ok(maximum.is_none_or(|max| desired <= max))
+    }
+
+    fn instances(&self) -> usize {
+        self.max_instances.unwrap_or(DEFAULT_INSTANCE_LIMIT)
+    }
+}
+
+/// Instantiate a guest in `store`, failing with [`LimitExceeded::Instances`]
+/// when the guest creates more (core) instances than the store's
+/// [`Limiter`] allows.
+///
+/// # Errors
+///
+/// Returns an error if the guest cannot be instantiated.
+pub async fn instantiate<T: LimiterView + Send + 'static>(
+    store: &mut Store<T>, instance_pre: &InstancePre<T>,
+) -> Result<Instance> {
+    match instance_pre.instantiate_async(&mut *store).await {
+        // wasmtime reports the breach using a plain error, so is recognised by
+        // its message
+        Err(e) if e.to_string().contains("instance count too high") => {
+            let err = LimitExceeded::Instances {
+                limit: store.data_mut().limiter().instances(),
+            };
+            tracing::warn!("{err}");
+            Err(err.into())
+        }
+        result => result,
+    }
+}
+
+/// Returned when a guest invocation exceeds one of its resource limits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimitExceeded {
+    /// A linear memory attempted to grow beyond the configured limit.
+    Memory { desired: usize, limit: usize },
+
+    /// A table attempted to grow beyond the configured limit.
+    Table { desired: usize, limit: usize },
+
+    /// Instantiation created more instances than the configured limit.
+    Instances { limit: usize },
+}
+
+impl LimitExceeded {
+    /// Returns `true` if the error, or any of its causes, is the result of a
+    /// resource limit being exceeded. This includes a guest running out of
+    /// fuel.
+    #[must_use]
+    pub fn is(err: &anyhow::Error) -> bool {
+        err.chain().any(|e| e.is::<Self>() || e.downcast_ref::<Trap>() == Some(&Trap::OutOfFuel))
+    }
+}
+
+impl Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Memory { desired, limit } => {
+                write!(f, "memory limit exceeded: {desired} bytes requested, limit is {limit}")
+            }
+            Self::Table { desired, limit } => {
+                write!(f, "table limit exceeded: {desired} elements requested, limit is {limit}")
+            }
+            Self::Instances { limit } => {
+                write!(f, "instance limit exceeded: limit is {limit}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+#[cfg(test)]
+mod tests {
+    use wasmtime::Engine;
+    use wasmtime::component::{Component, Linker};
+
+    use super::*;
+
+    #[test]
+    fn memory_limit() {
+        let mut limiter = Limiter::new(Some(1024), None, None);
+
+        assert!(limiter.memory_growing(0, 1024, None).expect("within limit"));
+        assert!(!limiter.memory_growing(0, 1024, Some(512)).expect("within limit"));
+
+        let err = limiter.memory_growing(1024, 2048, None).expect_err("should exceed limit");
+        assert!(LimitExceeded::is(&err));
+        assert_eq!(err.to_string(), "memory limit exceeded: 2048 bytes requested, limit is 1024");
+    }
+
+    #[tokio::test]
+    async fn instance_limit() {
+        struct Ctx(Limiter);
+
+        impl LimiterView for Ctx {
+            fn limiter(&mut self) -> &mut Limiter {
+                &mut self.0
+            }
+        }
+
+        let engine = Engine::new(&crate::engine::config(false)).expect("engine");
+        let wasm = wat::parse_str(
+            r"(component
+                (core module $m)
+                (core instance (instantiate $m))
+                (core instance (instantiate $m))
+            )",
+        )
+        .expect("wat");
+        let component = Component::new(&engine, wasm).expect("component");
+        let instance_pre = Linker::new(&engine).instantiate_pre(&component).expect("instance_pre");
+
+        let mut store = Store::new(&engine, Ctx(Limiter::new(None, None, Some(2))));
+        store.limiter(|ctx| ctx.limiter());
+        instantiate(&mut store, &instance_pre).await.expect("within limit");
+
+        let mut store = Store::new(&engine, Ctx(Limiter::new(None, None, Some(1))));
+        store.limiter(|ctx| ctx.limiter());
+        let err = instantiate(&mut store, &instance_pre).await.map(drop).expect_err("over limit");
+        assert!(LimitExceeded::is(&err));
+        assert_eq!(err.downcast_ref(), Some(&LimitExceeded::Instances { limit: 1 }));
+    }
+
+    #[test]
+    fn out_of_fuel() {
+        let err = anyhow::Error::from(Trap::OutOfFuel).context("calling guest");
+        assert!(LimitExceeded::is(&err));
+        assert!(!LimitExceeded::is(&anyhow::anyhow!("guest error")));
+    }
+}
@@ -0,0 +1,163 @@
+//! # Component Manifest
+//!
+//! A manifest lists the components hosted by a single runtime process and
+//! the routes used to direct HTTP requests and messages to each component.
+//! Components share the runtime's backend connections, but each invocation
+//! runs in its own store.
+//!
+//! ```json
+//! {
+//!     "name": "orders",
+//!     "components": [
+//!         { "wasm": "api.wasm", "routes": { "paths": ["/orders"] } },
+//!         { "wasm": "admin.wasm", "routes": { "hosts": ["admin.example.com"] } },
+//!         { "name": "worker", "wasm": "worker.wasm", "routes": { "topics": ["orders.*"] } }
+//!     ]
+//! }
+//! ```
+//!
+//! Component paths are relative to the manifest.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+
+use crate::guests::Routes;
+
+/// The components hosted by the runtime.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// The name used to identify the runtime in telemetry. Defaults to the
+    /// manifest's file name.
+    #[serde(default)]
+    pub name: String,
+
+    /// The components to run.
+    pub components: Vec<ComponentSpec>,
+}
+
+/// A component listed in a [`Manifest`].
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentSpec {
+    /// The name used to identify the component in telemetry (its
+    /// `COMPONENT`). Defaults to the wasm file name.
+    #[serde(default)]
+    pub name: String,
+
+    /// The path to the wasm file.
+    pub wasm: PathBuf,
+
+    /// The HTTP requests and messages routed to the component.
+    #[serde(default)]
+    pub routes: Routes,
+}
+
+impl Manifest {
+    /// Load the manifest at `path`. Any other file is treated as a single
+    /// wasm component (see [`Manifest::component`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifest cannot be read or is invalid.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        if path.extension().is_none_or(|ext| ext != "json") {
+            return Ok(Self::component(path));
+        }
+
+        let contents =
+            fs::read(path).with_context(|| format!("reading manifest {}", path.display()))?;
+        let mut manifest: Self = serde_json::from_slice(&contents)
+            .with_context(|| format!("parsing manifest {}", path.display()))?;
+
+        if manifest.name.is_empty() {
+            manifest.name = file_stem(path);
+        }
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        for spec in &mut manifest.components {
+            spec.wasm = dir.join(&spec.wasm);
+            if spec.name.is_empty() {
+                spec.name = file_stem(&spec.wasm);
+            }
+        }
+
+        manifest.validate().with_context(|| format!("invalid manifest {}", path.display()))?;
+        Ok(manifest)
+    }
+
+    /// A manifest for a single wasm component that handles every request
+    /// and message.
+    #[must_use]
+    pub fn component(wasm: &Path) -> Self {
+        let name = file_stem(wasm);
+        Self {
+            name: name.clone(),
+            components: vec![ComponentSpec {
+                name,
+                wasm: wasm.to_path_buf(),
+                routes: Routes::default(),
+            }],
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.components.is_empty() {
+            bail!("no components listed");
+        }
+        let mut names = HashSet::new();
+        for spec in &self.components {
+            if !names.insert(&spec.name) {
+                bail!("duplicate component name `{}`", spec.name);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem().and_then(|s| s.to_str()).unwrap_or("unknown").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn load_manifest() {
+        let dir = env::temp_dir().join(format!("wrt-manifest-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("should create dir");
+        let path = dir.join("orders.json");
+        fs::write(
+            &path,
+            r#"{
+                "components": [
+                    { "wasm": "api.wasm", "routes": { "paths": ["/orders"] } },
+                    { "name": "worker", "wasm": "jobs.wasm", "routes": { "topics": ["a.*"] } }
+                ]
+            }"#,
+        )
+        .expect("should write manifest");
+
+        let manifest = Manifest::from_path(&path).expect("should load manifest");
+        assert_eq!(manifest.name, "orders");
+        assert_eq!(manifest.components[0].name, "api");
+        assert_eq!(manifest.components[0].wasm, dir.join("api.wasm"));
+        assert_eq!(manifest.components[1].name, "worker");
+
+        fs::write(&path, r#"{ "components": [{ "wasm": "a.wasm" }, { "wasm": "a.wasm" }] }"#)
+            .expect("should write manifest");
+        Manifest::from_path(&path).expect_err("should reject duplicate names");
+
+        let manifest = Manifest::from_path(Path::new("guest.wasm")).expect("should load");
+        assert_eq!(manifest.components.len(), 1);
+        assert_eq!(manifest.components[0].name, "guest");
+
+        fs::remove_dir_all(dir).expect("should remove dir");
+    }
+}
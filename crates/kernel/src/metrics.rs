@@ -0,0 +1,230 @@
+//! # Runtime Metrics
+//!
+//! Process-wide guest invocation metrics, rendered in the Prometheus text
+//! exposition format by the admin server. Invocations are recorded by
+//! [`Diagnostics`](crate::Diagnostics), so servers need not record them
+//! separately.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::sync::{LazyLock, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::backends::{Backends, Health};
+use crate::limits::LimitExceeded;
+use crate::options::DeadlineExceeded;
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+/// The runtime's metrics.
+#[must_use]
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+/// Guest invocation metrics, by component.
+pub struct Metrics {
+    started: Instant,
+    components: Mutex<BTreeMap<String, Stats>>,
+}
+
+#[derive(Default)]
+struct Stats {
+    in_flight: u64,
+    outcomes: BTreeMap<Outcome, u64>,
+    duration: Duration,
+}
+
+/// The outcome of a guest invocation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Outcome {
+    /// The invocation completed successfully.
+    Ok,
+
+    /// The invocation failed.
+    Error,
+
+    /// The invocation exceeded its deadline.
+    Deadline,
+
+    /// The invocation exceeded a resource limit.
+    Limit,
+}
+
+impl Outcome {
+    /// The outcome of an invocation returning `result`.
+    #[must_use]
+    pub fn of<T>(result: &anyhow::Result<T>) -> Self {
+        match result {
+            Ok(_) => Self::Ok,
+            Err(e) if DeadlineExceeded::is(e) => Self::Deadline,
+            Err(e) if LimitExceeded::is(e) => Self::Limit,
+            Err(_) => Self::Error,
+        }
+    }
+
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Error => "error",
+            Self::Deadline => "deadline",
+            Self::Limit => "limit",
+        }
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            components: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl Metrics {
+    /// Start recording an invocation of `component`. The invocation is
+    /// recorded as failed unless [`Invocation::finish`] is called.
+    pub fn invocation(&'static self, component: &str) -> Invocation {
+        self.update(component, |stats| stats.in_flight += 1);
+        Invocation {
+            metrics: self,
+            component: component.to_string(),
+            started: Instant::now(),
+            outcome: Outcome::Error,
+        }
+    }
+
+    /// Render the metrics, and the health of `backends`, in the Prometheus
+    /// text exposition format.
+    #[must_use]
+    pub fn render(&self, backends: &Backends) -> String {
+        let mut out = String::new();
+        // writing to a `String` cannot fail
+        self.write(&mut out, backends).unwrap_or_default();
+        out
+    }
+
+    fn write(&self, out: &mut String, backends: &Backends) -> fmt::Result {
+        header(out, "wrt_uptime_seconds", "gauge", "Time since the runtime started.")?;
+        writeln!(out, "wrt_uptime_seconds {}", self.started.elapsed().as_secs_f64())?;
+
+        let components = self.components.lock().unwrap_or_else(PoisonError::into_inner);
+        header(out, "wrt_invocations_total", "counter", "Completed guest invocations.")?;
+        for (component, stats) in components.iter() {
+            for (outcome, count) in &stats.outcomes {
+                writeln!(
+                    out,
+                    "wrt_invocations_total{{component=\"{}\",outcome=\"{}\"}} {count}",
+                    escape(component),
+                    outcome.label()
+                )?;
+            }
+        }
+        header(out, "wrt_invocations_in_flight", "gauge", "Guest invocations in progress.")?;
+        for (component, stats) in components.iter() {
+            writeln!(
+                out,
+                "wrt_invocations_in_flight{{component=\"{}\"}} {}",
+                escape(component),
+                stats.in_flight
+            )?;
+        }
+        header(
+            out,
+            "wrt_invocation_seconds_total",
+            "counter",
+            "Time spent in completed guest invocations.",
+        )?;
+        for (component, stats) in components.iter() {
+            writeln!(
+                out,
+                "wrt_invocation_seconds_total{{component=\"{}\"}} {}",
+                escape(component),
+                stats.duration.as_secs_f64()
+            )?;
+        }
+        drop(components);
+
+        header(out, "wrt_backend_healthy", "gauge", "Whether the backend is healthy.")?;
+        for (name, health) in backends.health() {
+            let healthy = u8::from(!matches!(health, Health::Unhealthy(_)));
+            writeln!(out, "wrt_backend_healthy{{backend=\"{}\"}} {healthy}", escape(&name))?;
+        }
+
+        Ok(())
+    }
+
+    fn update(&self, component: &str, f: impl FnOnce(&mut Stats)) {
+        let mut components = self.components.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(stats) = components.get_mut(component) {
+            f(stats);
+        } else {
+            f(components.entry(component.to_string()).or_default());
+        }
+    }
+}
+
+/// A guest invocation being recorded.
+pub struct Invocation {
+    metrics: &'static Metrics,
+    component: String,
+    started: Instant,
+    outcome: Outcome,
+}
+
+impl Invocation {
+    /// Record the invocation's result.
+    pub fn finish<T>(mut self, result: &anyhow::Result<T>) {
+        self.outcome = Outcome::of(result);
+    }
+}
+
+impl Drop for Invocation {
+    fn drop(&mut self) {
+        let elapsed = self.started.elapsed();
+        let outcome = self.outcome;
+        self.metrics.update(&self.component, |stats| {
+            stats.in_flight = stats.in_flight.saturating_sub(1);
+            *stats.outcomes.entry(outcome).or_default() += 1;
+            stats.duration += elapsed;
+        });
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) -> fmt::Result {
+    writeln!(out, "# HELP {name} {help}")?;
+    writeln!(out, "# TYPE {name} {kind}")
+}
+
+// Escape a Prometheus label value.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+
+    #[test]
+    fn render() {
+        let metrics: &'static Metrics = Box::leak(Box::default());
+        metrics.invocation("api").finish(&Ok(()));
+        metrics.invocation("api").finish::<()>(&Err(anyhow!("failed")));
+        let in_flight = metrics.invocation("worker");
+
+        let rendered = metrics.render(&Backends::default());
+        assert!(rendered.contains("wrt_invocations_total{component=\"api\",outcome=\"ok\"} 1"));
+        assert!(rendered.contains("wrt_invocations_total{component=\"api\",outcome=\"error\"} 1"));
+        assert!(rendered.contains("wrt_invocations_in_flight{component=\"worker\"} 1"));
+
+        drop(in_flight);
+        let rendered = metrics.render(&Backends::default());
+        assert!(rendered.contains("wrt_invocations_in_flight{component=\"worker\"} 0"));
+        assert!(
+            rendered.contains("wrt_invocations_total{component=\"worker\",outcome=\"error\"} 1")
+        );
+    }
+}
@@ -0,0 +1,340 @@
+//! # Runtime Options
+//!
+//! Options used to configure the runtime and each guest invocation. Options
+//! can be set using command line flags or their corresponding environment
+//! variables.
+
+use std::fmt::{self, Display};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use clap::Args;
+use wasmtime::{InstanceAllocationStrategy, PoolingAllocationConfig, Store, UpdateDeadline};
+
+use crate::backends::BackendOptions;
+use crate::cache::CacheOptions;
+use crate::diagnostics::Profiler;
+use crate::limits::{Limiter, LimiterView};
+use crate::replay::RecordOptions;
+use crate::signing::TrustOptions;
+use crate::wasi::WasiOptions;
+
+/// The interval at which the engine's epoch is incremented. Executing guests
+/// yield to the async executor (and check their deadline) on every tick.
+pub const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// The default time allowed for in-flight guest invocations to complete once
+/// shutdown has been triggered.
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+/// The default number of concurrent guest instances supported by the pooling
+/// allocator.
+pub const DEFAULT_POOL_INSTANCES: u32 = 1000;
+
+// Per-instance sizing used to derive the pool's totals from the number of
+// concurrent guest instances. A typical guest component is made up of a
+// handful of core instances (the guest module plus adapter and shim modules)
+// with at most a couple of linear memories.
+const CORE_INSTANCES_PER_COMPONENT: u32 = 16;
+const MEMORIES_PER_COMPONENT: u32 = 2;
+const TABLES_PER_COMPONENT: u32 = 8;
+const STACKS_PER_COMPONENT: u32 = 4;
+
+/// Runtime options.
+#[derive(Args, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RuntimeOptions {
+    /// The maximum wall-clock time, in milliseconds, a single guest
+    /// invocation may run before it is interrupted. When unset, invocations
+    /// are not time-limited.
+    #[arg(long, env = "DEADLINE_MS")]
+    pub deadline_ms: Option<u64>,
+
+    /// The maximum size, in bytes, of any linear memory in a guest instance.
+    #[arg(long, env = "MAX_MEMORY_BYTES")]
+    pub max_memory_bytes: Option<usize>,
+
+    /// The maximum number of elements in any table in a guest instance.
+    #[arg(long, env = "MAX_TABLE_ELEMENTS")]
+    pub max_table_elements: Option<usize>,
+
+    /// The maximum number of (core) instances a single guest invocation may
+    /// create.
+    #[arg(long, env = "MAX_INSTANCES")]
+    pub max_instances: Option<usize>,
+
+    /// The fuel available to a single guest invocation. When set, fuel
+    /// metering is enabled and invocations are interrupted once their fuel
+    /// is consumed.
+    #[arg(long, env = "FUEL")]
+    pub fuel: Option<u64>,
+
+    /// Use the pooling instance allocator. Resources for guest instances are
+    /// reserved up front and reused across invocations, reducing the cost of
+    /// instantiating a guest for each request.
+    #[arg(long, env = "POOLING")]
+    pub pooling: bool,
+
+    /// The maximum number of guest instances that can be live at once when
+    /// pooling is enabled. Instantiation fails once the pool is exhausted.
+    /// Defaults to 1000.
+    #[arg(long, env = "POOL_INSTANCES")]
+    pub pool_instances: Option<u32>,
+
+    /// The maximum number of unused instance slots kept warm for reuse when
+    /// pooling is enabled. Defaults to 100.
+    #[arg(long, env = "POOL_WARM_SLOTS")]
+    pub pool_warm_slots: Option<u32>,
+
+    /// The time, in milliseconds, in-flight guest invocations are given to
+    /// complete once shutdown has been triggered. Defaults to 30 seconds.
+    #[arg(long, env = "GRACE_PERIOD_MS")]
+    pub grace_period_ms: Option<u64>,
+
+    /// Compile wasm components on every start rather than reusing compiled
+    /// artifacts from the compile cache.
+    #[arg(long, env = "NO_CACHE")]
+    pub no_cache: bool,
+
+    /// Watch the wasm file, reloading the guest whenever it changes.
+    /// In-flight invocations complete using the previous guest.
+    #[arg(long, env = "WATCH")]
+    pub watch: bool,
+
+    /// Write a profile of each guest invocation to this directory, in the
+    /// Firefox profiler format (view at <https://profiler.firefox.com>).
+    #[arg(long = "profile", env = "PROFILE_DIR", value_name = "DIR")]
+    pub profile_dir: Option<PathBuf>,
+
+    /// The interval, in milliseconds, at which guest stacks are sampled when
+    /// profiling. Defaults to, and is at least, the epoch tick (10ms).
+    #[arg(long, env = "PROFILE_INTERVAL_MS")]
+    pub profile_interval_ms: Option<u64>,
+
+    /// The percentage of invocations profiled, from 1 to 100, when
+    /// profiling. Defaults to every invocation.
+    #[arg(long, env = "PROFILE_SAMPLE_RATE", value_name = "PERCENT",
+        value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub profile_sample_rate: Option<u8>,
+
+    /// Write a core dump to this directory when a guest traps.
+    #[arg(long, env = "COREDUMP_DIR", value_name = "DIR")]
+    pub coredump_dir: Option<PathBuf>,
+
+    /// Serve the admin endpoints (health, readiness, runtime information
+    /// and metrics) on this address, e.g. `0.0.0.0:9090`.
+    #[arg(long, env = "ADMIN_ADDR")]
+    pub admin_addr: Option<SocketAddr>,
+
+    /// The capability policy listing the interfaces each component may
+    /// import and the named resources it may open. When unset, components
+    /// are unrestricted.
+    #[arg(long, env = "POLICY_FILE", value_name = "FILE")]
+    pub policy: Option<PathBuf>,
+
+    /// Backend connection options.
+    #[command(flatten)]
+    pub backend: BackendOptions,
+
+    /// Compile cache options.
+    #[command(flatten)]
+    pub cache: CacheOptions,
+
+    /// Component trust policy.
+    #[command(flatten)]
+    pub trust: TrustOptions,
+
+    /// The WASI context policy applied to guests.
+    #[command(flatten)]
+    pub wasi: WasiOptions,
+
+    /// Host call recording and replay.
+    #[command(flatten)]
+    pub record: RecordOptions,
+}
+
+impl RuntimeOptions {
+    /// The per-invocation deadline, if set.
+    #[must_use]
+    pub fn deadline(&self) -> Option<Duration> {
+        self.deadline_ms.map(Duration::from_millis)
+    }
+
+    /// The shutdown grace period.
+    #[must_use]
+    pub fn grace_period(&self) -> Duration {
+        self.grace_period_ms.map_or(DEFAULT_GRACE_PERIOD, Duration::from_millis)
+    }
+
+    /// The interval at which guest stacks are sampled when profiling.
+    #[must_use]
+    pub fn profile_interval(&self) -> Duration {
+        self.profile_interval_ms.map_or(EPOCH_TICK, Duration::from_millis).max(EPOCH_TICK)
+    }
+
+    /// Whether to profile an invocation, chosen at random using the profile
+    /// sample rate.
+    #[must_use]
+    pub fn profile_sampled(&self) -> bool {
+        self.profile_sample_rate.is_none_or(|rate| rand::random_range(0..100) < rate)
+    }
+
+    /// A resource limiter for a single guest invocation.
+    #[must_use]
+    pub const fn limiter(&self) -> Limiter {
+        Limiter::new(self.max_memory_bytes, self.max_table_elements, self.max_instances)
+    }
+
+    /// The instance allocation strategy used by the engine.
+    ///
+    /// When pooling is enabled, the pool is sized from the number of
+    /// concurrent guest instances, with each instance allowed a fixed number
+    /// of core instances, memories, tables and async stacks. Memory and table
+    /// slots are sized using the configured resource limits, if set.
+    #[must_use]
+    pub fn allocation_strategy(&self) -> InstanceAllocationStrategy {
+        if !self.pooling {
+            return InstanceAllocationStrategy::OnDemand;
+        }
+
+        let instances = self.pool_instances.unwrap_or(DEFAULT_POOL_INSTANCES);
+        let mut pooling = PoolingAllocationConfig::new();
+        pooling
+            .total_component_instances(instances)
+            .max_core_instances_per_component(CORE_INSTANCES_PER_COMPONENT)
+            .total_core_instances(instances.saturating_mul(CORE_INSTANCES_PER_COMPONENT))
+            .max_memories_per_component(MEMORIES_PER_COMPONENT)
+            .total_memories(instances.saturating_mul(MEMORIES_PER_COMPONENT))
+            .max_tables_per_component(TABLES_PER_COMPONENT)
+            .total_tables(instances.saturating_mul(TABLES_PER_COMPONENT))
+            .total_stacks(instances.saturating_mul(STACKS_PER_COMPONENT));
+
+        if let Some(bytes) = self.max_memory_bytes {
+            pooling.max_memory_size(bytes);
+        }
+        if let Some(elements) = self.max_table_elements {
+            pooling.table_elements(elements);
+        }
+        if let Some(slots) = self.pool_warm_slots {
+            pooling.max_unused_warm_slots(slots);
+        }
+
+        InstanceAllocationStrategy::Pooling(pooling)
+    }
+
+    /// Apply per-invocation settings to a newly created `Store`.
+    ///
+    /// The store will yield to the async executor on every epoch tick and,
+    /// when a deadline is configured, fail with [`DeadlineExceeded`] once the
+    /// deadline has passed. Resource limits are enforced by the store's
+    /// [`Limiter`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if fuel is configured but fuel metering was not
+    /// enabled for the engine.
+    pub fn configure_store<T: LimiterView + 'static>(&self, store: &mut Store<T>) -> Result<()> {
+        store.limiter(|ctx| ctx.limiter());
+        if let Some(fuel) = self.fuel {
+            store.set_fuel(fuel)?;
+        }
+
+        self.set_epoch_callback(store, None);
+
+        Ok(())
+    }
+
+    // Yield to the async executor on every epoch tick, failing once the
+    // deadline (if any) has passed and sampling the guest's stack when
+    // profiling.
+    pub(crate) fn set_epoch_callback<T: 'static>(
+        &self, store: &mut Store<T>, profiler: Option<Profiler>,
+    ) {
+        let deadline = self.deadline();
+        if deadline.is_none() && profiler.is_none() {
+            store.epoch_deadline_async_yield_and_update(1);
+        } else {
+            let started = Instant::now();
+            store.epoch_deadline_callback(move |store| {
+                if let Some(profiler) = &profiler {
+                    profiler.sample(&store);
+                }
+                if let Some(deadline) = deadline
+                    && started.elapsed() >= deadline
+                {
+                    return Err(DeadlineExceeded(deadline).into());
+                }
+                Ok(UpdateDeadline::Yield(1))
+            });
+        }
+        store.set_epoch_deadline(1);
+    }
+}
+
+/// Returned when a guest invocation runs past its configured deadline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeadlineExceeded(pub Duration);
+
+impl DeadlineExceeded {
+    /// Returns `true` if the error, or any of its causes, is a
+    /// [`DeadlineExceeded`] error.
+    #[must_use]
+    pub fn is(err: &anyhow::Error) -> bool {
+        err.chain().any(|e| e.is::<Self>())
+    }
+}
+
+impl Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "guest invocation exceeded deadline of {}ms", self.0.as_millis())
+    }
+}
+
+impl std::error::Error for DeadlineExceeded {}
+
+#[cfg(test)]
+mod tests {
+    use wasmtime::{Engine, Linker, Module};
+
+    use super::*;
+    use crate::create::start_epoch_ticker;
+
+    struct Ctx(Limiter);
+
+    impl LimiterView for Ctx {
+        fn limiter(&mut self) -> &mut Limiter {
+            &mut self.0
+        }
+    }
+
+    #[tokio::test]
+    async fn deadline_exceeded() {
+        let engine = Engine::new(&crate::engine::config(false)).expect("engine");
+        start_epoch_ticker(&engine).expect("epoch ticker");
+        let wasm = wat::parse_str(r#"(module (func (export "run") (loop br 0)))"#).expect("wat");
+        let module = Module::new(&engine, wasm).expect("module");
+
+        let options = RuntimeOptions {
+            deadline_ms: Some(50),
+            ..RuntimeOptions::default()
+        };
+        let mut store = Store::new(&engine, Ctx(options.limiter()));
+        options.configure_store(&mut store).expect("configure store");
+
+        let instance =
+            Linker::new(&engine).instantiate_async(&mut store, &module).await.expect("instance");
+        let run = instance.get_typed_func::<(), ()>(&mut store, "run").expect("run");
+
+        // the hot loop is interrupted rather than running forever
+        let result = tokio::time::timeout(Duration::from_secs(5), run.call_async(&mut store, ()))
+            .await
+            .expect("guest should be interrupted");
+        let err = result.expect_err("deadline should be exceeded");
+        assert!(DeadlineExceeded::is(&err));
+        assert_eq!(
+            err.downcast_ref::<DeadlineExceeded>(),
+            Some(&DeadlineExceeded(Duration::from_millis(50)))
+        );
+    }
+}
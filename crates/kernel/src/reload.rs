@@ -0,0 +1,161 @@
+//! # Hot Reload
+//!
+//! When the runtime is run with `--watch`, each guest's wasm file is watched
+//! for changes. A changed file is recompiled and pre-instantiated using the
+//! runtime's existing engine and linker, then swapped into the runtime's
+//! state. Invocations already in flight complete using the previous
+//! `InstancePre`, and backend connections are unaffected.
+
+use std::path::Path;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, SystemTime};
+use std::{fs, thread};
+
+use anyhow::Result;
+use wasmtime::component::InstancePre;
+use wasmtime_wasi::WasiView;
+
+use crate::create::Compiled;
+use crate::guests::Guests;
+use crate::shutdown::Shutdown;
+
+/// The interval at which the watched wasm file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The guest's `InstancePre`, shared by the runtime's servers, that is
+/// replaced when the guest is reloaded.
+pub struct SharedInstancePre<T: 'static> {
+    inner: Arc<RwLock<Loaded<T>>>,
+}
+
+struct Loaded<T: 'static> {
+    instance_pre: InstancePre<T>,
+    digest: String,
+}
+
+impl<T: 'static> SharedInstancePre<T> {
+    /// Share `instance_pre`, compiled from the wasm file with SHA-256
+    /// `digest`.
+    #[must_use]
+    pub fn new(instance_pre: InstancePre<T>, digest: impl Into<String>) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(Loaded {
+                instance_pre,
+                digest: digest.into(),
+            })),
+        }
+    }
+
+    /// The current `InstancePre`. The returned value is unaffected by later
+    /// reloads, so should be used for the duration of an invocation.
+    #[must_use]
+    pub fn get(&self) -> InstancePre<T> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner).instance_pre.clone()
+    }
+
+    /// The SHA-256 digest of the current `InstancePre`'s wasm file.
+    #[must_use]
+    pub fn digest(&self) -> String {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner).digest.clone()
+    }
+
+    /// Replace the current `InstancePre`.
+    pub fn swap(&self, instance_pre: InstancePre<T>, digest: impl Into<String>) {
+        *self.inner.write().unwrap_or_else(PoisonError::into_inner) = Loaded {
+            instance_pre,
+            digest: digest.into(),
+        };
+    }
+}
+
+impl<T: 'static> Clone for SharedInstancePre<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+/// Watch the wasm file of each compiled component, reloading the
+/// corresponding guest whenever its file changes.
+///
+/// Files are polled on a dedicated thread that exits once shutdown has been
+/// triggered. A file that fails to compile or link is logged and the current
+/// guest is kept.
+///
+/// # Errors
+///
+/// Returns an error if the watcher thread cannot be started.
+pub fn watch<T: WasiView + 'static>(
+    mut compiled: Compiled<T>, guests: Guests<T>, shutdown: Shutdown,
+) -> Result<()> {
+    let mut watched = compiled
+        .manifest()
+        .components
+        .iter()
+        .map(|spec| {
+            tracing::info!("watching {} for changes", spec.wasm.display());
+            let loaded = fingerprint(&spec.wasm);
+            (spec.name.clone(), spec.wasm.clone(), loaded, loaded)
+        })
+        .collect::<Vec<_>>();
+
+    thread::Builder::new().name("guest-watcher".into()).spawn(move || {
+        while !shutdown.is_triggered() {
+            thread::sleep(POLL_INTERVAL);
+
+            for (name, wasm, loaded, previous) in &mut watched {
+                // wait for the file to stop changing (e.g. while it is being
+                // written by the compiler) before reloading
+                let current = fingerprint(wasm);
+                if current.is_none() || current == *loaded || current != *previous {
+                    *previous = current;
+                    continue;
+                }
+                *loaded = current;
+
+                let Some(guest) = guests.get(name) else {
+                    continue;
+                };
+                tracing::info!("reloading {}", wasm.display());
+                match compiled.reload(name) {
+                    Ok((reloaded, digest)) => {
+                        guest.shared().swap(reloaded, digest);
+                        tracing::info!("reloaded {}", wasm.display());
+                    }
+                    Err(e) => tracing::error!("failed to reload {}: {e:#}", wasm.display()),
+                }
+            }
+        }
+    })?;
+
+    Ok(())
+}
+
+// Identifies a version of the file by its modification time and size.
+fn fingerprint(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn file_fingerprint() {
+        let file = env::temp_dir().join(format!("wrt-reload-{}.wasm", std::process::id()));
+        assert_eq!(fingerprint(&file), None);
+
+        fs::write(&file, b"\0asm").expect("should write file");
+        let original = fingerprint(&file).expect("should fingerprint file");
+        assert_eq!(fingerprint(&file), Some(original));
+
+        fs::write(&file, b"\0asm\x01").expect("should write file");
+        assert_ne!(fingerprint(&file), Some(original));
+
+        fs::remove_file(file).expect("should remove file");
+    }
+}
@@ -0,0 +1,417 @@
+//! # Host Call Recording
+//!
+//! Records the calls guests make to backends through the runtime's WASI
+//! hosts, with their arguments and results, so a misbehaving invocation can
+//! be reproduced locally. When replaying, recorded results are returned to
+//! the guest without calling the backend, allowing the invocation to be
+//! re-executed under a debugger.
+//!
+//! Recordings are JSON lines, one call per line. Calls are replayed by
+//! matching their interface, function and arguments, in the order they were
+//! recorded. Recordings do not depend on the backend used, so a recording
+//! made against production backends can be replayed by a runtime built with
+//! the default (in-memory) backends.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::Args;
+use futures::{FutureExt, future};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::traits::FutureResult;
+
+static HOST_CALLS: OnceLock<HostCalls> = OnceLock::new();
+
+/// The runtime's host call recorder. Calls are neither recorded nor replayed
+/// unless enabled when the runtime is created.
+#[must_use]
+pub fn host_calls() -> &'static HostCalls {
+    HOST_CALLS.get_or_init(HostCalls::default)
+}
+
+/// Host call recording options.
+#[derive(Args, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecordOptions {
+    /// Record the calls guests make to backends, with their arguments and
+    /// results, to this file.
+    #[arg(long, env = "RECORD_FILE", value_name = "FILE", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Replay the calls recorded in this file rather than calling backends.
+    #[arg(long, env = "REPLAY_FILE", value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+}
+
+impl RecordOptions {
+    /// Enable recording or replay of the runtime's host calls.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the recording cannot be created or read, or host
+    /// calls are already being recorded or replayed.
+    pub fn install(&self) -> Result<()> {
+        let calls = match (&self.record, &self.replay) {
+            (Some(path), _) => HostCalls::record(path)?,
+            (None, Some(path)) => HostCalls::replay(path)?,
+            (None, None) => return Ok(()),
+        };
+        if HOST_CALLS.set(calls).is_err() {
+            bail!("host call recording is already configured");
+        }
+        Ok(())
+    }
+}
+
+/// Records, or replays, the calls made by the runtime's hosts.
+#[derive(Default)]
+pub struct HostCalls {
+    mode: Mode,
+}
+
+#[derive(Default)]
+enum Mode {
+    #[default]
+    Live,
+    Record(Mutex<LineWriter<File>>),
+    Replay(Mutex<HashMap<String, VecDeque<Outcome>>>),
+}
+
+/// A recorded host call.
+#[derive(Debug, Deserialize, Serialize)]
+struct Call {
+    interface: String,
+    function: String,
+    args: Value,
+    result: Outcome,
+}
+
+/// The recorded result of a host call.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    Ok(Value),
+    Err(String),
+}
+
+impl Outcome {
+    fn of<T: Serialize>(result: &Result<T>) -> Self {
+        match result {
+            Ok(value) => match serde_json::to_value(value) {
+                Ok(value) => Self::Ok(value),
+                Err(e) => Self::Err(format!("result could not be recorded: {e}")),
+            },
+            Err(e) => Self::Err(format!("{e:#}")),
+        }
+    }
+}
+
+impl HostCalls {
+    // Record calls to the file at `path`, replacing any existing recording.
+    fn record(path: &Path) -> Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("creating recording {}", path.display()))?;
+        tracing::info!("recording host calls to {}", path.display());
+        Ok(Self {
+            mode: Mode::Record(Mutex::new(LineWriter::new(file))),
+        })
+    }
+
+    // Replay the calls recorded in the file at `path`.
+    fn replay(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("opening recording {}", path.display()))?;
+
+        let mut calls = HashMap::<String, VecDeque<Outcome>>::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let call: Call = serde_json::from_str(&line)
+                .with_context(|| format!("reading {} line {}", path.display(), number + 1))?;
+            let key = key(&call.interface, &call.function, &call.args);
+            calls.entry(key).or_default().push_back(call.result);
+        }
+
+        tracing::info!("replaying host calls from {}", path.display());
+        Ok(Self {
+            mode: Mode::Replay(Mutex::new(calls)),
+        })
+    }
+
+    /// Returns `true` when calls are neither recorded nor replayed.
+    #[must_use]
+    pub const fn is_live(&self) -> bool {
+        matches!(self.mode, Mode::Live)
+    }
+
+    /// Returns `true` when recorded results are returned instead of calling
+    /// backends.
+    #[must_use]
+    pub const fn is_replaying(&self) -> bool {
+        matches!(self.mode, Mode::Replay(_))
+    }
+
+    /// Make a host call, recording its arguments and result. When replaying,
+    /// the recorded result is returned and `call` is not made.
+    pub fn call<T>(
+        &'static self, interface: &str, function: &str, args: impl Serialize,
+        call: impl FnOnce() -> FutureResult<T>,
+    ) -> FutureResult<T>
+    where
+        T: Serialize + DeserializeOwned + Send + 'static,
+    {
+        match &self.mode {
+            Mode::Live => call(),
+            Mode::Record(_) => {
+                let (interface, function) = (interface.to_string(), function.to_string());
+                let args = to_value(args);
+                let future = call();
+                async move {
+                    let result = future.await;
+                    self.write(interface, function, args, Outcome::of(&result));
+                    result
+                }
+                .boxed()
+            }
+            Mode::Replay(_) => {
+                let result = self.next(interface, function, &to_value(args));
+                future::ready(result).boxed()
+            }
+        }
+    }
+
+    /// Make a synchronous host call, recording its arguments and result.
+    /// When replaying, the recorded result is returned and `call` is not
+    /// made.
+    ///
+    /// # Errors
+    ///
+    /// Returns the call's error or, when replaying, an error if the call was
+    /// not recorded.
+    pub fn call_sync<T>(
+        &self, interface: &str, function: &str, args: impl Serialize,
+        call: impl FnOnce() -> Result<T>,
+    ) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        match &self.mode {
+            Mode::Live => call(),
+            Mode::Record(_) => {
+                let args = to_value(args);
+                let result = call();
+                self.write(interface.to_string(), function.to_string(), args, Outcome::of(&result));
+                result
+            }
+            Mode::Replay(_) => self.next(interface, function, &to_value(args)),
+        }
+    }
+
+    fn write(&self, interface: String, function: String, args: Value, result: Outcome) {
+        let Mode::Record(writer) = &self.mode else {
+            return;
+        };
+        let call = Call {
+            interface,
+            function,
+            args,
+            result,
+        };
+        let written = serde_json::to_string(&call).map_err(anyhow::Error::from).and_then(|line| {
+            let mut writer = writer.lock().unwrap_or_else(PoisonError::into_inner);
+            Ok(writeln!(writer, "{line}")?)
+        });
+        if let Err(e) = written {
+            tracing::warn!("failed to record {} {}: {e}", call.interface, call.function);
+        }
+    }
+
+    fn next<T: DeserializeOwned>(
+        &self, interface: &str, function: &str, args: &Value,
+    ) -> Result<T> {
+        let Mode::Replay(calls) = &self.mode else {
+            bail!("host calls are not being replayed");
+        };
+        let outcome = calls
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_mut(&key(interface, function, args))
+            .and_then(VecDeque::pop_front);
+
+        match outcome {
+            Some(Outcome::Ok(value)) => serde_json::from_value(value)
+                .with_context(|| format!("replaying {interface} {function}")),
+            Some(Outcome::Err(e)) => Err(anyhow!(e)),
+            None => bail!("no recorded call to {interface} {function} with arguments {args}"),
+        }
+    }
+}
+
+/// A resource opened by a backend, such as a key-value bucket, whose calls
+/// are recorded or replayed.
+///
+/// Calls are identified by the resource's interface and id, so calls to
+/// different resources are replayed independently. When replaying, resources
+/// are not opened, so only hold an id.
+#[derive(Clone, Debug)]
+pub struct Recorded<R> {
+    interface: &'static str,
+    id: String,
+    resource: Option<R>,
+}
+
+impl<R: Send + 'static> Recorded<R> {
+    /// Open the resource identified by `id`, recording whether it could be
+    /// opened. When replaying, `open` is not called.
+    pub fn open(
+        interface: &'static str, function: &'static str, id: impl Into<String>,
+        open: impl FnOnce() -> FutureResult<R>,
+    ) -> FutureResult<Self> {
+        let calls = host_calls();
+        let id = id.into();
+
+        if calls.is_replaying() {
+            let result = calls.next::<()>(interface, function, &to_value(&id));
+            return future::ready(result.map(|()| Self {
+                interface,
+                id,
+                resource: None,
+            }))
+            .boxed();
+        }
+
+        let future = open();
+        async move {
+            let result = future.await;
+            let outcome = match &result {
+                Ok(_) => Outcome::Ok(Value::Null),
+                Err(e) => Outcome::Err(format!("{e:#}")),
+            };
+            calls.write(interface.to_string(), function.to_string(), to_value(&id), outcome);
+            result.map(|resource| Self {
+                interface,
+                id,
+                resource: Some(resource),
+            })
+        }
+        .boxed()
+    }
+}
+
+impl<R> Recorded<R> {
+    /// The id of the resource.
+    #[must_use]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The opened resource, or `None` when replaying.
+    #[must_use]
+    pub const fn resource(&self) -> Option<&R> {
+        self.resource.as_ref()
+    }
+
+    /// Call `function` on the resource, recording (or replaying) its result.
+    pub fn call<T>(
+        &self, function: &str, args: impl Serialize, call: impl FnOnce(&R) -> FutureResult<T>,
+    ) -> FutureResult<T>
+    where
+        T: Serialize + DeserializeOwned + Send + 'static,
+    {
+        host_calls().call(self.interface, function, (&self.id, args), || {
+            self.resource.as_ref().map_or_else(
+                || future::ready(Err(anyhow!("{} is not open", self.id))).boxed(),
+                call,
+            )
+        })
+    }
+
+    /// Call synchronous `function` on the resource, recording (or replaying)
+    /// its result.
+    ///
+    /// # Errors
+    ///
+    /// Returns the call's error or, when replaying, an error if the call was
+    /// not recorded.
+    pub fn call_sync<T>(
+        &self, function: &str, args: impl Serialize, call: impl FnOnce(&R) -> Result<T>,
+    ) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        host_calls().call_sync(self.interface, function, (&self.id, args), || {
+            self.resource.as_ref().map_or_else(|| Err(anyhow!("{} is not open", self.id)), call)
+        })
+    }
+}
+
+fn to_value(value: impl Serialize) -> Value {
+    serde_json::to_value(value).unwrap_or_else(|e| {
+        tracing::warn!("host call arguments could not be recorded: {e}");
+        Value::Null
+    })
+}
+
+fn key(interface: &str, function: &str, args: &Value) -> String {
+    format!("{interface} {function} {args}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn record_replay() {
+        let path = std::env::temp_dir().join(format!("host-calls-{}.jsonl", std::process::id()));
+
+        let recorder: &'static HostCalls = Box::leak(Box::new(HostCalls::record(&path).unwrap()));
+        let value = recorder
+            .call("wasi:keyvalue/store", "get", ("orders", "1"), || {
+                async { Ok(Some(b"order".to_vec())) }.boxed()
+            })
+            .await
+            .expect("should record value");
+        assert_eq!(value, Some(b"order".to_vec()));
+        recorder
+            .call::<()>("wasi:keyvalue/store", "set", ("orders", "2"), || {
+                async { Err(anyhow!("unavailable")) }.boxed()
+            })
+            .await
+            .expect_err("should record error");
+
+        let replayer: &'static HostCalls = Box::leak(Box::new(HostCalls::replay(&path).unwrap()));
+        let value = replayer
+            .call::<Option<Vec<u8>>>("wasi:keyvalue/store", "get", ("orders", "1"), || {
+                unreachable!("backend should not be called")
+            })
+            .await
+            .expect("should replay value");
+        assert_eq!(value, Some(b"order".to_vec()));
+
+        let err = replayer
+            .call::<()>("wasi:keyvalue/store", "set", ("orders", "2"), || {
+                unreachable!("backend should not be called")
+            })
+            .await
+            .expect_err("should replay error");
+        assert_eq!(err.to_string(), "unavailable");
+
+        // each recorded call is replayed once
+        replayer
+            .call::<Option<Vec<u8>>>("wasi:keyvalue/store", "get", ("orders", "1"), || {
+                unreachable!("backend should not be called")
+            })
+            .await
+            .expect_err("should not replay call twice");
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
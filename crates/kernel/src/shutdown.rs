@@ -0,0 +1,239 @@
+//! # Graceful Shutdown
+//!
+//! Coordinates stopping the runtime's servers when the process is asked to
+//! terminate. Servers stop accepting new work once shutdown is triggered and
+//! use [`InFlight`] to wait (up to a grace period) for in-flight guest
+//! invocations to complete.
+//!
+//! Runtimes embedded in another application start each server separately,
+//! stopping it using its [`ServerHandle`].
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+/// A signal, shared by all servers, that the runtime is shutting down.
+#[derive(Clone, Debug, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+}
+
+impl Shutdown {
+    /// Create a new, untriggered, shutdown signal.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A signal triggered when this signal is triggered, or by itself,
+    /// without triggering this signal.
+    #[must_use]
+    pub fn child(&self) -> Self {
+        Self {
+            token: self.token.child_token(),
+        }
+    }
+
+    /// Trigger shutdown.
+    pub fn trigger(&self) {
+        self.token.cancel();
+    }
+
+    /// Returns `true` once shutdown has been triggered.
+    #[must_use]
+    pub fn is_triggered(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Completes once shutdown has been triggered.
+    pub async fn triggered(&self) {
+        self.token.cancelled().await;
+    }
+
+    /// Trigger shutdown when the process receives SIGINT or SIGTERM.
+    pub fn listen(&self) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = signal().await {
+                tracing::error!("failed to listen for shutdown signal: {e}");
+                return;
+            }
+            tracing::info!("shutdown signal received");
+            shutdown.trigger();
+        });
+    }
+}
+
+/// Completes when the process receives SIGINT (Ctrl-C) or, on Unix, SIGTERM.
+async fn signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result?,
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+
+    Ok(())
+}
+
+/// Tracks a server's in-flight work so it can be drained on shutdown.
+#[derive(Clone, Debug, Default)]
+pub struct InFlight {
+    tracker: TaskTracker,
+}
+
+impl InFlight {
+    /// Spawn a task, tracking it until it completes.
+    pub fn spawn<F>(&self, task: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.tracker.spawn(task)
+    }
+
+    /// The number of tasks still in flight.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.tracker.len()
+    }
+
+    /// Returns `true` when no tasks are in flight.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.tracker.is_empty()
+    }
+
+    /// Wait up to `grace_period` for in-flight tasks to complete.
+    ///
+    /// Returns `false` if tasks were still running when the grace period
+    /// expired.
+    pub async fn drain(&self, grace_period: Duration) -> bool {
+        self.tracker.close();
+        if tokio::time::timeout(grace_period, self.tracker.wait()).await.is_err() {
+            tracing::warn!(
+                "{} task(s) still in flight after {}ms grace period",
+                self.len(),
+                grace_period.as_millis()
+            );
+            return false;
+        }
+        true
+    }
+}
+
+/// A running server, stopped independently of the runtime's other servers.
+#[derive(Debug)]
+pub struct ServerHandle {
+    name: String,
+    shutdown: Shutdown,
+    task: JoinHandle<Result<()>>,
+}
+
+impl ServerHandle {
+    /// Spawn the server, `server`, stopped by triggering `shutdown`.
+    pub fn spawn<F>(name: impl Into<String>, shutdown: Shutdown, server: F) -> Self
+    where
+        F: Future<Output = Result<()>> + Send + 'static,
+    {
+        Self {
+            name: name.into(),
+            shutdown,
+            task: tokio::spawn(server),
+        }
+    }
+
+    /// The name of the server.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns `true` once the server has stopped.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Stop the server, waiting for it to shut down gracefully.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server failed or panicked.
+    pub async fn stop(self) -> Result<()> {
+        self.shutdown.trigger();
+        self.wait().await
+    }
+
+    /// Wait for the server to stop.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server failed or panicked.
+    pub async fn wait(self) -> Result<()> {
+        let name = self.name;
+        self.task.await.with_context(|| format!("{name} server panicked"))?
+    }
+}
+
+/// Flush and shut down the runtime's telemetry providers.
+pub fn flush_telemetry() {
+    tracing::info!("flushing telemetry");
+    if let Err(e) = credibil_otel::init::shutdown() {
+        tracing::error!("failed to flush telemetry: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn drain_in_flight() {
+        let in_flight = InFlight::default();
+        in_flight.spawn(tokio::time::sleep(Duration::from_millis(10)));
+        assert!(in_flight.drain(Duration::from_secs(1)).await);
+        assert!(in_flight.is_empty());
+
+        let in_flight = InFlight::default();
+        in_flight.spawn(tokio::time::sleep(Duration::from_secs(10)));
+        assert!(!in_flight.drain(Duration::from_millis(10)).await);
+        assert_eq!(in_flight.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn trigger() {
+        let shutdown = Shutdown::new();
+        assert!(!shutdown.is_triggered());
+
+        shutdown.clone().trigger();
+        shutdown.triggered().await;
+        assert!(shutdown.is_triggered());
+    }
+
+    #[tokio::test]
+    async fn stop_server() {
+        let shutdown = Shutdown::new();
+        let (first, second) = (shutdown.child(), shutdown.child());
+
+        let server = first.clone();
+        let handle = ServerHandle::spawn("first", first, async move {
+            server.triggered().await;
+            Ok(())
+        });
+        handle.stop().await.expect("should stop server");
+        assert!(!second.is_triggered());
+
+        shutdown.trigger();
+        assert!(second.is_triggered());
+    }
+}
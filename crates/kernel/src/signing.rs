@@ -0,0 +1,221 @@
+//! # Component Signing
+//!
+//! Components and compiled artifacts can be signed using an ed25519 key. The
+//! signature is written to a detached `<file>.sig` file alongside the signed
+//! file and verified against the runtime's trusted keys before the file is
+//! loaded.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use base64ct::{Base64, Encoding};
+use clap::Args;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+/// The trust policy applied to components before they are loaded.
+#[derive(Args, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TrustOptions {
+    /// A public key trusted to sign components. When set, components must
+    /// be signed by one of the trusted keys, and the compile cache is not
+    /// used. May be repeated.
+    #[arg(long = "trusted-key", env = "TRUSTED_KEYS", value_delimiter = ',', value_name = "PATH")]
+    pub trusted_keys: Vec<PathBuf>,
+
+    /// Load components that are unsigned, logging a warning rather than
+    /// refusing to load them. Signed components must still pass
+    /// verification.
+    #[arg(long, env = "ALLOW_UNSIGNED")]
+    pub allow_unsigned: bool,
+}
+
+impl TrustOptions {
+    /// Verify `contents`, read from `file`, were signed by a trusted key.
+    /// Files are not verified when no trusted keys are configured.
+    ///
+    /// The caller should load the same `contents` it verified, rather than
+    /// reading the file again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file's signature is invalid or not from a
+    /// trusted key, or if the file is unsigned and unsigned files are not
+    /// allowed.
+    pub fn verify(&self, file: &Path, contents: &[u8]) -> Result<()> {
+        if self.trusted_keys.is_empty() {
+            return Ok(());
+        }
+
+        let sig_path = signature_path(file);
+        if !sig_path.exists() {
+            if self.allow_unsigned {
+                tracing::warn!("loading unsigned component {}", file.display());
+                return Ok(());
+            }
+            bail!(
+                "refusing to load {}: not signed, {} not found",
+                file.display(),
+                sig_path.display()
+            );
+        }
+
+        self.check(file, &sig_path, contents)
+            .with_context(|| format!("refusing to load {}", file.display()))?;
+        tracing::info!("verified signature for {}", file.display());
+        Ok(())
+    }
+
+    fn check(&self, file: &Path, sig_path: &Path, contents: &[u8]) -> Result<()> {
+        let signature = Signature::from_slice(&read_base64(sig_path)?)
+            .with_context(|| format!("invalid signature {}", sig_path.display()))?;
+
+        for key_path in &self.trusted_keys {
+            let key = verifying_key(key_path)?;
+            if key.verify_strict(contents, &signature).is_ok() {
+                return Ok(());
+            }
+        }
+        bail!("{} is not signed by a trusted key", file.display())
+    }
+}
+
+/// Sign `file` using the ed25519 signing key at `key`, writing the detached
+/// signature to `<file>.sig`. Keys are created using [`generate_key`].
+///
+/// # Errors
+///
+/// Returns an error if the key does not exist, the file or key cannot be
+/// read, or the signature cannot be written.
+pub fn sign(file: &Path, key: &Path) -> Result<PathBuf> {
+    if !key.exists() {
+        bail!("signing key {} not found", key.display());
+    }
+    let signing_key = signing_key(key)?;
+
+    let contents = fs::read(file).with_context(|| format!("reading {}", file.display()))?;
+    let signature = signing_key.sign(&contents);
+
+    let sig_path = signature_path(file);
+    fs::write(&sig_path, Base64::encode_string(&signature.to_bytes()))?;
+    Ok(sig_path)
+}
+
+/// Generate a new ed25519 signing key, saved to `key`, writing its public key
+/// to `<key>.pub` for use as a trusted key. Returns the public key's path.
+///
+/// # Errors
+///
+/// Returns an error if `key` already exists or either key cannot be written.
+pub fn generate_key(key: &Path) -> Result<PathBuf> {
+    let signing_key = SigningKey::from_bytes(&rand::random());
+    write_secret(key, &Base64::encode_string(signing_key.as_bytes()))
+        .with_context(|| format!("writing signing key {}", key.display()))?;
+
+    let mut pub_path = key.as_os_str().to_owned();
+    pub_path.push(".pub");
+    let pub_path = PathBuf::from(pub_path);
+    fs::write(&pub_path, Base64::encode_string(signing_key.verifying_key().as_bytes()))?;
+
+    tracing::info!("generated signing key {}", key.display());
+    Ok(pub_path)
+}
+
+/// The path of the detached signature for `file`.
+#[must_use]
+pub fn signature_path(file: &Path) -> PathBuf {
+    let mut path = file.as_os_str().to_owned();
+    path.push(".sig");
+    PathBuf::from(path)
+}
+
+fn signing_key(path: &Path) -> Result<SigningKey> {
+    let bytes = read_base64(path)?;
+    let seed = bytes.try_into().map_err(|bytes: Vec<u8>| {
+        anyhow!("invalid signing key {}: expected 32 bytes, found {}", path.display(), bytes.len())
+    })?;
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+fn verifying_key(path: &Path) -> Result<VerifyingKey> {
+    let bytes = read_base64(path)?;
+    let bytes = bytes.try_into().map_err(|bytes: Vec<u8>| {
+        anyhow!("invalid public key {}: expected 32 bytes, found {}", path.display(), bytes.len())
+    })?;
+    VerifyingKey::from_bytes(&bytes)
+        .with_context(|| format!("invalid public key {}", path.display()))
+}
+
+fn read_base64(path: &Path) -> Result<Vec<u8>> {
+    let encoded =
+        fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    Base64::decode_vec(encoded.trim()).map_err(|e| anyhow!("decoding {}: {e}", path.display()))
+}
+
+// Write the secret key so that only the current user can read it.
+#[cfg(unix)]
+fn write_secret(path: &Path, contents: &str) -> Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut file = fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(path)?;
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn write_secret(path: &Path, contents: &str) -> Result<()> {
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn sign_and_verify() {
+        let dir = env::temp_dir().join(format!("wrt-signing-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("should create dir");
+        let file = dir.join("guest.wasm");
+        let key = dir.join("signing.key");
+        fs::write(&file, b"\0asm").expect("should write file");
+
+        let trust = TrustOptions {
+            trusted_keys: vec![dir.join("signing.key.pub")],
+            allow_unsigned: false,
+        };
+
+        // unsigned
+        trust.verify(&file, b"\0asm").expect_err("should refuse unsigned file");
+
+        // keys are not generated implicitly
+        let err = sign(&file, &key).expect_err("should not sign with a missing key");
+        assert!(err.to_string().contains("not found"));
+        generate_key(&key).expect("should generate key");
+        generate_key(&key).expect_err("should not overwrite key");
+
+        // signed by a trusted key
+        sign(&file, &key).expect("should sign");
+        trust.verify(&file, b"\0asm").expect("should verify");
+
+        // modified after signing
+        let err = trust.verify(&file, b"\0asm\x01").expect_err("should fail verification");
+        assert!(format!("{err:#}").contains("not signed by a trusted key"));
+
+        // allowing unsigned files does not allow tampered or invalid ones
+        let trust = TrustOptions {
+            allow_unsigned: true,
+            ..trust
+        };
+        trust.verify(&file, b"\0asm\x01").expect_err("should refuse tampered file");
+        fs::write(signature_path(&file), "invalid").expect("should write signature");
+        trust.verify(&file, b"\0asm").expect_err("should refuse invalid signature");
+
+        fs::remove_file(signature_path(&file)).expect("should remove signature");
+        trust.verify(&file, b"\0asm").expect("should allow unsigned file");
+
+        fs::remove_dir_all(dir).expect("should remove dir");
+    }
+}
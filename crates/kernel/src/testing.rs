@@ -0,0 +1,88 @@
+//! # Test Harness
+//!
+//! Runs guests in-process, without starting the runtime's servers, so they
+//! can be tested from a plain `#[tokio::test]`. A runtime generated without
+//! a `main` function provides a `runtime::harness` function that loads a
+//! wasm component, links the runtime's hosts and connects to its backends:
+//!
+//! ```ignore
+//! use wasi_http::{HttpDefault, HttpHarness, WasiHttp};
+//! use wasi_keyvalue::{KeyValueDefault, WasiKeyValue};
+//!
+//! buildgen::runtime!({
+//!     WasiHttp: HttpDefault,
+//!     WasiKeyValue: KeyValueDefault,
+//! });
+//!
+//! #[tokio::test]
+//! async fn create_order() {
+//!     let harness = runtime::harness("guest.wasm").await.expect("should load guest");
+//!
+//!     let request = http::Request::post("http://localhost/orders").body("{}").unwrap();
+//!     let response = harness.http(request).await.expect("should handle request");
+//!     assert_eq!(response.status(), 201);
+//!
+//!     let kv = harness.state().key_value_default.get().await.unwrap();
+//!     assert!(kv.value("orders", "1").is_some());
+//! }
+//! ```
+//!
+//! Each host crate provides typed entry points (such as `HttpHarness` and
+//! `MessagingHarness`) implemented for [`Harness`]. Backend state can be
+//! inspected using the runtime's (public) backend connections.
+//!
+//! See `examples/harness` for a complete example.
+
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use wasmtime_wasi::WasiView;
+
+use crate::create::{Compiled, load as load_manifest, start_epoch_ticker};
+use crate::guests::Guest;
+use crate::manifest::Manifest;
+use crate::options::RuntimeOptions;
+use crate::traits::State;
+
+/// Load the wasm guest (or manifest of guests) at `path` for testing.
+///
+/// Unlike [`create`](crate::create), telemetry is not initialized, so
+/// several harnesses can be created in the same test process.
+///
+/// # Errors
+///
+/// Will fail if a wasm file cannot be loaded.
+pub fn load<T: WasiView + 'static>(path: &Path, options: &RuntimeOptions) -> Result<Compiled<T>> {
+    let manifest = Manifest::from_path(path)?;
+    let compiled = load_manifest(&manifest, options)?;
+    start_epoch_ticker(compiled.engine())?;
+    Ok(compiled)
+}
+
+/// A runtime's state, used to invoke its guests in tests.
+pub struct Harness<S: State> {
+    state: S,
+}
+
+impl<S: State> Harness<S> {
+    /// Create a harness for the runtime state.
+    #[must_use]
+    pub const fn new(state: S) -> Self {
+        Self { state }
+    }
+
+    /// The runtime's state, including its backend connections.
+    #[must_use]
+    pub const fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// The guest named `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime has no such guest.
+    pub fn guest(&self, name: &str) -> Result<&Guest<S::StoreCtx>> {
+        self.state.guests().get(name).ok_or_else(|| anyhow!("no guest named `{name}`"))
+    }
+}
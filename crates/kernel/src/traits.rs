@@ -5,21 +5,72 @@
 //! Each service is a module that provides a concrete implementation in support
 //! of a specific set of WASI interfaces.
 
+use std::env;
 use std::fmt::Debug;
+use std::sync::Arc;
 
 use anyhow::Result;
 use futures::future::BoxFuture;
-use wasmtime::component::{InstancePre, Linker};
+use wasmtime::Store;
+use wasmtime::component::{Linker, ResourceTable};
+
+use crate::admin::RuntimeInfo;
+use crate::backends::{Backends, RetryPolicy};
+use crate::capabilities::Capabilities;
+use crate::guests::{Guest, Guests};
+use crate::limits::LimiterView;
+use crate::options::RuntimeOptions;
+use crate::shutdown::Shutdown;
+use crate::wit::HostWit;
 
 pub type FutureResult<T> = BoxFuture<'static, Result<T>>;
 
 pub trait State: Clone + Send + Sync + 'static {
-    type StoreCtx: Send;
+    type StoreCtx: LimiterView + Send + 'static;
 
-    #[must_use]
-    fn store(&self) -> Self::StoreCtx;
+    /// Create the store data for an invocation of `guest`, connecting to
+    /// backends not yet connected.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a backend cannot be connected.
+    fn store(
+        &self, guest: &Guest<Self::StoreCtx>,
+    ) -> impl Future<Output = Result<Self::StoreCtx>> + Send;
+
+    /// The components hosted by the runtime.
+    fn guests(&self) -> &Guests<Self::StoreCtx>;
+
+    /// The runtime's backend connections.
+    fn backends(&self) -> &Backends;
 
-    fn instance_pre(&self) -> &InstancePre<Self::StoreCtx>;
+    /// Information about the runtime.
+    fn info(&self) -> &RuntimeInfo;
+
+    /// The options applied to each guest invocation.
+    fn options(&self) -> &RuntimeOptions;
+
+    /// The signal used to notify servers the runtime is shutting down.
+    fn shutdown(&self) -> &Shutdown;
+
+    /// Create a new `Store` for a single invocation of `guest` with the
+    /// runtime's per-invocation options (deadline, resource limits, etc.)
+    /// applied.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a backend cannot be connected or the options
+    /// cannot be applied to the `Store`.
+    fn new_store(
+        &self, guest: &Guest<Self::StoreCtx>,
+    ) -> impl Future<Output = Result<Store<Self::StoreCtx>>> + Send {
+        async move {
+            let data = self.store(guest).await?;
+            let mut store = Store::new(guest.instance_pre().engine(), data);
+            self.options().configure_store(&mut store)?;
+            Ok(store)
+        }
+    }
 }
 
 /// Implemented by all WASI hosts in order to allow the runtime to link their
@@ -33,13 +84,49 @@ pub trait Host<T>: Debug + Sync + Send {
     fn add_to_linker(linker: &mut Linker<T>) -> Result<()>;
 }
 
+/// Implemented by hosts outside the built-in `wasi-*` crates so runtimes
+/// generated by `buildgen::runtime!` can hold the host's context in their
+/// store. The host's bindings access the context using [`HostView`].
+pub trait WasiHost: 'static {
+    /// The context used by the host's bindings, implemented by each of its
+    /// backends. Typically a trait object, e.g. `dyn AcmeLedgerCtx`.
+    type Ctx: ?Sized + Send + Sync;
+
+    /// The host's interfaces, added to the runtime's WIT world.
+    const WIT: HostWit = HostWit {
+        world: &[],
+        deps: &[],
+    };
+}
+
+/// Gives a host's bindings access to its context. Implemented for each
+/// [`WasiHost`] by the `T` in `Linker<T>`.
+pub trait HostView<H: WasiHost>: Send {
+    /// Return a [`HostCtxView`] from mutable reference to self.
+    fn host(&mut self) -> HostCtxView<'_, H>;
+}
+
+/// View into a [`WasiHost`]'s context and the [`ResourceTable`].
+pub struct HostCtxView<'a, H: WasiHost> {
+    /// Mutable reference to the host's context.
+    pub ctx: &'a mut H::Ctx,
+
+    /// Mutable reference to table used to manage resources.
+    pub table: &'a mut ResourceTable,
+
+    /// The interfaces and named resources the guest may use.
+    pub capabilities: &'a Capabilities,
+}
+
 /// Implemented by WASI hosts that are servers in order to allow the runtime to
 /// start them.
 pub trait Server<S: State>: Debug + Sync + Send {
     /// Start the service.
     ///
     /// This is typically implemented by services that instantiate (or run)
-    /// wasm components.
+    /// wasm components. Once `state.shutdown()` is triggered, the service
+    /// should stop accepting new work, wait up to the configured grace period
+    /// for in-flight guest invocations to complete, and return.
     #[allow(unused_variables)]
     fn run(&self, state: &S) -> impl Future<Output = Result<()>> {
         async { Ok(()) }
@@ -49,15 +136,48 @@ pub trait Server<S: State>: Debug + Sync + Send {
 /// Implemented by backend resources to allow the backend to be connected to a
 /// WASI component.
 pub trait Backend: Sized + Sync + Send {
-    type ConnectOptions: FromEnv;
+    type ConnectOptions: FromEnv + Send;
 
     /// Connect to the resource.
     #[must_use]
-    fn connect() -> impl Future<Output = Result<Self>> {
+    fn connect() -> impl Future<Output = Result<Self>> + Send {
         async { Self::connect_with(Self::ConnectOptions::from_env()?).await }
     }
 
-    fn connect_with(options: Self::ConnectOptions) -> impl Future<Output = Result<Self>>;
+    fn connect_with(options: Self::ConnectOptions) -> impl Future<Output = Result<Self>> + Send;
+
+    /// Connect to the instance of the resource named `instance`, for hosts
+    /// using several instances of the backend.
+    fn connect_instance(instance: &str) -> impl Future<Output = Result<Self>> + Send {
+        let options = Self::ConnectOptions::from_instance_env(instance);
+        async move { Self::connect_with(options?).await }
+    }
+
+    /// The policy used to retry failed connection attempts. Defaults to the
+    /// runtime's policy, `default`.
+    #[must_use]
+    fn retry_policy(default: RetryPolicy) -> RetryPolicy {
+        default
+    }
+
+    /// Check the connection to the resource is healthy. Backends without a
+    /// meaningful check are always healthy.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing why the connection is unhealthy.
+    fn health(&self) -> impl Future<Output = Result<()>> + Send {
+        async { Ok(()) }
+    }
+
+    /// The connection used by an invocation of a guest granted
+    /// `capabilities`. Access to named resources is usually checked by the
+    /// host, so only backends acting as their host's context (such as
+    /// outgoing HTTP) need restrict the connection.
+    #[must_use]
+    fn for_guest(self, _: &Arc<Capabilities>) -> Self {
+        self
+    }
 }
 
 pub trait FromEnv: Sized {
@@ -67,6 +187,28 @@ pub trait FromEnv: Sized {
     ///
     /// Returns an error if required environment variables are missing or invalid.
     fn from_env() -> Result<Self>;
+
+    /// Create connection options for the backend instance named `instance`.
+    /// Defaults to [`FromEnv::from_env`], so every instance is configured
+    /// alike. Backends connecting to a server or file should read options
+    /// that differ between instances using [`instance_var`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if required environment variables are missing or invalid.
+    #[allow(unused_variables)]
+    fn from_instance_env(instance: &str) -> Result<Self> {
+        Self::from_env()
+    }
+}
+
+/// The value of the environment variable `var` for the backend instance named
+/// `instance`: `<INSTANCE>_<VAR>` (e.g. `AUDIT_SQL_DATABASE` for `"audit"`),
+/// falling back to `var`.
+#[must_use]
+pub fn instance_var(instance: &str, var: &str) -> Option<String> {
+    let prefix = instance.to_uppercase().replace('-', "_");
+    env::var(format!("{prefix}_{var}")).or_else(|_| env::var(var)).ok()
 }
 
 // pub trait WasiHostCtx: Debug + Send + Sync + 'static {}
@@ -0,0 +1,228 @@
+//! # WASI Context Policy
+//!
+//! Controls what each guest is given through its WASI context: environment
+//! variables, arguments, preopened directories and stdio. By default guests
+//! receive no host environment variables, no directories and no stdin, so
+//! backend credentials held in the runtime's environment are not exposed to
+//! guests.
+
+use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow, bail};
+use clap::{Args, ValueEnum};
+use wasmtime_wasi::{DirPerms, FilePerms, WasiCtx, WasiCtxBuilder};
+
+/// The WASI context policy applied to guests.
+#[derive(Args, Clone, Debug, Default, PartialEq, Eq)]
+pub struct WasiOptions {
+    /// An environment variable passed to guests, as `NAME` (passing the
+    /// runtime's value) or `NAME=VALUE`. May be repeated.
+    #[arg(long = "env", env = "GUEST_ENV", value_delimiter = ',', value_name = "NAME[=VALUE]")]
+    pub env: Vec<String>,
+
+    /// Pass the runtime's environment variables starting with this prefix to
+    /// guests. May be repeated.
+    #[arg(
+        long = "env-prefix",
+        env = "GUEST_ENV_PREFIXES",
+        value_delimiter = ',',
+        value_name = "PREFIX"
+    )]
+    pub env_prefixes: Vec<String>,
+
+    /// An argument passed to guests, following the component name. May be
+    /// repeated.
+    #[arg(
+        long = "arg",
+        env = "GUEST_ARGS",
+        value_delimiter = ',',
+        value_name = "ARG",
+        allow_hyphen_values = true
+    )]
+    pub args: Vec<String>,
+
+    /// A directory preopened read-only for guests, as `HOST[::GUEST]`. May be
+    /// repeated.
+    #[arg(long = "dir", env = "GUEST_DIRS", value_delimiter = ',', value_name = "HOST[::GUEST]")]
+    pub dirs: Vec<Preopen>,
+
+    /// A directory preopened read-write for guests, as `HOST[::GUEST]`. May
+    /// be repeated.
+    #[arg(
+        long = "dir-rw",
+        env = "GUEST_DIRS_RW",
+        value_delimiter = ',',
+        value_name = "HOST[::GUEST]"
+    )]
+    pub dirs_rw: Vec<Preopen>,
+
+    /// The runtime's stdio streams made available to guests.
+    #[arg(long, env = "GUEST_STDIO", value_enum, default_value_t)]
+    pub stdio: Stdio,
+}
+
+impl WasiOptions {
+    /// Check the policy can be applied, in particular that preopened
+    /// directories exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a preopened directory does not exist.
+    pub fn validate(&self) -> Result<()> {
+        for preopen in self.dirs.iter().chain(&self.dirs_rw) {
+            if !preopen.host.is_dir() {
+                bail!("preopened directory {} does not exist", preopen.host.display());
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the WASI context for an invocation of `component`.
+    ///
+    /// The component's name is passed as the first argument and as the
+    /// `COMPONENT` environment variable.
+    #[must_use]
+    pub fn ctx(&self, component: &str) -> WasiCtx {
+        let mut builder = WasiCtxBuilder::new();
+
+        builder.arg(component);
+        for arg in &self.args {
+            builder.arg(arg);
+        }
+        for (name, value) in self.envs(env::vars()) {
+            builder.env(name, value);
+        }
+        builder.env("COMPONENT", component);
+
+        for (preopen, writable) in
+            self.dirs.iter().map(|p| (p, false)).chain(self.dirs_rw.iter().map(|p| (p, true)))
+        {
+            if let Err(e) = preopen.add_to(&mut builder, writable) {
+                tracing::warn!("failed to preopen {}: {e}", preopen.host.display());
+            }
+        }
+
+        match self.stdio {
+            Stdio::None => {}
+            Stdio::Output => {
+                builder.stdout(tokio::io::stdout()).stderr(tokio::io::stderr());
+            }
+            Stdio::Inherit => {
+                builder.inherit_stdin().stdout(tokio::io::stdout()).stderr(tokio::io::stderr());
+            }
+        }
+
+        builder.build()
+    }
+
+    // The environment variables passed to guests, selected from `vars`.
+    fn envs(&self, vars: impl Iterator<Item = (String, String)>) -> Vec<(String, String)> {
+        let mut envs = vars
+            .filter(|(name, _)| {
+                self.env.contains(name)
+                    || self.env_prefixes.iter().any(|prefix| name.starts_with(prefix.as_str()))
+            })
+            .collect::<Vec<_>>();
+        for var in &self.env {
+            if let Some((name, value)) = var.split_once('=') {
+                envs.push((name.to_string(), value.to_string()));
+            }
+        }
+        envs
+    }
+}
+
+/// The runtime's stdio streams made available to guests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Stdio {
+    /// No stdio.
+    None,
+
+    /// Guest stdout and stderr are written to the runtime's stdout and
+    /// stderr.
+    #[default]
+    Output,
+
+    /// Stdout and stderr, plus the runtime's stdin.
+    Inherit,
+}
+
+/// A directory preopened for guests.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Preopen {
+    /// The directory on the host.
+    pub host: PathBuf,
+
+    /// The path the directory is mounted at in the guest.
+    pub guest: String,
+}
+
+impl Preopen {
+    fn add_to(&self, builder: &mut WasiCtxBuilder, writable: bool) -> Result<()> {
+        let (dir_perms, file_perms) = if writable {
+            (DirPerms::all(), FilePerms::all())
+        } else {
+            (DirPerms::READ, FilePerms::READ)
+        };
+        builder.preopened_dir(&self.host, &self.guest, dir_perms, file_perms)?;
+        Ok(())
+    }
+}
+
+impl FromStr for Preopen {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (host, guest) = s.split_once("::").unwrap_or((s, s));
+        if host.is_empty() || guest.is_empty() {
+            return Err(anyhow!("invalid directory `{s}`, expected `HOST[::GUEST]`"));
+        }
+        Ok(Self {
+            host: PathBuf::from(host),
+            guest: guest.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_env() {
+        let options = WasiOptions {
+            env: vec!["LOG_LEVEL".into(), "MODE=test".into()],
+            env_prefixes: vec!["APP_".into()],
+            ..WasiOptions::default()
+        };
+        let vars = [
+            ("LOG_LEVEL", "debug"),
+            ("APP_NAME", "orders"),
+            ("DATABASE_PASSWORD", "secret"),
+            ("MODE", "prod"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+
+        let mut envs = options.envs(vars.into_iter());
+        envs.sort();
+        assert_eq!(
+            envs,
+            [("APP_NAME", "orders"), ("LOG_LEVEL", "debug"), ("MODE", "test")]
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_preopen() {
+        let preopen = "/var/data::/data".parse::<Preopen>().expect("should parse");
+        assert_eq!(preopen.host, PathBuf::from("/var/data"));
+        assert_eq!(preopen.guest, "/data");
+
+        let preopen = "/tmp".parse::<Preopen>().expect("should parse");
+        assert_eq!(preopen.guest, "/tmp");
+
+        "::/data".parse::<Preopen>().expect_err("should require a host directory");
+    }
+}
@@ -0,0 +1,199 @@
+//! # Runtime WIT
+//!
+//! Describes the interfaces a runtime provides as a single WIT package, so
+//! guests can generate bindings against exactly what the runtime offers.
+//!
+//! Each host contributes the statements its interfaces add to the runtime's
+//! world (the interfaces it links and those its server calls) along with the
+//! WIT packages they use. The combined package is written as:
+//!
+//! ```text
+//! <dir>/
+//! ├── world.wit
+//! └── deps/
+//!     ├── keyvalue@0.2.0-draft2/
+//!     └── ...
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+
+/// The WIT a host adds to the runtime's world.
+#[derive(Clone, Copy, Debug)]
+pub struct HostWit {
+    /// Statements added to the world, e.g.
+    /// `include wasi:keyvalue/imports@0.2.0-draft2;`.
+    pub world: &'static [&'static str],
+
+    /// The WIT files of packages used by the world, as `(path, contents)`
+    /// with paths relative to the `deps` directory.
+    pub deps: &'static [(&'static str, &'static str)],
+}
+
+/// A WIT package with a single world combining each host's.
+#[derive(Clone, Debug)]
+pub struct WitPackage {
+    package: String,
+    world: String,
+    hosts: Vec<HostWit>,
+}
+
+impl WitPackage {
+    /// Create an empty package named `package` (e.g. `credibil:runtime`)
+    /// with a world named `world`.
+    #[must_use]
+    pub fn new(package: impl Into<String>, world: impl Into<String>) -> Self {
+        Self {
+            package: package.into(),
+            world: world.into(),
+            hosts: Vec::new(),
+        }
+    }
+
+    /// Add a host's interfaces to the world.
+    #[must_use]
+    pub fn with(mut self, host: HostWit) -> Self {
+        self.hosts.push(host);
+        self
+    }
+
+    /// The WIT files of packages used by the world, keyed by their path
+    /// relative to the `deps` directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if hosts bundle different files at the same path.
+    pub fn deps(&self) -> Result<BTreeMap<&'static str, &'static str>> {
+        let mut deps = BTreeMap::new();
+        for &(path, contents) in self.hosts.iter().flat_map(|host| host.deps) {
+            if let Some(existing) = deps.insert(path, contents)
+                && existing != contents
+            {
+                bail!("hosts bundle different versions of `{path}`");
+            }
+        }
+        Ok(deps)
+    }
+
+    /// Write the package to `dir`, replacing any existing `world.wit` and
+    /// dependencies.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the package cannot be written.
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        let deps_dir = dir.join("deps");
+        for (path, contents) in self.deps()? {
+            let file = deps_dir.join(path);
+            if let Some(parent) = file.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("creating {}", parent.display()))?;
+            }
+            fs::write(&file, contents).with_context(|| format!("writing {}", file.display()))?;
+        }
+        let world = dir.join("world.wit");
+        fs::write(&world, self.to_string()).with_context(|| format!("writing {}", world.display()))
+    }
+}
+
+/// Formats the package's `world.wit`.
+impl Display for WitPackage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "package {};", self.package)?;
+        writeln!(f)?;
+        writeln!(f, "world {} {{", self.world)?;
+        let mut seen = Vec::new();
+        for statement in self.hosts.iter().flat_map(|host| host.world) {
+            if !seen.contains(statement) {
+                seen.push(statement);
+                writeln!(f, "    {statement}")?;
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use wit_parser::Resolve;
+
+    use super::*;
+
+    const KEYVALUE: HostWit = HostWit {
+        world: &["include wasi:keyvalue/imports@0.2.0-draft2;"],
+        deps: &[("keyvalue@0.2.0-draft2/world.wit", "package wasi:keyvalue@0.2.0-draft2;")],
+    };
+
+    const OTEL: HostWit = HostWit {
+        world: &["include wasi:otel/imports@0.2.0-draft;"],
+        deps: &[
+            ("otel@0.2.0-draft/world.wit", "package wasi:otel@0.2.0-draft;"),
+            ("io@0.2.8/world.wit", "package wasi:io@0.2.8;"),
+        ],
+    };
+
+    #[test]
+    fn world() {
+        let package = WitPackage::new("credibil:runtime", "runtime").with(KEYVALUE).with(OTEL);
+        assert_eq!(
+            package.to_string(),
+            "package credibil:runtime;\n\nworld runtime {\n    include \
+             wasi:keyvalue/imports@0.2.0-draft2;\n    include wasi:otel/imports@0.2.0-draft;\n}\n"
+        );
+
+        let deps = package.deps().expect("deps");
+        assert_eq!(
+            deps.keys().copied().collect::<Vec<_>>(),
+            ["io@0.2.8/world.wit", "keyvalue@0.2.0-draft2/world.wit", "otel@0.2.0-draft/world.wit"]
+        );
+    }
+
+    #[test]
+    fn resolve_hosts() {
+        // the hosts' `HostWit` is from their own build of this crate
+        let package = [
+            wasi_blobstore::WIT,
+            wasi_config::WIT,
+            wasi_http::WIT,
+            wasi_identity::WIT,
+            wasi_keyvalue::WIT,
+            wasi_messaging::WIT,
+            wasi_otel::WIT,
+            wasi_sql::WIT,
+            wasi_vault::WIT,
+            wasi_websockets::WIT,
+        ]
+        .into_iter()
+        .fold(WitPackage::new("credibil:runtime", "runtime"), |package, wit| {
+            package.with(HostWit {
+                world: wit.world,
+                deps: wit.deps,
+            })
+        });
+
+        let dir = env::temp_dir().join(format!("wrt-wit-{}", process::id()));
+        package.write(&dir).expect("should write package");
+
+        let mut resolve = Resolve::default();
+        let (id, _) = resolve.push_dir(&dir).expect("should resolve package");
+        resolve.select_world(&[id], Some("runtime")).expect("should select world");
+
+        fs::remove_dir_all(&dir).expect("should remove package");
+    }
+
+    #[test]
+    fn conflicting_deps() {
+        const IO: HostWit = HostWit {
+            world: &[],
+            deps: &[("io@0.2.8/world.wit", "package wasi:io@0.2.8; // changed")],
+        };
+        let package = WitPackage::new("credibil:runtime", "runtime").with(OTEL).with(IO);
+        assert!(package.deps().is_err());
+    }
+}
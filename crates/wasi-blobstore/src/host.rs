@@ -3,7 +3,10 @@
 mod blobstore_impl;
 mod container_impl;
 pub mod default_impl;
+mod replay_impl;
 mod resource;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_impl;
 mod types_impl;
 
 mod generated {
@@ -17,6 +20,7 @@ mod generated {
         imports: {
             default: store | tracing | trappable,
         },
+        additional_derives: [serde::Deserialize, serde::Serialize],
         with: {
             "wasi:io": wasmtime_wasi::p2::bindings::io,
             "wasi:blobstore/types.incoming-value": IncomingValue,
@@ -36,12 +40,14 @@ use std::sync::Arc;
 use anyhow::Result;
 use bytes::Bytes;
 pub use kernel::FutureResult;
-use kernel::{Host, Server, State};
+use kernel::{Capabilities, Host, Named, Server, State};
 pub use resource::*;
 use wasmtime::component::{HasData, Linker, ResourceTable};
 use wasmtime_wasi::p2::pipe::MemoryOutputPipe;
 
 pub use self::default_impl::BlobstoreDefault;
+#[cfg(feature = "sqlite")]
+pub use self::sqlite_impl::BlobstoreSqlite;
 pub use self::generated::wasi::blobstore::container::{ContainerMetadata, ObjectMetadata};
 use self::generated::wasi::blobstore::{blobstore, container, types};
 
@@ -69,6 +75,27 @@ where
 
 impl<S> Server<S> for WasiBlobstore where S: State {}
 
+/// The host's interfaces, added to the runtime's WIT world.
+pub const WIT: kernel::HostWit = kernel::HostWit {
+    world: &["include wasi:blobstore/imports@0.2.0-draft;"],
+    deps: &[
+        (
+            "blobstore@0.2.0-draft/blobstore.wit",
+            include_str!("../wit/deps/blobstore/blobstore.wit"),
+        ),
+        (
+            "blobstore@0.2.0-draft/container.wit",
+            include_str!("../wit/deps/blobstore/container.wit"),
+        ),
+        ("blobstore@0.2.0-draft/types.wit", include_str!("../wit/deps/blobstore/types.wit")),
+        ("blobstore@0.2.0-draft/world.wit", include_str!("../wit/deps/blobstore/world.wit")),
+        ("io@0.2.2/error.wit", include_str!("../wit/deps/io/error.wit")),
+        ("io@0.2.2/poll.wit", include_str!("../wit/deps/io/poll.wit")),
+        ("io@0.2.2/streams.wit", include_str!("../wit/deps/io/streams.wit")),
+        ("io@0.2.2/world.wit", include_str!("../wit/deps/io/world.wit")),
+    ],
+};
+
 /// A trait which provides internal WASI Blobstore context.
 ///
 /// This is implemented by the resource-specific provider of Blobstore
@@ -87,6 +114,25 @@ pub trait WasiBlobstoreCtx: Debug + Send + Sync + 'static {
     fn container_exists(&self, name: String) -> FutureResult<bool>;
 }
 
+/// Uses the backend instance named for each container.
+impl WasiBlobstoreCtx for Named<dyn WasiBlobstoreCtx> {
+    fn create_container(&self, name: String) -> FutureResult<Arc<dyn Container>> {
+        self.get(&name).create_container(name)
+    }
+
+    fn get_container(&self, name: String) -> FutureResult<Arc<dyn Container>> {
+        self.get(&name).get_container(name)
+    }
+
+    fn delete_container(&self, name: String) -> FutureResult<()> {
+        self.get(&name).delete_container(name)
+    }
+
+    fn container_exists(&self, name: String) -> FutureResult<bool> {
+        self.get(&name).container_exists(name)
+    }
+}
+
 /// View into [`WasiBlobstoreCtx`] implementation and [`ResourceTable`].
 pub struct WasiBlobstoreCtxView<'a> {
     /// Mutable reference to the WASI Blobstore context.
@@ -94,6 +140,9 @@ pub struct WasiBlobstoreCtxView<'a> {
 
     /// Mutable reference to table used to manage resources.
     pub table: &'a mut ResourceTable,
+
+    /// The interfaces and named resources the guest may use.
+    pub capabilities: &'a Capabilities,
 }
 
 /// A trait which provides internal WASI Blobstore state.
@@ -113,6 +162,7 @@ macro_rules! wasi_view {
                 wasi_blobstore::WasiBlobstoreCtxView {
                     ctx: &mut self.$field_name,
                     table: &mut self.table,
+                    capabilities: &self.capabilities,
                 }
             }
         }
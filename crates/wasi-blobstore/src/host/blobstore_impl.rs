@@ -1,16 +1,23 @@
-use anyhow::Result;
+use anyhow::{Result, bail};
+use kernel::ResourceKind;
 use wasmtime::component::{Accessor, Resource};
 
 use crate::host::generated::wasi::blobstore::blobstore::{Host, HostWithStore, ObjectId};
 use crate::host::resource::ContainerProxy;
-use crate::host::{WasiBlobstore, WasiBlobstoreCtxView};
+use crate::host::{WasiBlobstore, WasiBlobstoreCtxView, replay_impl};
 
 impl HostWithStore for WasiBlobstore {
     async fn create_container<T>(
         accessor: &Accessor<T, Self>, name: String,
     ) -> Result<Resource<ContainerProxy>> {
         tracing::trace!("create_container: {name}");
-        let container = accessor.with(|mut store| store.get().ctx.create_container(name)).await?;
+        let container = accessor
+            .with(|mut store| {
+                let view = store.get();
+                check_access(&view, &name)?;
+                Ok::<_, anyhow::Error>(replay_impl::create_container(view.ctx, name))
+            })?
+            .await?;
         let proxy = ContainerProxy(container);
         Ok(accessor.with(|mut store| store.get().table.push(proxy))?)
     }
@@ -19,19 +26,37 @@ impl HostWithStore for WasiBlobstore {
         accessor: &Accessor<T, Self>, name: String,
     ) -> Result<Resource<ContainerProxy>> {
         tracing::trace!("get_container: {name}");
-        let container = accessor.with(|mut store| store.get().ctx.get_container(name)).await?;
+        let container = accessor
+            .with(|mut store| {
+                let view = store.get();
+                check_access(&view, &name)?;
+                Ok::<_, anyhow::Error>(replay_impl::get_container(view.ctx, name))
+            })?
+            .await?;
         let proxy = ContainerProxy(container);
         Ok(accessor.with(|mut store| store.get().table.push(proxy))?)
     }
 
     async fn delete_container<T>(accessor: &Accessor<T, Self>, name: String) -> Result<()> {
         tracing::trace!("delete_container: {name}");
-        accessor.with(|mut store| store.get().ctx.delete_container(name)).await
+        accessor
+            .with(|mut store| {
+                let view = store.get();
+                check_access(&view, &name)?;
+                Ok::<_, anyhow::Error>(replay_impl::delete_container(view.ctx, name))
+            })?
+            .await
     }
 
     async fn container_exists<T>(accessor: &Accessor<T, Self>, name: String) -> Result<bool> {
         tracing::trace!("container_exists: {name}");
-        accessor.with(|mut store| store.get().ctx.container_exists(name)).await
+        accessor
+            .with(|mut store| {
+                let view = store.get();
+                check_access(&view, &name)?;
+                Ok::<_, anyhow::Error>(replay_impl::container_exists(view.ctx, name))
+            })?
+            .await
     }
 
     async fn copy_object<T>(_: &Accessor<T, Self>, _src: ObjectId, _dest: ObjectId) -> Result<()> {
@@ -44,3 +69,11 @@ impl HostWithStore for WasiBlobstore {
 }
 
 impl Host for WasiBlobstoreCtxView<'_> {}
+
+// Fail unless the guest's capabilities allow it to use the container.
+fn check_access(view: &WasiBlobstoreCtxView<'_>, name: &str) -> Result<()> {
+    if !view.capabilities.allows(ResourceKind::Container, name) {
+        bail!("access denied to container `{name}`");
+    }
+    Ok(())
+}
@@ -30,6 +30,25 @@ pub struct BlobstoreDefault {
     store: Arc<RwLock<HashMap<String, InMemContainer>>>,
 }
 
+impl BlobstoreDefault {
+    /// The data of the object `name` in `container`. Used to inspect the
+    /// store in tests.
+    #[must_use]
+    pub fn object(&self, container: &str, name: &str) -> Option<Vec<u8>> {
+        self.store.read().get(container)?.objects.read().get(name).cloned()
+    }
+
+    /// Write the object `name` to `container`, creating the container if
+    /// needed. Used to seed the store in tests.
+    pub fn insert(&self, container: &str, name: &str, data: impl Into<Vec<u8>>) {
+        let mut store = self.store.write();
+        let container = store
+            .entry(container.to_string())
+            .or_insert_with(|| InMemContainer::new(container.to_string()));
+        container.objects.write().insert(name.to_string(), data.into());
+    }
+}
+
 impl Backend for BlobstoreDefault {
     type ConnectOptions = ConnectOptions;
 
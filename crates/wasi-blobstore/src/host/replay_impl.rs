@@ -0,0 +1,85 @@
+//! Recording and replay of blobstore host calls.
+//!
+//! Containers are wrapped so their calls are recorded, or replayed without an
+//! open container, when host call recording is enabled.
+
+use std::sync::Arc;
+
+use futures::FutureExt;
+use kernel::{Recorded, host_calls};
+
+use crate::host::resource::{Container, FutureResult};
+use crate::host::{ContainerMetadata, ObjectMetadata, WasiBlobstoreCtx};
+
+const INTERFACE: &str = "wasi:blobstore/blobstore";
+
+/// Create the container `name`, recording its calls when recording is
+/// enabled.
+pub fn create_container(
+    ctx: &dyn WasiBlobstoreCtx, name: String,
+) -> FutureResult<Arc<dyn Container>> {
+    if host_calls().is_live() {
+        return ctx.create_container(name);
+    }
+    let created =
+        Recorded::open(INTERFACE, "create_container", name.clone(), || ctx.create_container(name));
+    async move { Ok(Arc::new(created.await?) as Arc<dyn Container>) }.boxed()
+}
+
+/// Get the container `name`, recording its calls when recording is enabled.
+pub fn get_container(ctx: &dyn WasiBlobstoreCtx, name: String) -> FutureResult<Arc<dyn Container>> {
+    if host_calls().is_live() {
+        return ctx.get_container(name);
+    }
+    let opened =
+        Recorded::open(INTERFACE, "get_container", name.clone(), || ctx.get_container(name));
+    async move { Ok(Arc::new(opened.await?) as Arc<dyn Container>) }.boxed()
+}
+
+/// Delete the container `name`.
+pub fn delete_container(ctx: &dyn WasiBlobstoreCtx, name: String) -> FutureResult<()> {
+    host_calls().call(INTERFACE, "delete_container", &name, || ctx.delete_container(name.clone()))
+}
+
+/// Check whether the container `name` exists.
+pub fn container_exists(ctx: &dyn WasiBlobstoreCtx, name: String) -> FutureResult<bool> {
+    host_calls().call(INTERFACE, "container_exists", &name, || ctx.container_exists(name.clone()))
+}
+
+impl Container for Recorded<Arc<dyn Container>> {
+    fn name(&self) -> anyhow::Result<String> {
+        self.call_sync("name", (), |container| container.name())
+    }
+
+    fn info(&self) -> anyhow::Result<ContainerMetadata> {
+        self.call_sync("info", (), |container| container.info())
+    }
+
+    fn get_data(&self, name: String, start: u64, end: u64) -> FutureResult<Option<Vec<u8>>> {
+        self.call("get_data", (&name, start, end), |container| {
+            container.get_data(name.clone(), start, end)
+        })
+    }
+
+    fn write_data(&self, name: String, data: Vec<u8>) -> FutureResult<()> {
+        self.call("write_data", (&name, &data), |container| {
+            container.write_data(name.clone(), data.clone())
+        })
+    }
+
+    fn list_objects(&self) -> FutureResult<Vec<String>> {
+        self.call("list_objects", (), |container| container.list_objects())
+    }
+
+    fn delete_object(&self, name: String) -> FutureResult<()> {
+        self.call("delete_object", &name, |container| container.delete_object(name.clone()))
+    }
+
+    fn has_object(&self, name: String) -> FutureResult<bool> {
+        self.call("has_object", &name, |container| container.has_object(name.clone()))
+    }
+
+    fn object_info(&self, name: String) -> FutureResult<ObjectMetadata> {
+        self.call("object_info", &name, |container| container.object_info(name.clone()))
+    }
+}
@@ -0,0 +1,311 @@
+//! `SQLite` implementation for wasi-blobstore
+//!
+//! Persists containers to a local database file, for development without an
+//! object store. Enabled using the `sqlite` feature.
+
+use std::env;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow};
+use futures::FutureExt;
+use kernel::Backend;
+use parking_lot::Mutex;
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use tracing::instrument;
+
+use crate::host::WasiBlobstoreCtx;
+use crate::host::generated::wasi::blobstore::container::{ContainerMetadata, ObjectMetadata};
+use crate::host::resource::{Container, FutureResult};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS containers (
+    name TEXT PRIMARY KEY,
+    created_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS objects (
+    container TEXT NOT NULL,
+    name TEXT NOT NULL,
+    data BLOB NOT NULL,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (container, name)
+);";
+
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    /// The database file, or `:memory:`.
+    pub database: String,
+}
+
+impl kernel::FromEnv for ConnectOptions {
+    /// The database is read from `BLOBSTORE_DATABASE`, defaulting to
+    /// `blobstore.db`.
+    fn from_env() -> Result<Self> {
+        let database = env::var("BLOBSTORE_DATABASE").unwrap_or_else(|_| "blobstore.db".into());
+        Ok(Self { database })
+    }
+
+    /// Each instance's database is read from `<INSTANCE>_BLOBSTORE_DATABASE`,
+    /// falling back to `BLOBSTORE_DATABASE`, then `<instance>-blobstore.db`, so
+    /// that instances do not share a database unless configured to.
+    fn from_instance_env(instance: &str) -> Result<Self> {
+        let database = kernel::instance_var(instance, "BLOBSTORE_DATABASE")
+            .unwrap_or_else(|| format!("{instance}-blobstore.db"));
+        Ok(Self { database })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BlobstoreSqlite {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl BlobstoreSqlite {
+    // Run `query` against the database.
+    fn query<T: Send + 'static>(
+        &self, query: impl FnOnce(&Connection) -> rusqlite::Result<T> + Send + 'static,
+    ) -> FutureResult<T> {
+        let conn = Arc::clone(&self.conn);
+        async move { query(&conn.lock()).context("querying blobstore") }.boxed()
+    }
+
+    // The container `name`, if it exists.
+    fn container(&self, name: String) -> FutureResult<Option<SqliteContainer>> {
+        let conn = Arc::clone(&self.conn);
+        self.query(move |db| {
+            db.query_row("SELECT created_at FROM containers WHERE name = ?1", [&name], |row| {
+                unsigned(row, 0)
+            })
+            .optional()
+            .map(|created_at| {
+                created_at.map(|created_at| SqliteContainer {
+                    name,
+                    created_at,
+                    conn,
+                })
+            })
+        })
+    }
+}
+
+impl Backend for BlobstoreSqlite {
+    type ConnectOptions = ConnectOptions;
+
+    #[instrument]
+    async fn connect_with(options: Self::ConnectOptions) -> Result<Self> {
+        tracing::debug!("initializing SQLite blobstore: {}", options.database);
+        let conn = Connection::open(&options.database)
+            .with_context(|| format!("failed to open SQLite database {}", options.database))?;
+        conn.execute_batch(SCHEMA).context("failed to create blobstore tables")?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+}
+
+impl WasiBlobstoreCtx for BlobstoreSqlite {
+    fn create_container(&self, name: String) -> FutureResult<Arc<dyn Container>> {
+        tracing::debug!("creating container: {name}");
+        let created = self.query(move |conn| {
+            conn.execute(
+                "INSERT OR IGNORE INTO containers (name, created_at) VALUES (?1, unixepoch())",
+                [&name],
+            )
+            .map(|_| name)
+        });
+        let this = self.clone();
+
+        async move {
+            let name = created.await?;
+            let container = this.container(name.clone()).await?;
+            let container = container.ok_or_else(|| anyhow!("container not found: {name}"))?;
+            Ok(Arc::new(container) as Arc<dyn Container>)
+        }
+        .boxed()
+    }
+
+    fn get_container(&self, name: String) -> FutureResult<Arc<dyn Container>> {
+        tracing::debug!("getting container: {name}");
+        let container = self.container(name.clone());
+
+        async move {
+            let container =
+                container.await?.ok_or_else(|| anyhow!("container not found: {name}"))?;
+            Ok(Arc::new(container) as Arc<dyn Container>)
+        }
+        .boxed()
+    }
+
+    fn delete_container(&self, name: String) -> FutureResult<()> {
+        tracing::debug!("deleting container: {name}");
+        self.query(move |conn| {
+            conn.execute("DELETE FROM objects WHERE container = ?1", [&name])?;
+            conn.execute("DELETE FROM containers WHERE name = ?1", [&name]).map(drop)
+        })
+    }
+
+    fn container_exists(&self, name: String) -> FutureResult<bool> {
+        tracing::debug!("checking existence of container: {name}");
+        let container = self.container(name);
+        async move { Ok(container.await?.is_some()) }.boxed()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SqliteContainer {
+    name: String,
+    created_at: u64,
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteContainer {
+    // Run `query` against the container's database.
+    fn query<T: Send + 'static>(
+        &self, query: impl FnOnce(&Connection, &str) -> rusqlite::Result<T> + Send + 'static,
+    ) -> FutureResult<T> {
+        let conn = Arc::clone(&self.conn);
+        let name = self.name.clone();
+
+        async move {
+            let result = query(&conn.lock(), &name);
+            result.with_context(|| format!("querying container {name}"))
+        }
+        .boxed()
+    }
+}
+
+impl Container for SqliteContainer {
+    fn name(&self) -> anyhow::Result<String> {
+        Ok(self.name.clone())
+    }
+
+    fn info(&self) -> anyhow::Result<ContainerMetadata> {
+        Ok(ContainerMetadata {
+            name: self.name.clone(),
+            created_at: self.created_at,
+        })
+    }
+
+    fn get_data(&self, name: String, _start: u64, _end: u64) -> FutureResult<Option<Vec<u8>>> {
+        tracing::debug!("getting object: {name} from container: {}", self.name);
+
+        // Note: start/end parameters are ignored, as for the in-memory
+        // implementation
+        self.query(move |conn, container| {
+            conn.query_row(
+                "SELECT data FROM objects WHERE container = ?1 AND name = ?2",
+                params![container, name],
+                |row| row.get(0),
+            )
+            .optional()
+        })
+    }
+
+    fn write_data(&self, name: String, data: Vec<u8>) -> FutureResult<()> {
+        tracing::debug!("writing object: {name} to container: {}", self.name);
+        self.query(move |conn, container| {
+            conn.execute(
+                "INSERT OR REPLACE INTO objects (container, name, data, created_at)
+                VALUES (?1, ?2, ?3, unixepoch())",
+                params![container, name, data],
+            )
+            .map(drop)
+        })
+    }
+
+    fn list_objects(&self) -> FutureResult<Vec<String>> {
+        tracing::debug!("listing objects in container: {}", self.name);
+        self.query(|conn, container| {
+            let mut stmt = conn.prepare("SELECT name FROM objects WHERE container = ?1")?;
+            stmt.query_map([container], |row| row.get(0))?.collect()
+        })
+    }
+
+    fn delete_object(&self, name: String) -> FutureResult<()> {
+        tracing::debug!("deleting object: {name} from container: {}", self.name);
+        self.query(move |conn, container| {
+            conn.execute(
+                "DELETE FROM objects WHERE container = ?1 AND name = ?2",
+                params![container, name],
+            )
+            .map(drop)
+        })
+    }
+
+    fn has_object(&self, name: String) -> FutureResult<bool> {
+        tracing::debug!("checking existence of object: {name} in container: {}", self.name);
+        self.query(move |conn, container| {
+            conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM objects WHERE container = ?1 AND name = ?2)",
+                params![container, name],
+                |row| row.get(0),
+            )
+        })
+    }
+
+    fn object_info(&self, name: String) -> FutureResult<ObjectMetadata> {
+        tracing::debug!("getting info for object: {name} in container: {}", self.name);
+        let info = self.query(move |conn, container| {
+            conn.query_row(
+                "SELECT length(data), created_at FROM objects WHERE container = ?1 AND name = ?2",
+                params![container, name],
+                |row| {
+                    Ok(ObjectMetadata {
+                        name: name.clone(),
+                        container: container.to_string(),
+                        created_at: unsigned(row, 1)?,
+                        size: unsigned(row, 0)?,
+                    })
+                },
+            )
+            .optional()
+        });
+        let container = self.name.clone();
+
+        async move {
+            info.await?.ok_or_else(|| anyhow!("object not found in container {container}"))
+        }
+        .boxed()
+    }
+}
+
+// Read column `idx` of `row` as a count or timestamp. SQLite integers are
+// signed.
+fn unsigned(row: &Row, idx: usize) -> rusqlite::Result<u64> {
+    row.get::<_, i64>(idx).map(|value| u64::try_from(value).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn container_operations() {
+        let options = ConnectOptions {
+            database: ":memory:".to_string(),
+        };
+        let ctx = BlobstoreSqlite::connect_with(options).await.expect("connect");
+
+        let container = ctx.create_container("test".to_string()).await.expect("create");
+        assert!(ctx.container_exists("test".to_string()).await.expect("exists"));
+        ctx.get_container("missing".to_string()).await.map(drop).expect_err("missing container");
+        assert_eq!(container.info().expect("info").name, "test");
+
+        container.write_data("obj1".to_string(), b"data1".to_vec()).await.expect("write");
+        let data = container.get_data("obj1".to_string(), 0, 0).await.expect("get");
+        assert_eq!(data, Some(b"data1".to_vec()));
+        assert!(container.has_object("obj1".to_string()).await.expect("has"));
+        assert_eq!(container.object_info("obj1".to_string()).await.expect("info").size, 5);
+        assert_eq!(container.list_objects().await.expect("list"), vec!["obj1".to_string()]);
+
+        // creating an existing container keeps its objects
+        let container = ctx.create_container("test".to_string()).await.expect("create");
+        assert!(container.has_object("obj1".to_string()).await.expect("has"));
+
+        container.delete_object("obj1".to_string()).await.expect("delete");
+        assert!(!container.has_object("obj1".to_string()).await.expect("has"));
+
+        ctx.delete_container("test".to_string()).await.expect("delete");
+        assert!(!ctx.container_exists("test".to_string()).await.expect("exists"));
+    }
+}
@@ -3,37 +3,81 @@
 //! This module implements a host-side service for `wasi:http`
 
 mod default_impl;
+mod replay_impl;
+mod store_impl;
+
+mod generated {
+    pub use self::wasi::config::store::Error;
+
+    wasmtime::component::bindgen!({
+        world: "config",
+        path: "wit",
+        imports: {
+            default: tracing | trappable,
+        },
+    });
+}
 
 use std::fmt::Debug;
 
 use anyhow::Result;
 pub use default_impl::ConfigDefault;
 use kernel::{Host, Server, State};
-use wasmtime::component::Linker;
-pub use wasmtime_wasi_config;
-use wasmtime_wasi_config::WasiConfigVariables;
+use wasmtime::component::{HasData, Linker};
+
+use self::generated::wasi::config::store;
 
 #[derive(Debug)]
 pub struct WasiConfig;
 
+impl HasData for WasiConfig {
+    type Data<'a> = WasiConfigCtxView<'a>;
+}
+
 impl<T> Host<T> for WasiConfig
 where
     T: WasiConfigView + 'static,
 {
     fn add_to_linker(linker: &mut Linker<T>) -> Result<()> {
-        wasmtime_wasi_config::add_to_linker(linker, T::config)
+        store::add_to_linker::<_, Self>(linker, T::config)
     }
 }
 
 impl<S> Server<S> for WasiConfig where S: State {}
 
+/// The host's interfaces, added to the runtime's WIT world.
+pub const WIT: kernel::HostWit = kernel::HostWit {
+    world: &["include wasi:config/imports@0.2.0-rc.1;"],
+    deps: &[
+        ("config@0.2.0-rc.1/store.wit", include_str!("../wit/deps/config/store.wit")),
+        ("config@0.2.0-rc.1/world.wit", include_str!("../wit/deps/config/world.wit")),
+    ],
+};
+
 /// A trait which provides internal WASI Config context.
 ///
 /// This is implemented by the resource-specific provider of Config
 /// functionality.
 pub trait WasiConfigCtx: Debug + Send + Sync + 'static {
-    /// Get the configuration variables.
-    fn get_config(&self) -> &WasiConfigVariables;
+    /// Get the configuration value for `key`, if set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration cannot be read.
+    fn get(&self, key: &str) -> Result<Option<String>>;
+
+    /// Get every configuration value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration cannot be read.
+    fn get_all(&self) -> Result<Vec<(String, String)>>;
+}
+
+/// View into [`WasiConfigCtx`] implementation.
+pub struct WasiConfigCtxView<'a> {
+    /// Mutable reference to the WASI Config context.
+    pub ctx: &'a mut dyn WasiConfigCtx,
 }
 
 /// A trait which provides internal WASI Config state.
@@ -41,17 +85,18 @@ pub trait WasiConfigCtx: Debug + Send + Sync + 'static {
 /// This is implemented by the `T` in `Linker<T>` — a single type shared across
 /// all WASI components for the runtime build.
 pub trait WasiConfigView: Send {
-    /// Return a [`WasiConfig`] from mutable reference to self.
-    fn config(&mut self) -> wasmtime_wasi_config::WasiConfig<'_>;
+    /// Return a [`WasiConfigCtxView`] from mutable reference to self.
+    fn config(&mut self) -> WasiConfigCtxView<'_>;
 }
 
 #[macro_export]
 macro_rules! wasi_view {
     ($store_ctx:ty, $field_name:ident) => {
         impl wasi_config::WasiConfigView for $store_ctx {
-            fn config(&mut self) -> wasi_config::wasmtime_wasi_config::WasiConfig<'_> {
-                let vars = wasi_config::WasiConfigCtx::get_config(&self.$field_name);
-                wasi_config::wasmtime_wasi_config::WasiConfig::from(vars)
+            fn config(&mut self) -> wasi_config::WasiConfigCtxView<'_> {
+                wasi_config::WasiConfigCtxView {
+                    ctx: &mut self.$field_name,
+                }
             }
         }
     };
@@ -1,11 +1,9 @@
-//! Default no-op implementation for wasi-otel
+//! Default implementation for wasi-config
 //!
-//! This is a lightweight implementation for development use only.
-//! It logs telemetry data but doesn't export it anywhere.
-//! For production use, use the `be-opentelemetry` backend.
-
-#![allow(clippy::used_underscore_binding)]
+//! Configuration is read from the runtime's environment variables, as they
+//! were when the backend connected.
 
+use std::collections::HashMap;
 use std::env;
 use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
@@ -13,7 +11,6 @@ use std::sync::Arc;
 use anyhow::Result;
 use kernel::Backend;
 use tracing::instrument;
-use wasmtime_wasi_config::WasiConfigVariables;
 
 use crate::WasiConfigCtx;
 
@@ -28,7 +25,7 @@ impl kernel::FromEnv for ConnectOptions {
 
 #[derive(Clone)]
 pub struct ConfigDefault {
-    pub config_vars: Arc<WasiConfigVariables>,
+    pub config_vars: Arc<HashMap<String, String>>,
 }
 
 impl Debug for ConfigDefault {
@@ -42,16 +39,18 @@ impl Backend for ConfigDefault {
 
     #[instrument]
     async fn connect_with(_: Self::ConnectOptions) -> Result<Self> {
-        let config_vars = env::vars().collect();
-
         Ok(Self {
-            config_vars: Arc::new(config_vars),
+            config_vars: Arc::new(env::vars().collect()),
         })
     }
 }
 
 impl WasiConfigCtx for ConfigDefault {
-    fn get_config(&self) -> &WasiConfigVariables {
-        &self.config_vars
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.config_vars.get(key).cloned())
+    }
+
+    fn get_all(&self) -> Result<Vec<(String, String)>> {
+        Ok(self.config_vars.iter().map(|(key, value)| (key.clone(), value.clone())).collect())
     }
 }
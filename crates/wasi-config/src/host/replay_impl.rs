@@ -0,0 +1,23 @@
+//! Recording and replay of configuration.
+//!
+//! Each read is recorded, rather than the backend's configuration, so a
+//! recording only includes the variables the guest read.
+
+use anyhow::Result;
+use kernel::host_calls;
+
+use crate::host::WasiConfigCtx;
+
+const INTERFACE: &str = "wasi:config/store";
+
+/// Get the configuration value for `key`, recording the read when recording
+/// is enabled. When replaying, the recorded value is returned instead.
+pub fn get(ctx: &dyn WasiConfigCtx, key: &str) -> Result<Option<String>> {
+    host_calls().call_sync(INTERFACE, "get", key, || ctx.get(key))
+}
+
+/// Get every configuration value, recording the read when recording is
+/// enabled. When replaying, the recorded values are returned instead.
+pub fn get_all(ctx: &dyn WasiConfigCtx) -> Result<Vec<(String, String)>> {
+    host_calls().call_sync(INTERFACE, "get_all", (), || ctx.get_all())
+}
@@ -0,0 +1,13 @@
+use crate::host::generated::Error;
+use crate::host::store::Host;
+use crate::host::{WasiConfigCtxView, replay_impl};
+
+impl Host for WasiConfigCtxView<'_> {
+    fn get(&mut self, key: String) -> wasmtime::Result<Result<Option<String>, Error>> {
+        Ok(replay_impl::get(self.ctx, &key).map_err(|e| Error::Upstream(format!("{e:#}"))))
+    }
+
+    fn get_all(&mut self) -> wasmtime::Result<Result<Vec<(String, String)>, Error>> {
+        Ok(replay_impl::get_all(self.ctx).map_err(|e| Error::Upstream(format!("{e:#}"))))
+    }
+}
@@ -3,10 +3,13 @@
 //! This module implements a host-side service for `wasi:http`
 
 mod default_impl;
+mod harness;
+mod replay_impl;
 mod server;
 
 use anyhow::Result;
 pub use default_impl::HttpDefault;
+pub use harness::HttpHarness;
 use kernel::{Host, Server, State};
 use wasmtime::component::Linker;
 pub use wasmtime_wasi_http::p3::{WasiHttpCtxView, WasiHttpView};
@@ -33,6 +36,70 @@ where
     }
 }
 
+/// The host's interfaces, added to the runtime's WIT world, bundling the
+/// `wasi:http` proxy WIT (and the packages it uses) linked by
+/// `wasmtime-wasi-http`.
+pub const WIT: kernel::HostWit = kernel::HostWit {
+    world: &["include wasi:http/proxy@0.3.0-rc-2025-09-16;"],
+    deps: &[
+        ("cli@0.3.0-rc-2025-09-16/command.wit", include_str!("../wit/deps/cli/command.wit")),
+        (
+            "cli@0.3.0-rc-2025-09-16/environment.wit",
+            include_str!("../wit/deps/cli/environment.wit"),
+        ),
+        ("cli@0.3.0-rc-2025-09-16/exit.wit", include_str!("../wit/deps/cli/exit.wit")),
+        ("cli@0.3.0-rc-2025-09-16/imports.wit", include_str!("../wit/deps/cli/imports.wit")),
+        ("cli@0.3.0-rc-2025-09-16/run.wit", include_str!("../wit/deps/cli/run.wit")),
+        ("cli@0.3.0-rc-2025-09-16/stdio.wit", include_str!("../wit/deps/cli/stdio.wit")),
+        ("cli@0.3.0-rc-2025-09-16/terminal.wit", include_str!("../wit/deps/cli/terminal.wit")),
+        (
+            "clocks@0.3.0-rc-2025-09-16/monotonic-clock.wit",
+            include_str!("../wit/deps/clocks/monotonic-clock.wit"),
+        ),
+        (
+            "clocks@0.3.0-rc-2025-09-16/timezone.wit",
+            include_str!("../wit/deps/clocks/timezone.wit"),
+        ),
+        ("clocks@0.3.0-rc-2025-09-16/types.wit", include_str!("../wit/deps/clocks/types.wit")),
+        (
+            "clocks@0.3.0-rc-2025-09-16/wall-clock.wit",
+            include_str!("../wit/deps/clocks/wall-clock.wit"),
+        ),
+        ("clocks@0.3.0-rc-2025-09-16/world.wit", include_str!("../wit/deps/clocks/world.wit")),
+        (
+            "filesystem@0.3.0-rc-2025-09-16/preopens.wit",
+            include_str!("../wit/deps/filesystem/preopens.wit"),
+        ),
+        (
+            "filesystem@0.3.0-rc-2025-09-16/types.wit",
+            include_str!("../wit/deps/filesystem/types.wit"),
+        ),
+        (
+            "filesystem@0.3.0-rc-2025-09-16/world.wit",
+            include_str!("../wit/deps/filesystem/world.wit"),
+        ),
+        ("http@0.3.0-rc-2025-09-16/handler.wit", include_str!("../wit/deps/http/handler.wit")),
+        ("http@0.3.0-rc-2025-09-16/proxy.wit", include_str!("../wit/deps/http/proxy.wit")),
+        ("http@0.3.0-rc-2025-09-16/types.wit", include_str!("../wit/deps/http/types.wit")),
+        (
+            "random@0.3.0-rc-2025-09-16/insecure-seed.wit",
+            include_str!("../wit/deps/random/insecure-seed.wit"),
+        ),
+        (
+            "random@0.3.0-rc-2025-09-16/insecure.wit",
+            include_str!("../wit/deps/random/insecure.wit"),
+        ),
+        ("random@0.3.0-rc-2025-09-16/random.wit", include_str!("../wit/deps/random/random.wit")),
+        ("random@0.3.0-rc-2025-09-16/world.wit", include_str!("../wit/deps/random/world.wit")),
+        (
+            "sockets@0.3.0-rc-2025-09-16/ip-name-lookup.wit",
+            include_str!("../wit/deps/sockets/ip-name-lookup.wit"),
+        ),
+        ("sockets@0.3.0-rc-2025-09-16/types.wit", include_str!("../wit/deps/sockets/types.wit")),
+        ("sockets@0.3.0-rc-2025-09-16/world.wit", include_str!("../wit/deps/sockets/world.wit")),
+    ],
+};
+
 #[macro_export]
 macro_rules! wasi_view {
     ($store_ctx:ty, $field_name:ident) => {
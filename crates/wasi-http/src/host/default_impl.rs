@@ -1,17 +1,22 @@
+use std::sync::Arc;
+
 use anyhow::{Context, Result};
 use base64ct::{Base64, Encoding};
 use bytes::Bytes;
 use fromenv::FromEnv;
 use futures::Future;
+use http::request::Parts;
 use http::{Request, Response};
 use http_body_util::BodyExt;
 use http_body_util::combinators::UnsyncBoxBody;
-use kernel::Backend;
+use kernel::{Backend, Capabilities, ResourceKind, host_calls};
 use tracing::instrument;
 use wasmtime_wasi::TrappableError;
 use wasmtime_wasi_http::p3::bindings::http::types::ErrorCode;
 use wasmtime_wasi_http::p3::{self, RequestOptions};
 
+use crate::host::replay_impl;
+
 pub type HttpResult<T> = Result<T, HttpError>;
 pub type HttpError = TrappableError<ErrorCode>;
 pub type FutureResult<T> = Box<dyn Future<Output = Result<T, ErrorCode>> + Send>;
@@ -28,15 +33,24 @@ impl kernel::FromEnv for ConnectOptions {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct HttpDefault;
+#[derive(Debug, Clone, Default)]
+pub struct HttpDefault {
+    // the hosts the guest may send requests to
+    capabilities: Arc<Capabilities>,
+}
 
 impl Backend for HttpDefault {
     type ConnectOptions = ConnectOptions;
 
     #[instrument]
     async fn connect_with(options: Self::ConnectOptions) -> Result<Self> {
-        Ok(Self)
+        Ok(Self::default())
+    }
+
+    fn for_guest(self, capabilities: &Arc<Capabilities>) -> Self {
+        Self {
+            capabilities: Arc::clone(capabilities),
+        }
     }
 }
 
@@ -49,47 +63,63 @@ impl p3::WasiHttpCtx for HttpDefault {
                 Output = HttpResult<(Response<UnsyncBoxBody<Bytes, ErrorCode>>, FutureResult<()>)>,
             > + Send,
     > {
+        let host = request.uri().host().unwrap_or_default().to_ascii_lowercase();
+        let allowed = self.capabilities.allows(ResourceKind::Host, &host);
+
         Box::new(async move {
-            let (mut parts, body) = request.into_parts();
+            if !allowed {
+                return Err(ErrorCode::HttpRequestDenied.into());
+            }
+            let (parts, body) = request.into_parts();
             let collected =
                 body.collect().await.map_err(|e| ErrorCode::InternalError(Some(e.to_string())))?;
 
-            // build reqwest::Request
-            let mut builder = reqwest::Client::builder();
-
-            // check for client certificate in headers
-            if let Some(encoded_cert) = parts.headers.remove("Client-Cert") {
-                tracing::debug!("using client certificate");
-
-                let encoded_str = encoded_cert
-                    .to_str()
-                    .map_err(|e| ErrorCode::InternalError(Some(e.to_string())))?;
-                let pem_bytes = Base64::decode_vec(encoded_str)
-                    .map_err(|e| ErrorCode::InternalError(Some(e.to_string())))?;
-                let identity = reqwest::Identity::from_pem(&pem_bytes)
-                    .map_err(|e| ErrorCode::InternalError(Some(e.to_string())))?;
-                builder = builder.use_rustls_tls().identity(identity);
-            }
-
-            let client = builder.build().map_err(into_error)?;
-            let resp = client
-                .request(parts.method, parts.uri.to_string())
-                .headers(parts.headers)
-                .body(collected.to_bytes())
-                .send()
-                .await
-                .map_err(into_error)?;
-
-            let converted: Response<reqwest::Body> = resp.into();
-            let (parts, body) = converted.into_parts();
-            let body = body.map_err(into_error).boxed_unsync();
-            let response = Response::from_parts(parts, body);
+            let response = if host_calls().is_live() {
+                send(parts, collected.to_bytes()).await?
+            } else {
+                replay_impl::send(parts, collected.to_bytes()).await?
+            };
 
             Ok((response, fut))
         })
     }
 }
 
+/// Send an outgoing request.
+pub async fn send(
+    mut parts: Parts, body: Bytes,
+) -> Result<Response<UnsyncBoxBody<Bytes, ErrorCode>>, ErrorCode> {
+    // build reqwest::Request
+    let mut builder = reqwest::Client::builder();
+
+    // check for client certificate in headers
+    if let Some(encoded_cert) = parts.headers.remove("Client-Cert") {
+        tracing::debug!("using client certificate");
+
+        let encoded_str =
+            encoded_cert.to_str().map_err(|e| ErrorCode::InternalError(Some(e.to_string())))?;
+        let pem_bytes = Base64::decode_vec(encoded_str)
+            .map_err(|e| ErrorCode::InternalError(Some(e.to_string())))?;
+        let identity = reqwest::Identity::from_pem(&pem_bytes)
+            .map_err(|e| ErrorCode::InternalError(Some(e.to_string())))?;
+        builder = builder.use_rustls_tls().identity(identity);
+    }
+
+    let client = builder.build().map_err(into_error)?;
+    let resp = client
+        .request(parts.method, parts.uri.to_string())
+        .headers(parts.headers)
+        .body(body)
+        .send()
+        .await
+        .map_err(into_error)?;
+
+    let converted: Response<reqwest::Body> = resp.into();
+    let (parts, body) = converted.into_parts();
+    let body = body.map_err(into_error).boxed_unsync();
+    Ok(Response::from_parts(parts, body))
+}
+
 #[allow(clippy::needless_pass_by_value)]
 fn into_error(e: reqwest::Error) -> ErrorCode {
     if e.is_timeout() {
@@ -0,0 +1,57 @@
+//! # HTTP Test Harness
+//!
+//! Invokes a guest's HTTP handler from tests (see [`kernel::testing`]).
+
+use std::convert::Infallible;
+
+use anyhow::Result;
+use bytes::Bytes;
+use http::header::{HOST, HeaderValue};
+use http_body_util::{BodyExt, Full};
+use kernel::State;
+use kernel::testing::Harness;
+use wasmtime_wasi_http::p3::WasiHttpView;
+use wasmtime_wasi_http::p3::bindings::http::types::ErrorCode;
+
+use crate::host::server;
+
+/// Invoke guests' HTTP handlers.
+pub trait HttpHarness {
+    /// Handle `request` using the guest routed for it, returning the
+    /// response with its body collected.
+    ///
+    /// Requests for an absolute URI need not set the `Host` header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request's host is invalid or the response
+    /// body cannot be read.
+    fn http<B: Into<Bytes>>(
+        &self, request: http::Request<B>,
+    ) -> impl Future<Output = Result<http::Response<Bytes>>>;
+}
+
+impl<S> HttpHarness for Harness<S>
+where
+    S: State,
+    S::StoreCtx: WasiHttpView,
+{
+    async fn http<B: Into<Bytes>>(
+        &self, request: http::Request<B>,
+    ) -> Result<http::Response<Bytes>> {
+        let mut request = request.map(|body| {
+            Full::new(body.into()).map_err(|e: Infallible| -> ErrorCode { match e {} })
+        });
+        if !request.headers().contains_key(HOST)
+            && let Some(authority) = request.uri().authority()
+        {
+            let host = HeaderValue::from_str(authority.as_str())?;
+            request.headers_mut().insert(HOST, host);
+        }
+
+        let response = server::handle(self.state(), request).await;
+        let (parts, body) = response.into_parts();
+        let body = body.collect().await?.to_bytes();
+        Ok(http::Response::from_parts(parts, body))
+    }
+}
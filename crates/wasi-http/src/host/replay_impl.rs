@@ -0,0 +1,91 @@
+//! Recording and replay of outgoing HTTP requests.
+//!
+//! Outgoing requests, and the (collected) responses to them, are recorded
+//! when host call recording is enabled. When replaying, the recorded response
+//! is returned without sending the request.
+//!
+//! Requests are identified by their method, URI and body. Request headers
+//! are not recorded as they often vary between invocations (e.g. trace
+//! context) or hold credentials (e.g. client certificates).
+
+use anyhow::anyhow;
+use bytes::Bytes;
+use futures::FutureExt;
+use http::header::HeaderMap;
+use http::request::Parts;
+use http::{Response, StatusCode};
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, Full};
+use kernel::host_calls;
+use serde::{Deserialize, Serialize};
+use wasmtime_wasi_http::p3::bindings::http::types::ErrorCode;
+
+use crate::host::default_impl;
+
+const INTERFACE: &str = "wasi:http/handler";
+
+/// Send an outgoing request, recording the request and its response.
+pub async fn send(
+    parts: Parts, body: Bytes,
+) -> Result<Response<UnsyncBoxBody<Bytes, ErrorCode>>, ErrorCode> {
+    let request = RecordedRequest {
+        method: parts.method.to_string(),
+        uri: parts.uri.to_string(),
+        body: body.to_vec(),
+    };
+
+    let response = host_calls().call(INTERFACE, "handle", request, || {
+        async move {
+            let response = default_impl::send(parts, body).await.map_err(|e| anyhow!("{e:?}"))?;
+            let (parts, body) = response.into_parts();
+            let body = body.collect().await.map_err(|e| anyhow!("{e:?}"))?.to_bytes();
+            Ok(RecordedResponse {
+                status: parts.status.as_u16(),
+                headers: headers(&parts.headers),
+                body: body.to_vec(),
+            })
+        }
+        .boxed()
+    });
+    let response = response.await.map_err(|e| ErrorCode::InternalError(Some(format!("{e:#}"))))?;
+
+    response.into_response()
+}
+
+/// An outgoing request as recorded.
+#[derive(Debug, Deserialize, Serialize)]
+struct RecordedRequest {
+    method: String,
+    uri: String,
+    body: Vec<u8>,
+}
+
+/// A response as recorded.
+#[derive(Debug, Deserialize, Serialize)]
+struct RecordedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl RecordedResponse {
+    fn into_response(self) -> Result<Response<UnsyncBoxBody<Bytes, ErrorCode>>, ErrorCode> {
+        let status = StatusCode::from_u16(self.status)
+            .map_err(|e| ErrorCode::InternalError(Some(e.to_string())))?;
+        let mut builder = Response::builder().status(status);
+        for (name, value) in self.headers {
+            builder = builder.header(name, value);
+        }
+        let body = Full::new(Bytes::from(self.body)).map_err(|e| match e {}).boxed_unsync();
+        builder.body(body).map_err(|e| ErrorCode::InternalError(Some(e.to_string())))
+    }
+}
+
+fn headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned())
+        })
+        .collect()
+}
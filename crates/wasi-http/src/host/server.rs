@@ -3,6 +3,7 @@
 use std::clone::Clone;
 use std::convert::Infallible;
 use std::env;
+use std::pin::pin;
 use std::sync::Arc;
 
 use anyhow::{Context, Result, anyhow};
@@ -11,78 +12,102 @@ use http::StatusCode;
 use http::uri::{PathAndQuery, Uri};
 use http_body_util::combinators::UnsyncBoxBody;
 use http_body_util::{BodyExt, Full};
-use hyper::body::Incoming;
+use hyper::body::{Body, Incoming};
 use hyper::header::{FORWARDED, HOST};
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
-use kernel::State;
+use kernel::{DeadlineExceeded, Diagnostics, Guest, InFlight, LimitExceeded, State};
 use tokio::net::TcpListener;
 use tokio::sync::oneshot;
 use tracing::{Instrument, debug_span};
-use wasmtime::Store;
 use wasmtime_wasi_http::io::TokioIo;
 use wasmtime_wasi_http::p3::WasiHttpView;
 use wasmtime_wasi_http::p3::bindings::ProxyIndices;
 use wasmtime_wasi_http::p3::bindings::http::types::{self as wasi, ErrorCode};
 
 type OutgoingBody = UnsyncBoxBody<Bytes, anyhow::Error>;
+pub(crate) type Response = hyper::Response<OutgoingBody>;
 
 const HTTP_ADDR: &str = "0.0.0.0:8080";
+const REQUEST_ID: &str = "x-request-id";
 
 pub async fn serve<S>(state: &S) -> Result<()>
 where
     S: State,
     S::StoreCtx: WasiHttpView,
 {
-    let component = env::var("COMPONENT").unwrap_or_else(|_| "unknown".into());
     let addr = env::var("HTTP_ADDR").unwrap_or_else(|_| HTTP_ADDR.into());
 
     let listener = TcpListener::bind(&addr).await?;
-    tracing::info!("{component} http server listening on: {addr}");
+    for guest in state.guests().iter() {
+        tracing::info!("{} http server listening on: {addr}", guest.name());
+    }
 
+    let shutdown = state.shutdown().clone();
     let handler = Handler {
         state: Arc::new(state.clone()),
-        component,
+        in_flight: InFlight::default(),
     };
 
-    // listen for requests until terminated
+    // listen for requests until shutdown
     loop {
-        let (stream, _) = listener.accept().await?;
+        let (stream, _) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            () = shutdown.triggered() => break,
+        };
         stream.set_nodelay(true)?;
         let stream = TokioIo::new(stream);
         let handler = handler.clone();
+        let shutdown = shutdown.clone();
 
-        tokio::spawn(async move {
+        handler.in_flight.clone().spawn(async move {
             let mut http1 = http1::Builder::new();
             http1.keep_alive(true);
 
-            if let Err(e) = http1
-                .serve_connection(
-                    stream,
-                    service_fn(move |request| {
-                        let handler = handler.clone();
-                        async move {
-                            let response =
-                                handler.handle(request).await.unwrap_or_else(|_e| internal_error());
-
-                            // track server error responses
-                            if response.status() >= StatusCode::INTERNAL_SERVER_ERROR {
-                                tracing::error!(
-                                    monotonic_counter.processing_errors = 1,
-                                    service = %handler.component,
-                                    error = format!("{response:?}"),
-                                );
-                            }
-                            Ok::<_, Infallible>(response)
-                        }
-                    }),
-                )
-                .await
-            {
+            let connection = http1.serve_connection(
+                stream,
+                service_fn(move |request: hyper::Request<Incoming>| {
+                    let handler = handler.clone();
+                    let request =
+                        request.map(|body| body.map_err(ErrorCode::from_hyper_request_error));
+                    async move { Ok::<_, Infallible>(handler.route(request).await) }
+                }),
+            );
+            let mut connection = pin!(connection);
+
+            // finish in-flight requests then close the connection on shutdown
+            let result = tokio::select! {
+                result = connection.as_mut() => result,
+                () = shutdown.triggered() => {
+                    connection.as_mut().graceful_shutdown();
+                    connection.await
+                }
+            };
+            if let Err(e) = result {
                 tracing::error!("connection error: {e:?}");
             }
         });
     }
+
+    tracing::info!("http server shutting down");
+    handler.in_flight.drain(state.options().grace_period()).await;
+
+    Ok(())
+}
+
+/// Handle a single request using the guest routed for it, without starting
+/// the server.
+pub(crate) async fn handle<S, B>(state: &S, request: http::Request<B>) -> Response
+where
+    S: State,
+    S::StoreCtx: WasiHttpView,
+    B: Body<Data = Bytes, Error = ErrorCode> + Send + 'static,
+{
+    let handler = Handler {
+        state: Arc::new(state.clone()),
+        in_flight: InFlight::default(),
+    };
+    handler.route(request).await
 }
 
 #[derive(Clone)]
@@ -92,7 +117,7 @@ where
     S::StoreCtx: WasiHttpView,
 {
     state: Arc<S>,
-    component: String,
+    in_flight: InFlight,
 }
 
 impl<S> Handler<S>
@@ -100,33 +125,71 @@ where
     S: State,
     S::StoreCtx: WasiHttpView,
 {
-    // Forward request to the wasm Guest.
-    async fn handle(
-        &self, request: hyper::Request<Incoming>,
-    ) -> Result<hyper::Response<OutgoingBody>> {
+    // Route the request to the guest handling its host and path.
+    async fn route<B>(&self, request: http::Request<B>) -> Response
+    where
+        B: Body<Data = Bytes, Error = ErrorCode> + Send + 'static,
+    {
         tracing::debug!("handling request: {request:?}");
 
         // prepare wasmtime http request and response
-        let request = fix_request(request).context("preparing request")?;
+        let request = match fix_request(request).context("preparing request") {
+            Ok(request) => request,
+            Err(e) => {
+                tracing::error!("invalid request: {e:#}");
+                return error_response(StatusCode::BAD_REQUEST, "Invalid request");
+            }
+        };
+
+        let host = request.uri().host().unwrap_or_default();
+        let Some(guest) = self.state.guests().route(host, request.uri().path()) else {
+            tracing::debug!("no guest routed for request: {}", request.uri());
+            return error_response(StatusCode::NOT_FOUND, "No guest handles this request");
+        };
 
+        let response =
+            self.handle(guest, request).await.unwrap_or_else(|e| guest_error(guest.name(), &e));
+
+        // track server error responses
+        if response.status() >= StatusCode::INTERNAL_SERVER_ERROR {
+            tracing::error!(
+                monotonic_counter.processing_errors = 1,
+                service = %guest.name(),
+                error = format!("{response:?}"),
+            );
+        }
+        response
+    }
+
+    // Forward request to the wasm Guest.
+    async fn handle<B>(
+        &self, guest: &Guest<S::StoreCtx>, request: http::Request<B>,
+    ) -> Result<Response>
+    where
+        B: Body<Data = Bytes, Error = ErrorCode> + Send + 'static,
+    {
         // instantiate the guest and get the proxy
-        let instance_pre = self.state.instance_pre();
-        let store_data = self.state.store();
-        let mut store = Store::new(instance_pre.engine(), store_data);
-        let indices = ProxyIndices::new(instance_pre)?;
-        let instance = instance_pre.instantiate_async(&mut store).await?;
+        let instance_pre = guest.instance_pre();
+        let mut store = self.state.new_store(guest).await?;
+        let request_id = request.headers().get(REQUEST_ID).and_then(|id| id.to_str().ok());
+        let diagnostics = Diagnostics::start(
+            self.state.options(),
+            &mut store,
+            &instance_pre,
+            guest.name(),
+            request_id,
+        )?;
+        let indices = ProxyIndices::new(&instance_pre)?;
+        let instance = kernel::instantiate(&mut store, &instance_pre).await?;
         let proxy = indices.load(&mut store, &instance)?;
 
         let (sender, receiver) = oneshot::channel();
 
-        tokio::spawn(async move {
+        let guest = self.in_flight.spawn(async move {
             let guest_result = store
                 .run_concurrent(async |store| {
-                    // convert hyper::Request to wasi::Request
-                    let (parts, body) = request.into_parts();
-                    let body = body.map_err(ErrorCode::from_hyper_request_error);
-                    let http_req = http::Request::from_parts(parts, body);
-                    let (request, io_result) = wasi::Request::from_http(http_req);
+                    // convert http::Request to wasi::Request
+                    let (request, io_result) = wasi::Request::from_http(request);
 
                     // forward request to guest
                     let (wasi_resp, task) = proxy.handle(store, request).await??;
@@ -138,20 +201,26 @@ where
                     anyhow::Ok(())
                 })
                 .instrument(debug_span!("http-request"))
-                .await?;
+                .await
+                .unwrap_or_else(Err);
+            diagnostics.finish(&mut store, &guest_result);
 
             if let Err(e) = guest_result {
                 tracing::error!("Guest error: {e:?}");
                 return Err(e);
             }
 
-            // write_profile(&mut store);
-            // drop(epoch_thread);
-
             Ok(())
         });
 
-        let response = receiver.await?.map(|body| body.map_err(Into::into).boxed_unsync());
+        // if the guest exits without responding, return its error
+        let Ok(response) = receiver.await else {
+            return match guest.await? {
+                Err(e) => Err(e),
+                Ok(()) => Err(anyhow!("guest exited without sending a response")),
+            };
+        };
+        let response = response.map(|body| body.map_err(Into::into).boxed_unsync());
         tracing::debug!("received response: {response:?}");
 
         Ok(response)
@@ -159,9 +228,7 @@ where
 }
 
 // Prepare the request for the guest.
-fn fix_request(mut request: hyper::Request<Incoming>) -> Result<hyper::Request<Incoming>> {
-    // let req_id = self.next_id.fetch_add(1, Ordering::Relaxed);
-
+fn fix_request<B>(mut request: http::Request<B>) -> Result<http::Request<B>> {
     // rebuild Uri with scheme and authority explicitly set so they are passed to the Guest
     let uri = request.uri_mut();
     let p_and_q = uri.path_and_query().map_or_else(|| PathAndQuery::from_static("/"), Clone::clone);
@@ -189,31 +256,68 @@ fn fix_request(mut request: hyper::Request<Incoming>) -> Result<hyper::Request<I
     // update the uri with the new scheme and authority
     let (mut parts, body) = request.into_parts();
     parts.uri = uri_builder.build()?;
-    let request = hyper::Request::from_parts(parts, body);
+    let request = http::Request::from_parts(parts, body);
 
     Ok(request)
 }
 
-const BODY: &str = r"<!doctype html>
+// Map an error returned while handling a request to an error response.
+fn guest_error(component: &str, err: &anyhow::Error) -> Response {
+    if DeadlineExceeded::is(err) {
+        return error_response(StatusCode::GATEWAY_TIMEOUT, "Guest deadline exceeded");
+    }
+    if LimitExceeded::is(err) {
+        tracing::error!(monotonic_counter.limit_errors = 1, service = %component, error = %err);
+        return error_response(StatusCode::INTERNAL_SERVER_ERROR, "Guest resource limit exceeded");
+    }
+    error_response(StatusCode::INTERNAL_SERVER_ERROR, "Guest error")
+}
+
+fn error_response(status: StatusCode, detail: &str) -> Response {
+    let title = format!("{} {}", status.as_str(), status.canonical_reason().unwrap_or_default());
+    let html = format!(
+        r"<!doctype html>
 <html>
 <head>
-    <title>500 Internal Server Error</title>
+    <title>{title}</title>
 </head>
 <body>
     <center>
-        <h1>500 Internal Server Error</h1>
+        <h1>{title}</h1>
         <hr>
-        <pre>Guest error</pre>
+        <pre>{detail}</pre>
     </center>
 </body>
-</html>";
-
-fn internal_error() -> hyper::Response<OutgoingBody> {
-    let body = Full::new(Bytes::from(BODY)).map_err(Into::into).boxed_unsync();
+</html>"
+    );
+    let body = Full::new(Bytes::from(html)).map_err(Into::into).boxed_unsync();
 
     hyper::Response::builder()
-        .status(500)
+        .status(status)
         .header("Content-Type", "text/html; charset=UTF-8")
         .body(body)
-        .expect("should build internal error response")
+        .expect("should build error response")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn guest_errors() {
+        let deadline = anyhow::Error::from(DeadlineExceeded(Duration::from_millis(50)))
+            .context("calling guest");
+        assert_eq!(guest_error("guest", &deadline).status(), StatusCode::GATEWAY_TIMEOUT);
+
+        let limit = anyhow::Error::from(LimitExceeded::Table {
+            desired: 20,
+            limit: 10,
+        });
+        assert_eq!(guest_error("guest", &limit).status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let other = anyhow!("guest trapped");
+        assert_eq!(guest_error("guest", &other).status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
 }
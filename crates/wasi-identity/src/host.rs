@@ -57,6 +57,19 @@ where
 
 impl<S> Server<S> for WasiIdentity where S: State {}
 
+/// The host's interfaces, added to the runtime's WIT world.
+pub const WIT: kernel::HostWit = kernel::HostWit {
+    world: &["include wasi:identity/imports@0.1.0-draft;"],
+    deps: &[
+        (
+            "identity@0.1.0-draft/credentials.wit",
+            include_str!("../wit/deps/identity/credentials.wit"),
+        ),
+        ("identity@0.1.0-draft/types.wit", include_str!("../wit/deps/identity/types.wit")),
+        ("identity@0.1.0-draft/world.wit", include_str!("../wit/deps/identity/world.wit")),
+    ],
+};
+
 //===============================================
 // TODO: this could be a generic trait for all WASI hosts
 //===============================================
@@ -3,7 +3,10 @@
 mod atomics_impl;
 mod batch_impl;
 mod default_impl;
+mod replay_impl;
 mod resource;
+#[cfg(feature = "sqlite")]
+mod sqlite_impl;
 mod store_impl;
 
 mod generated {
@@ -31,11 +34,13 @@ mod generated {
 use std::fmt::Debug;
 use std::sync::Arc;
 
-use kernel::{Host, Server, State};
+use kernel::{Capabilities, Host, Named, Server, State};
 use wasmtime::component::{HasData, Linker, ResourceTableError};
 use wasmtime_wasi::ResourceTable;
 
 pub use self::default_impl::KeyValueDefault;
+#[cfg(feature = "sqlite")]
+pub use self::sqlite_impl::KeyValueSqlite;
 use self::generated::wasi::keyvalue::store::Error;
 use self::generated::wasi::keyvalue::{atomics, batch, store};
 pub use self::resource::*;
@@ -62,6 +67,18 @@ where
 
 impl<S> Server<S> for WasiKeyValue where S: State {}
 
+/// The host's interfaces, added to the runtime's WIT world.
+pub const WIT: kernel::HostWit = kernel::HostWit {
+    world: &["include wasi:keyvalue/imports@0.2.0-draft2;"],
+    deps: &[
+        ("keyvalue@0.2.0-draft2/atomic.wit", include_str!("../wit/deps/keyvalue/atomic.wit")),
+        ("keyvalue@0.2.0-draft2/batch.wit", include_str!("../wit/deps/keyvalue/batch.wit")),
+        ("keyvalue@0.2.0-draft2/store.wit", include_str!("../wit/deps/keyvalue/store.wit")),
+        ("keyvalue@0.2.0-draft2/watch.wit", include_str!("../wit/deps/keyvalue/watch.wit")),
+        ("keyvalue@0.2.0-draft2/world.wit", include_str!("../wit/deps/keyvalue/world.wit")),
+    ],
+};
+
 /// A trait which provides internal WASI Key-Value context.
 ///
 /// This is implemented by the resource-specific provider of Key-Value
@@ -70,6 +87,13 @@ pub trait WasiKeyValueCtx: Debug + Send + Sync + 'static {
     fn open_bucket(&self, identifier: String) -> FutureResult<Arc<dyn Bucket>>;
 }
 
+/// Opens buckets using the backend instance named for the bucket.
+impl WasiKeyValueCtx for Named<dyn WasiKeyValueCtx> {
+    fn open_bucket(&self, identifier: String) -> FutureResult<Arc<dyn Bucket>> {
+        self.get(&identifier).open_bucket(identifier)
+    }
+}
+
 /// View into [`WasiKeyValueCtx`] implementation and [`ResourceTable`].
 pub struct WasiKeyValueCtxView<'a> {
     /// Mutable reference to the WASI Key-Value context.
@@ -77,6 +101,9 @@ pub struct WasiKeyValueCtxView<'a> {
 
     /// Mutable reference to table used to manage resources.
     pub table: &'a mut ResourceTable,
+
+    /// The interfaces and named resources the guest may use.
+    pub capabilities: &'a Capabilities,
 }
 
 /// A trait which provides internal WASI Key-Value state.
@@ -102,6 +129,7 @@ macro_rules! wasi_view {
                 wasi_keyvalue::WasiKeyValueCtxView {
                     ctx: &mut self.$field_name,
                     table: &mut self.table,
+                    capabilities: &self.capabilities,
                 }
             }
         }
@@ -30,6 +30,23 @@ pub struct KeyValueDefault {
     store: Store,
 }
 
+impl KeyValueDefault {
+    /// The value of `key` in `bucket`. Used to inspect the store in tests.
+    #[must_use]
+    pub fn value(&self, bucket: &str, key: &str) -> Option<Vec<u8>> {
+        self.store.read().get(bucket)?.get(key).cloned()
+    }
+
+    /// Set the value of `key` in `bucket`. Used to seed the store in tests.
+    pub fn insert(&self, bucket: &str, key: &str, value: impl Into<Vec<u8>>) {
+        self.store
+            .write()
+            .entry(bucket.to_string())
+            .or_default()
+            .insert(key.to_string(), value.into());
+    }
+}
+
 impl Backend for KeyValueDefault {
     type ConnectOptions = ConnectOptions;
 
@@ -67,10 +84,8 @@ struct InMemBucket {
 }
 
 impl Bucket for InMemBucket {
-    fn name(&self) -> &'static str {
-        // Note: This returns a static str, but we need to leak the string
-        // For a proper implementation, consider changing the trait
-        Box::leak(self.name.clone().into_boxed_str())
+    fn name(&self) -> &str {
+        &self.name
     }
 
     fn get(&self, key: String) -> FutureResult<Option<Vec<u8>>> {
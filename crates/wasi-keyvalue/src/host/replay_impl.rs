@@ -0,0 +1,51 @@
+//! Recording and replay of key-value host calls.
+//!
+//! Buckets are wrapped so their calls are recorded, or replayed without an
+//! open bucket, when host call recording is enabled.
+
+use std::sync::Arc;
+
+use futures::FutureExt;
+use kernel::{Recorded, host_calls};
+
+use crate::host::WasiKeyValueCtx;
+use crate::host::resource::{Bucket, FutureResult};
+
+const INTERFACE: &str = "wasi:keyvalue/store";
+
+/// Open the bucket identified by `identifier`, recording its calls when
+/// recording is enabled.
+pub fn open_bucket(ctx: &dyn WasiKeyValueCtx, identifier: String) -> FutureResult<Arc<dyn Bucket>> {
+    if host_calls().is_live() {
+        return ctx.open_bucket(identifier);
+    }
+    let opened =
+        Recorded::open(INTERFACE, "open", identifier.clone(), || ctx.open_bucket(identifier));
+    async move { Ok(Arc::new(opened.await?) as Arc<dyn Bucket>) }.boxed()
+}
+
+impl Bucket for Recorded<Arc<dyn Bucket>> {
+    fn name(&self) -> &str {
+        self.resource().map_or_else(|| self.id(), |bucket| bucket.name())
+    }
+
+    fn get(&self, key: String) -> FutureResult<Option<Vec<u8>>> {
+        self.call("get", &key, |bucket| bucket.get(key.clone()))
+    }
+
+    fn set(&self, key: String, value: Vec<u8>) -> FutureResult<()> {
+        self.call("set", (&key, &value), |bucket| bucket.set(key.clone(), value.clone()))
+    }
+
+    fn delete(&self, key: String) -> FutureResult<()> {
+        self.call("delete", &key, |bucket| bucket.delete(key.clone()))
+    }
+
+    fn exists(&self, key: String) -> FutureResult<bool> {
+        self.call("exists", &key, |bucket| bucket.exists(key.clone()))
+    }
+
+    fn keys(&self) -> FutureResult<Vec<String>> {
+        self.call("keys", (), |bucket| bucket.keys())
+    }
+}
@@ -0,0 +1,203 @@
+//! `SQLite` implementation for wasi-keyvalue
+//!
+//! Persists buckets to a local database file, for development without a
+//! key-value service. Enabled using the `sqlite` feature.
+
+use std::env;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use futures::FutureExt;
+use kernel::Backend;
+use parking_lot::Mutex;
+use rusqlite::{Connection, OptionalExtension, params};
+use tracing::instrument;
+
+use crate::host::WasiKeyValueCtx;
+use crate::host::resource::{Bucket, FutureResult};
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS keyvalue (
+    bucket TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value BLOB NOT NULL,
+    PRIMARY KEY (bucket, key)
+)";
+
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    /// The database file, or `:memory:`.
+    pub database: String,
+}
+
+impl kernel::FromEnv for ConnectOptions {
+    /// The database is read from `KEYVALUE_DATABASE`, defaulting to
+    /// `keyvalue.db`.
+    fn from_env() -> Result<Self> {
+        let database = env::var("KEYVALUE_DATABASE").unwrap_or_else(|_| "keyvalue.db".into());
+        Ok(Self { database })
+    }
+
+    /// Each instance's database is read from `<INSTANCE>_KEYVALUE_DATABASE`,
+    /// falling back to `KEYVALUE_DATABASE`, then `<instance>-keyvalue.db`, so
+    /// that instances do not share a database unless configured to.
+    fn from_instance_env(instance: &str) -> Result<Self> {
+        let database = kernel::instance_var(instance, "KEYVALUE_DATABASE")
+            .unwrap_or_else(|| format!("{instance}-keyvalue.db"));
+        Ok(Self { database })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyValueSqlite {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl Backend for KeyValueSqlite {
+    type ConnectOptions = ConnectOptions;
+
+    #[instrument]
+    async fn connect_with(options: Self::ConnectOptions) -> Result<Self> {
+        tracing::debug!("initializing SQLite key-value store: {}", options.database);
+        let conn = Connection::open(&options.database)
+            .with_context(|| format!("failed to open SQLite database {}", options.database))?;
+        conn.execute_batch(SCHEMA).context("failed to create key-value table")?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+}
+
+impl WasiKeyValueCtx for KeyValueSqlite {
+    fn open_bucket(&self, identifier: String) -> FutureResult<Arc<dyn Bucket>> {
+        tracing::debug!("opening bucket: {identifier}");
+
+        let bucket = SqliteBucket {
+            name: identifier,
+            conn: Arc::clone(&self.conn),
+        };
+        async move { Ok(Arc::new(bucket) as Arc<dyn Bucket>) }.boxed()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SqliteBucket {
+    name: String,
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteBucket {
+    // Run `query` against the bucket's database.
+    fn query<T: Send + 'static>(
+        &self, query: impl FnOnce(&Connection, &str) -> rusqlite::Result<T> + Send + 'static,
+    ) -> FutureResult<T> {
+        let conn = Arc::clone(&self.conn);
+        let name = self.name.clone();
+
+        async move {
+            let result = query(&conn.lock(), &name);
+            result.with_context(|| format!("querying bucket {name}"))
+        }
+        .boxed()
+    }
+}
+
+impl Bucket for SqliteBucket {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn get(&self, key: String) -> FutureResult<Option<Vec<u8>>> {
+        tracing::debug!("getting key: {key} from bucket: {}", self.name);
+        self.query(move |conn, bucket| {
+            conn.query_row(
+                "SELECT value FROM keyvalue WHERE bucket = ?1 AND key = ?2",
+                params![bucket, key],
+                |row| row.get(0),
+            )
+            .optional()
+        })
+    }
+
+    fn set(&self, key: String, value: Vec<u8>) -> FutureResult<()> {
+        tracing::debug!("setting key: {key} in bucket: {}", self.name);
+        self.query(move |conn, bucket| {
+            conn.execute(
+                "INSERT OR REPLACE INTO keyvalue (bucket, key, value) VALUES (?1, ?2, ?3)",
+                params![bucket, key, value],
+            )
+            .map(drop)
+        })
+    }
+
+    fn delete(&self, key: String) -> FutureResult<()> {
+        tracing::debug!("deleting key: {key} from bucket: {}", self.name);
+        self.query(move |conn, bucket| {
+            conn.execute(
+                "DELETE FROM keyvalue WHERE bucket = ?1 AND key = ?2",
+                params![bucket, key],
+            )
+            .map(drop)
+        })
+    }
+
+    fn exists(&self, key: String) -> FutureResult<bool> {
+        tracing::debug!("checking existence of key: {key} in bucket: {}", self.name);
+        self.query(move |conn, bucket| {
+            conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM keyvalue WHERE bucket = ?1 AND key = ?2)",
+                params![bucket, key],
+                |row| row.get(0),
+            )
+        })
+    }
+
+    fn keys(&self) -> FutureResult<Vec<String>> {
+        tracing::debug!("listing keys in bucket: {}", self.name);
+        self.query(|conn, bucket| {
+            let mut stmt = conn.prepare("SELECT key FROM keyvalue WHERE bucket = ?1")?;
+            stmt.query_map([bucket], |row| row.get(0))?.collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn bucket_operations() {
+        let options = ConnectOptions {
+            database: ":memory:".to_string(),
+        };
+        let ctx = KeyValueSqlite::connect_with(options).await.expect("connect");
+
+        let bucket = ctx.open_bucket("test-bucket".to_string()).await.expect("open bucket");
+        bucket.set("key1".to_string(), b"value1".to_vec()).await.expect("set");
+        bucket.set("key2".to_string(), b"value2".to_vec()).await.expect("set");
+        let value = bucket.get("key1".to_string()).await.expect("get");
+        assert_eq!(value, Some(b"value1".to_vec()));
+        assert!(bucket.exists("key1".to_string()).await.expect("exists"));
+
+        // buckets share the database but not keys
+        let other = ctx.open_bucket("other-bucket".to_string()).await.expect("open bucket");
+        assert!(!other.exists("key1".to_string()).await.expect("exists"));
+
+        let mut keys = bucket.keys().await.expect("keys");
+        keys.sort();
+        assert_eq!(keys, vec!["key1".to_string(), "key2".to_string()]);
+
+        bucket.delete("key1".to_string()).await.expect("delete");
+        assert_eq!(bucket.get("key1".to_string()).await.expect("get"), None);
+    }
+
+    #[test]
+    fn instance_databases() {
+        use kernel::FromEnv;
+
+        let cache = ConnectOptions::from_instance_env("cache").expect("options");
+        let state = ConnectOptions::from_instance_env("state").expect("options");
+        assert_eq!(cache.database, "cache-keyvalue.db");
+        assert_eq!(state.database, "state-keyvalue.db");
+    }
+}
@@ -1,4 +1,5 @@
 use anyhow::Context;
+use kernel::ResourceKind;
 use wasmtime::component::{Access, Accessor, Resource};
 
 use crate::host::generated::wasi::keyvalue::store::{
@@ -6,13 +7,21 @@ use crate::host::generated::wasi::keyvalue::store::{
 };
 use crate::host::resource::BucketProxy;
 use crate::host::store::{Host, HostBucket};
-use crate::host::{Result, WasiKeyValue, WasiKeyValueCtxView};
+use crate::host::{Result, WasiKeyValue, WasiKeyValueCtxView, replay_impl};
 
 impl HostWithStore for WasiKeyValue {
     async fn open<T>(
         accessor: &Accessor<T, Self>, identifier: String,
     ) -> Result<Resource<BucketProxy>> {
-        let bucket = accessor.with(|mut store| store.get().ctx.open_bucket(identifier)).await?;
+        let bucket = accessor
+            .with(|mut store| {
+                let view = store.get();
+                if !view.capabilities.allows(ResourceKind::Bucket, &identifier) {
+                    return Err(Error::AccessDenied);
+                }
+                Ok(replay_impl::open_bucket(view.ctx, identifier))
+            })?
+            .await?;
         let proxy = BucketProxy(bucket);
         Ok(accessor.with(|mut store| store.get().table.push(proxy))?)
     }
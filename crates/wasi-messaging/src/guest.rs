@@ -3,6 +3,8 @@
 
 //! # WASI Messaging Guest
 
+mod incoming;
+
 // Bindings for the `wasi:messaging` world.
 // See (<https://github.com/WebAssembly/wasi-messaging/>)
 wit_bindgen::generate!({
@@ -19,4 +21,5 @@ wit_bindgen::generate!({
 });
 
 pub use self::exports::wasi::messaging::*;
+pub use self::incoming::*;
 pub use self::wasi::messaging::*;
@@ -0,0 +1,60 @@
+//! Serve incoming messages using a [`MessageRouter`].
+
+use fabric::{MessageRouter, Provider};
+
+use crate::guest::wasi::messaging::request_reply;
+use crate::guest::wasi::messaging::types::{Error, Message};
+
+/// Route a message received by the `incoming-handler` using `router`.
+///
+/// The handler's reply is published to the message's reply topic, or dropped
+/// when the message has none.
+///
+/// ```rust,ignore
+/// impl wasi_messaging::incoming_handler::Guest for Messaging {
+///     async fn handle(message: Message) -> Result<(), Error> {
+///         let router = MessageRouter::new(client).on::<OrderPlaced>("orders.v1");
+///         wasi_messaging::serve(&router, message).await
+///     }
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::Other`] if the message cannot be handled, or an error if
+/// the reply cannot be published.
+pub async fn serve<P: Provider + 'static>(
+    router: &MessageRouter<P>, message: Message,
+) -> Result<(), Error> {
+    let topic = message.topic().unwrap_or_default();
+    let mut incoming = fabric::Message::new(&message.data());
+    // header names are case-insensitive, so normalise metadata keys
+    incoming.headers.extend(
+        message
+            .metadata()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(k, v)| (k.to_ascii_lowercase(), v)),
+    );
+    if let Some(content_type) = message.content_type() {
+        incoming.headers.insert("content-type".to_string(), content_type);
+    }
+
+    let reply = router
+        .handle(&topic, &incoming)
+        .await
+        .map_err(|e| Error::Other(format!("issue handling message on {topic}: {e}")))?;
+    let Some(reply) = reply else {
+        return Ok(());
+    };
+
+    let response = Message::new(&reply.payload);
+    for (key, value) in &reply.headers {
+        if key.eq_ignore_ascii_case("content-type") {
+            response.set_content_type(value);
+        } else {
+            response.add_metadata(key, value);
+        }
+    }
+    request_reply::reply(&message, response).await
+}
@@ -1,5 +1,7 @@
 pub mod default_impl;
+mod harness;
 mod producer_impl;
+mod replay_impl;
 mod request_reply_impl;
 mod resource;
 mod server;
@@ -37,7 +39,7 @@ mod generated {
 use std::fmt::Debug;
 use std::sync::Arc;
 
-use kernel::{Host, Server, State};
+use kernel::{Capabilities, Host, Server, State};
 use wasmtime::component::{HasData, Linker};
 use wasmtime_wasi::{ResourceTable, ResourceTableError};
 
@@ -45,6 +47,7 @@ pub use self::default_impl::MessagingDefault;
 pub use self::generated::Messaging;
 pub use self::generated::wasi::messaging::types::Error;
 use self::generated::wasi::messaging::{producer, request_reply, types};
+pub use self::harness::MessagingHarness;
 pub use self::resource::*;
 
 pub type Result<T, E = Error> = anyhow::Result<T, E>;
@@ -77,6 +80,24 @@ where
     }
 }
 
+/// The host's interfaces, added to the runtime's WIT world.
+pub const WIT: kernel::HostWit = kernel::HostWit {
+    world: &["include wasi:messaging/messaging-request-reply@0.2.0-draft;"],
+    deps: &[
+        ("messaging@0.2.0-draft/guest.wit", include_str!("../wit/deps/messaging/guest.wit")),
+        (
+            "messaging@0.2.0-draft/messaging.wit",
+            include_str!("../wit/deps/messaging/messaging.wit"),
+        ),
+        ("messaging@0.2.0-draft/producer.wit", include_str!("../wit/deps/messaging/producer.wit")),
+        (
+            "messaging@0.2.0-draft/request-reply.wit",
+            include_str!("../wit/deps/messaging/request-reply.wit"),
+        ),
+        ("messaging@0.2.0-draft/types.wit", include_str!("../wit/deps/messaging/types.wit")),
+    ],
+};
+
 /// A trait which provides internal WASI Messaging context.
 ///
 /// This is implemented by the resource-specific provider of messaging
@@ -150,6 +171,9 @@ pub struct WasiMessagingCtxView<'a> {
 
     /// Mutable reference to table used to manage resources.
     pub table: &'a mut ResourceTable,
+
+    /// The interfaces and named resources the guest may use.
+    pub capabilities: &'a Capabilities,
 }
 
 /// A trait which provides internal WASI Key-Value state.
@@ -181,6 +205,7 @@ macro_rules! wasi_view {
                 wasi_messaging::WasiMessagingCtxView {
                     ctx: &mut self.$field_name,
                     table: &mut self.table,
+                    capabilities: &self.capabilities,
                 }
             }
         }
@@ -42,6 +42,15 @@ impl Clone for MessagingDefault {
     }
 }
 
+impl MessagingDefault {
+    /// Receive the messages sent from this point on. Used to inspect the
+    /// messages sent by guests in tests.
+    #[must_use]
+    pub fn listen(&self) -> Receiver<MessageProxy> {
+        self.sender.subscribe()
+    }
+}
+
 impl Backend for MessagingDefault {
     type ConnectOptions = ConnectOptions;
 
@@ -215,7 +224,7 @@ impl Client for MessagingDefault {
 }
 
 #[derive(Debug, Clone, Default)]
-struct InMemMessage {
+pub(crate) struct InMemMessage {
     topic: String,
     payload: Vec<u8>,
     metadata: Option<Metadata>,
@@ -223,6 +232,15 @@ struct InMemMessage {
     reply: Option<Reply>,
 }
 
+impl InMemMessage {
+    pub(crate) fn new(topic: impl Into<String>, payload: Vec<u8>) -> Self {
+        Self {
+            topic: topic.into(),
+            ..Self::from(payload)
+        }
+    }
+}
+
 impl From<Vec<u8>> for InMemMessage {
     fn from(data: Vec<u8>) -> Self {
         Self {
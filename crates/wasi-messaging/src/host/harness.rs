@@ -0,0 +1,40 @@
+//! # Messaging Test Harness
+//!
+//! Delivers messages to guests' `incoming-handler` from tests (see
+//! [`kernel::testing`]).
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use kernel::State;
+use kernel::testing::Harness;
+
+use crate::host::default_impl::InMemMessage;
+use crate::host::resource::MessageProxy;
+use crate::host::{WasiMessagingView, server};
+
+/// Deliver messages to guests.
+pub trait MessagingHarness {
+    /// Deliver a message with `payload`, published to `topic`, to each guest
+    /// subscribed to the topic.
+    ///
+    /// Messages are created using the in-memory messaging backend
+    /// ([`MessagingDefault`](crate::MessagingDefault)).
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error returned by a guest handling the message.
+    fn deliver(&self, topic: &str, payload: impl Into<Vec<u8>>)
+    -> impl Future<Output = Result<()>>;
+}
+
+impl<S> MessagingHarness for Harness<S>
+where
+    S: State,
+    S::StoreCtx: WasiMessagingView,
+{
+    async fn deliver(&self, topic: &str, payload: impl Into<Vec<u8>>) -> Result<()> {
+        let message = InMemMessage::new(topic, payload.into());
+        server::deliver(self.state(), MessageProxy(Arc::new(message))).await
+    }
+}
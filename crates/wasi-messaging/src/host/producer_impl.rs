@@ -1,7 +1,8 @@
+use kernel::ResourceKind;
 use wasmtime::component::{Accessor, Resource};
 
 use crate::host::generated::wasi::messaging::producer::{Host, HostWithStore};
-use crate::host::generated::wasi::messaging::types::Topic;
+use crate::host::generated::wasi::messaging::types::{Error, Topic};
 use crate::host::resource::{ClientProxy, MessageProxy};
 use crate::host::types_impl::{get_client, get_message};
 use crate::host::{Result, WasiMessaging, WasiMessagingCtxView};
@@ -11,6 +12,7 @@ impl HostWithStore for WasiMessaging {
         accessor: &Accessor<T, Self>, c: Resource<ClientProxy>, topic: Topic,
         message: Resource<MessageProxy>,
     ) -> Result<()> {
+        check_topic(accessor, &topic)?;
         let client = get_client(accessor, &c)?;
         let msg = get_message(accessor, &message)?;
         client.send(topic, msg).await?;
@@ -20,3 +22,12 @@ impl HostWithStore for WasiMessaging {
 }
 
 impl Host for WasiMessagingCtxView<'_> {}
+
+/// Fail unless the guest's capabilities allow it to use `topic`.
+pub fn check_topic<T>(accessor: &Accessor<T, WasiMessaging>, topic: &str) -> Result<()> {
+    if accessor.with(|mut store| store.get().capabilities.allows(ResourceKind::Topic, topic)) {
+        Ok(())
+    } else {
+        Err(Error::PermissionDenied(format!("topic `{topic}` is not allowed")))
+    }
+}
@@ -0,0 +1,106 @@
+//! Recording and replay of messaging host calls.
+//!
+//! Clients are wrapped so the messages they send, and the replies they
+//! receive, are recorded (or replayed without a connected client) when host
+//! call recording is enabled.
+
+use std::any::Any;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use futures::{FutureExt, future};
+use kernel::{Recorded, host_calls};
+use serde::{Deserialize, Serialize};
+
+use crate::host::WasiMessagingCtx;
+use crate::host::resource::{
+    Client, FutureResult, Message, MessageProxy, Metadata, Reply, RequestOptions, Subscriptions,
+};
+
+const INTERFACE: &str = "wasi:messaging/producer";
+
+/// Connect the client `name`, recording its calls when recording is enabled.
+pub fn connect(ctx: &dyn WasiMessagingCtx, name: String) -> FutureResult<Arc<dyn Client>> {
+    if host_calls().is_live() {
+        return ctx.connect();
+    }
+    let connected = Recorded::open(INTERFACE, "connect", name, || ctx.connect());
+    async move { Ok(Arc::new(connected.await?) as Arc<dyn Client>) }.boxed()
+}
+
+impl Client for Recorded<Arc<dyn Client>> {
+    fn subscribe(&self) -> FutureResult<Subscriptions> {
+        self.resource().map_or_else(
+            || future::ready(Err(anyhow!("subscriptions cannot be replayed"))).boxed(),
+            |client| client.subscribe(),
+        )
+    }
+
+    fn send(&self, topic: String, message: MessageProxy) -> FutureResult<()> {
+        self.call("send", (&topic, RecordedMessage::from(&message)), |client| {
+            client.send(topic.clone(), message.clone())
+        })
+    }
+
+    fn request(
+        &self, topic: String, message: MessageProxy, options: Option<RequestOptions>,
+    ) -> FutureResult<MessageProxy> {
+        let reply = self.call("request", (&topic, RecordedMessage::from(&message)), |client| {
+            let reply = client.request(topic.clone(), message.clone(), options);
+            async move { Ok(RecordedMessage::from(&reply.await?)) }.boxed()
+        });
+        async move { Ok(MessageProxy(Arc::new(reply.await?))) }.boxed()
+    }
+}
+
+/// A message as recorded.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct RecordedMessage {
+    topic: String,
+    payload: Vec<u8>,
+    metadata: Option<Metadata>,
+    description: Option<String>,
+    reply: Option<Reply>,
+}
+
+impl From<&MessageProxy> for RecordedMessage {
+    fn from(message: &MessageProxy) -> Self {
+        Self {
+            topic: message.topic(),
+            payload: message.payload(),
+            metadata: message.metadata(),
+            description: message.description(),
+            reply: message.reply(),
+        }
+    }
+}
+
+impl Message for RecordedMessage {
+    fn topic(&self) -> String {
+        self.topic.clone()
+    }
+
+    fn payload(&self) -> Vec<u8> {
+        self.payload.clone()
+    }
+
+    fn metadata(&self) -> Option<Metadata> {
+        self.metadata.clone()
+    }
+
+    fn description(&self) -> Option<String> {
+        self.description.clone()
+    }
+
+    fn length(&self) -> usize {
+        self.payload.len()
+    }
+
+    fn reply(&self) -> Option<Reply> {
+        self.reply.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -6,15 +6,17 @@ use crate::host::generated::wasi::messaging::request_reply::{
     Host, HostRequestOptions, HostRequestOptionsWithStore, HostWithStore,
 };
 use crate::host::generated::wasi::messaging::types::Topic;
+use crate::host::producer_impl::check_topic;
 use crate::host::resource::{ClientProxy, MessageProxy, RequestOptions};
 use crate::host::types_impl::{get_client, get_message};
-use crate::host::{Result, WasiMessaging, WasiMessagingCtxView};
+use crate::host::{Result, WasiMessaging, WasiMessagingCtxView, replay_impl};
 
 impl HostWithStore for WasiMessaging {
     async fn request<T>(
         accessor: &Accessor<T, Self>, c: Resource<ClientProxy>, topic: Topic,
         message: Resource<MessageProxy>, options: Option<Resource<RequestOptions>>,
     ) -> Result<Vec<Resource<MessageProxy>>> {
+        check_topic(accessor, &topic)?;
         let client = get_client(accessor, &c)?;
         let request = get_message(accessor, &message)?;
         let options = accessor.with(|mut access| {
@@ -41,7 +43,8 @@ impl HostWithStore for WasiMessaging {
         let reply_to = get_message(accessor, &reply_to)?;
         let Some(reply) = &reply_to.reply() else { return Ok(()) };
 
-        let client = accessor.with(|mut store| store.get().ctx.connect()).await?;
+        let name = reply.client_name.clone();
+        let client = accessor.with(|mut store| replay_impl::connect(store.get().ctx, name)).await?;
         let message = get_message(accessor, &message)?;
 
         client.send(reply.topic.clone(), message).await?;
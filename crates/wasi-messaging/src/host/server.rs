@@ -1,10 +1,7 @@
-use std::env;
-
 use anyhow::{Context, Result, anyhow};
 use futures::StreamExt;
-use kernel::State;
+use kernel::{DeadlineExceeded, Diagnostics, Guest, InFlight, LimitExceeded, State};
 use tracing::{Instrument, debug_span, instrument};
-use wasmtime::Store;
 
 use crate::host::WasiMessagingView;
 use crate::host::generated::Messaging;
@@ -16,32 +13,85 @@ where
     S: State,
     S::StoreCtx: WasiMessagingView,
 {
-    let component = env::var("COMPONENT").unwrap_or_else(|_| "unknown".into());
-    tracing::info!("starting messaging server for: {component}");
+    for guest in state.guests().iter() {
+        tracing::info!("starting messaging server for: {}", guest.name());
+    }
 
-    let handler = Handler {
-        state: state.clone(),
-        component,
-    };
+    let handler = Handler { state: state.clone() };
     let mut stream = handler.subscriptions().await?;
+    let shutdown = state.shutdown().clone();
+    let in_flight = InFlight::default();
+
+    // process messages until shutdown
+    loop {
+        let message = tokio::select! {
+            message = stream.next() => match message {
+                Some(message) => message,
+                None => break,
+            },
+            () = shutdown.triggered() => break,
+        };
+
+        // forward the message to each guest subscribed to its topic
+        let topic = message.topic();
+        let mut guests = state.guests().subscribed(&topic).peekable();
+        if guests.peek().is_none() {
+            tracing::debug!("no guest subscribed to topic: {topic}");
+        }
+        for guest in guests {
+            let handler = handler.clone();
+            let guest = guest.clone();
+            let message = message.clone();
 
-    while let Some(message) = stream.next().await {
-        let handler = handler.clone();
-        tokio::spawn(async move {
-            tracing::info!(monotonic_counter.message_counter = 1, service = %handler.component);
-
-            if let Err(e) = handler.handle(message.clone()).await {
-                tracing::error!("issue processing message: {e}");
-                tracing::error!(
-                    monotonic_counter.processing_errors = 1,
-                    service = %handler.component,
-                    topic = %message.topic(),
-                    error = %e,
-                );
-            }
-        });
+            in_flight.spawn(async move {
+                tracing::info!(monotonic_counter.message_counter = 1, service = %guest.name());
+
+                if let Err(e) = handler.handle(&guest, message.clone()).await {
+                    if DeadlineExceeded::is(&e) {
+                        tracing::error!(
+                            monotonic_counter.timeout_errors = 1,
+                            service = %guest.name(),
+                            topic = %message.topic(),
+                            error = %e,
+                        );
+                    } else if LimitExceeded::is(&e) {
+                        tracing::error!(
+                            monotonic_counter.limit_errors = 1,
+                            service = %guest.name(),
+                            topic = %message.topic(),
+                            error = %e,
+                        );
+                    }
+                    tracing::error!("issue processing message: {e}");
+                    tracing::error!(
+                        monotonic_counter.processing_errors = 1,
+                        service = %guest.name(),
+                        topic = %message.topic(),
+                        error = %e,
+                    );
+                }
+            });
+        }
     }
 
+    tracing::info!("stopping messaging server");
+    drop(stream);
+    in_flight.drain(state.options().grace_period()).await;
+
+    Ok(())
+}
+
+/// Deliver a message to each guest subscribed to its topic, without starting
+/// the server.
+pub(crate) async fn deliver<S>(state: &S, message: MessageProxy) -> Result<()>
+where
+    S: State,
+    S::StoreCtx: WasiMessagingView,
+{
+    let handler = Handler { state: state.clone() };
+    for guest in state.guests().subscribed(&message.topic()) {
+        handler.handle(guest, message.clone()).await?;
+    }
     Ok(())
 }
 
@@ -52,7 +102,6 @@ where
     S::StoreCtx: WasiMessagingView,
 {
     state: S,
-    component: String,
 }
 
 impl<S> Handler<S>
@@ -61,33 +110,41 @@ where
     S::StoreCtx: WasiMessagingView,
 {
     // Forward message to the wasm guest.
-    async fn handle(&self, message: MessageProxy) -> Result<()> {
-        let mut store_data = self.state.store();
-        let msg_res = store_data
+    async fn handle(&self, guest: &Guest<S::StoreCtx>, message: MessageProxy) -> Result<()> {
+        let mut store = self.state.new_store(guest).await?;
+        let msg_res = store
+            .data_mut()
             .messaging()
             .table
             .push(message)
             .map_err(|e| anyhow!("failed to push message: {e}"))?;
 
-        let instance_pre = self.state.instance_pre();
-        let mut store = Store::new(instance_pre.engine(), store_data);
-        let instance = instance_pre.instantiate_async(&mut store).await?;
+        let instance_pre = guest.instance_pre();
+        let diagnostics = Diagnostics::start(
+            self.state.options(),
+            &mut store,
+            &instance_pre,
+            guest.name(),
+            None,
+        )?;
+        let instance = kernel::instantiate(&mut store, &instance_pre).await?;
         let messaging = Messaging::new(&mut store, &instance)?;
 
-        store
+        let result = store
             .run_concurrent(async |store| {
                 let guest = messaging.wasi_messaging_incoming_handler();
                 guest.call_handle(store, msg_res).await.map(|_| ()).context("issue sending message")
             })
             .instrument(debug_span!("messaging-handle"))
-            .await?
+            .await
+            .unwrap_or_else(Err);
+        diagnostics.finish(&mut store, &result);
+        result
     }
 
     // Get subscriptions for the topics configured in the wasm component.
     async fn subscriptions(&self) -> Result<Subscriptions> {
-        let instance_pre = self.state.instance_pre();
-        let store_data = self.state.store();
-        let mut store = Store::new(instance_pre.engine(), store_data);
+        let mut store = self.state.new_store(self.state.guests().primary()).await?;
 
         store
             .run_concurrent(async |store| {
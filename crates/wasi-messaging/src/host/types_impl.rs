@@ -7,13 +7,13 @@ pub use crate::host::generated::wasi::messaging::types::{
     Error, Host, HostClient, HostClientWithStore, HostMessage, HostMessageWithStore, Topic,
 };
 use crate::host::resource::{ClientProxy, MessageProxy};
-use crate::host::{Result, WasiMessaging, WasiMessagingCtxView};
+use crate::host::{Result, WasiMessaging, WasiMessagingCtxView, replay_impl};
 
 impl HostClientWithStore for WasiMessaging {
     async fn connect<T>(
-        accessor: &Accessor<T, Self>, _name: String,
+        accessor: &Accessor<T, Self>, name: String,
     ) -> Result<Resource<ClientProxy>> {
-        let client = accessor.with(|mut store| store.get().ctx.connect()).await?;
+        let client = accessor.with(|mut store| replay_impl::connect(store.get().ctx, name)).await?;
         let proxy = ClientProxy(client);
         Ok(accessor.with(|mut store| store.get().table.push(proxy))?)
     }
@@ -52,6 +52,29 @@ where
 
 impl<S: State> Server<S> for WasiOtel {}
 
+/// The host's interfaces, added to the runtime's WIT world.
+pub const WIT: kernel::HostWit = kernel::HostWit {
+    world: &["include wasi:otel/imports@0.2.0-draft;"],
+    deps: &[
+        (
+            "clocks@0.2.8/monotonic-clock.wit",
+            include_str!("../wit/deps/clocks/monotonic-clock.wit"),
+        ),
+        ("clocks@0.2.8/timezone.wit", include_str!("../wit/deps/clocks/timezone.wit")),
+        ("clocks@0.2.8/wall-clock.wit", include_str!("../wit/deps/clocks/wall-clock.wit")),
+        ("clocks@0.2.8/world.wit", include_str!("../wit/deps/clocks/world.wit")),
+        ("io@0.2.8/error.wit", include_str!("../wit/deps/io/error.wit")),
+        ("io@0.2.8/poll.wit", include_str!("../wit/deps/io/poll.wit")),
+        ("io@0.2.8/streams.wit", include_str!("../wit/deps/io/streams.wit")),
+        ("io@0.2.8/world.wit", include_str!("../wit/deps/io/world.wit")),
+        ("otel@0.2.0-draft/metrics.wit", include_str!("../wit/deps/otel/metrics.wit")),
+        ("otel@0.2.0-draft/resource.wit", include_str!("../wit/deps/otel/resource.wit")),
+        ("otel@0.2.0-draft/tracing.wit", include_str!("../wit/deps/otel/tracing.wit")),
+        ("otel@0.2.0-draft/types.wit", include_str!("../wit/deps/otel/types.wit")),
+        ("otel@0.2.0-draft/world.wit", include_str!("../wit/deps/otel/world.wit")),
+    ],
+};
+
 /// A trait which provides internal WASI OpenTelemetry state.
 ///
 /// This is implemented by the `T` in `Linker<T>` — a single type shared across
@@ -4,6 +4,7 @@
 
 pub mod default_impl;
 mod readwrite_impl;
+mod replay_impl;
 mod resource;
 mod types_impl;
 
@@ -20,6 +21,7 @@ mod generated {
         imports: {
             default: store | tracing | trappable,
         },
+        additional_derives: [serde::Deserialize, serde::Serialize],
         with: {
             "wasi:sql/types.connection": ConnectionProxy,
             "wasi:sql/types.statement": Statement,
@@ -34,7 +36,7 @@ mod generated {
 use std::fmt::Debug;
 use std::sync::Arc;
 
-use kernel::{Host, Server, State};
+use kernel::{Capabilities, Host, Named, Server, State};
 use wasmtime::component::{HasData, Linker};
 use wasmtime_wasi::ResourceTable;
 
@@ -62,6 +64,16 @@ where
 
 impl<S> Server<S> for WasiSql where S: State {}
 
+/// The host's interfaces, added to the runtime's WIT world.
+pub const WIT: kernel::HostWit = kernel::HostWit {
+    world: &["include wasi:sql/imports@0.2.0-draft;"],
+    deps: &[
+        ("sql@0.2.0-draft/readwrite.wit", include_str!("../wit/deps/sql/readwrite.wit")),
+        ("sql@0.2.0-draft/sql.wit", include_str!("../wit/deps/sql/sql.wit")),
+        ("sql@0.2.0-draft/types.wit", include_str!("../wit/deps/sql/types.wit")),
+    ],
+};
+
 /// A trait which provides internal WASI SQL context.
 ///
 /// This is implemented by the resource-specific provider of SQL
@@ -70,6 +82,13 @@ pub trait WasiSqlCtx: Debug + Send + Sync + 'static {
     fn open(&self, name: String) -> FutureResult<Arc<dyn Connection>>;
 }
 
+/// Opens databases using the backend instance named for the database.
+impl WasiSqlCtx for Named<dyn WasiSqlCtx> {
+    fn open(&self, name: String) -> FutureResult<Arc<dyn Connection>> {
+        self.get(&name).open(name)
+    }
+}
+
 /// View into [`WasiSqlCtx`] implementation and [`ResourceTable`].
 pub struct WasiSqlCtxView<'a> {
     /// Mutable reference to the WASI SQL context.
@@ -77,6 +96,9 @@ pub struct WasiSqlCtxView<'a> {
 
     /// Mutable reference to table used to manage resources.
     pub table: &'a mut ResourceTable,
+
+    /// The interfaces and named resources the guest may use.
+    pub capabilities: &'a Capabilities,
 }
 
 /// A trait which provides internal WASI Key-Value state.
@@ -96,6 +118,7 @@ macro_rules! wasi_view {
                 wasi_sql::WasiSqlCtxView {
                     ctx: &mut self.$field_name,
                     table: &mut self.table,
+                    capabilities: &self.capabilities,
                 }
             }
         }
@@ -30,6 +30,13 @@ impl kernel::FromEnv for ConnectOptions {
     fn from_env() -> Result<Self> {
         Self::from_env().finalize().context("issue loading connection options")
     }
+
+    /// Each instance's database is read from `<INSTANCE>_SQL_DATABASE`,
+    /// falling back to `SQL_DATABASE`.
+    fn from_instance_env(instance: &str) -> Result<Self> {
+        kernel::instance_var(instance, "SQL_DATABASE")
+            .map_or_else(kernel::FromEnv::from_env, |database| Ok(Self { database }))
+    }
 }
 
 #[derive(Debug, Clone)]
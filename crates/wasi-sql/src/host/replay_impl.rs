@@ -0,0 +1,38 @@
+//! Recording and replay of SQL host calls.
+//!
+//! Connections are wrapped so their calls are recorded, or replayed without
+//! an open connection, when host call recording is enabled.
+
+use std::sync::Arc;
+
+use futures::FutureExt;
+use kernel::{Recorded, host_calls};
+
+use crate::host::resource::{Connection, FutureResult};
+use crate::host::{DataType, Row, WasiSqlCtx};
+
+const INTERFACE: &str = "wasi:sql/readwrite";
+
+/// Open the connection identified by `name`, recording its calls when
+/// recording is enabled.
+pub fn open_connection(ctx: &dyn WasiSqlCtx, name: String) -> FutureResult<Arc<dyn Connection>> {
+    if host_calls().is_live() {
+        return ctx.open(name);
+    }
+    let opened = Recorded::open(INTERFACE, "open", name.clone(), || ctx.open(name));
+    async move { Ok(Arc::new(opened.await?) as Arc<dyn Connection>) }.boxed()
+}
+
+impl Connection for Recorded<Arc<dyn Connection>> {
+    fn query(&self, query: String, params: Vec<DataType>) -> FutureResult<Vec<Row>> {
+        self.call("query", (&query, &params), |connection| {
+            connection.query(query.clone(), params.clone())
+        })
+    }
+
+    fn exec(&self, query: String, params: Vec<DataType>) -> FutureResult<u32> {
+        self.call("exec", (&query, &params), |connection| {
+            connection.exec(query.clone(), params.clone())
+        })
+    }
+}
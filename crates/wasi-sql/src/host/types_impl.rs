@@ -1,4 +1,5 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use kernel::ResourceKind;
 use wasmtime::component::{Access, Accessor, Resource};
 
 use crate::host::generated::wasi::sql::types::{
@@ -6,13 +7,19 @@ use crate::host::generated::wasi::sql::types::{
     HostErrorWithStore, HostStatement, HostStatementWithStore, Statement,
 };
 use crate::host::resource::ConnectionProxy;
-use crate::host::{WasiSql, WasiSqlCtxView};
+use crate::host::{WasiSql, WasiSqlCtxView, replay_impl};
 
 impl HostConnectionWithStore for WasiSql {
     async fn open<T>(
         accessor: &Accessor<T, Self>, name: String,
     ) -> Result<Result<Resource<Connection>, Resource<Error>>> {
-        let open_conn = accessor.with(|mut store| store.get().ctx.open(name)).await;
+        let allowed = accessor
+            .with(|mut store| store.get().capabilities.allows(ResourceKind::Database, &name));
+        let open_conn = if allowed {
+            accessor.with(|mut store| replay_impl::open_connection(store.get().ctx, name)).await
+        } else {
+            Err(anyhow!("access denied to database `{name}`"))
+        };
 
         let result = match open_conn {
             Ok(conn) => {
@@ -3,7 +3,10 @@
 //! This module implements the host-side logic for the WASI Vault service.
 
 pub mod default_impl;
+mod replay_impl;
 mod resource;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_impl;
 mod vault_impl;
 
 mod generated {
@@ -30,12 +33,14 @@ mod generated {
 use std::fmt::Debug;
 use std::sync::Arc;
 
-use kernel::{Host, Server, State};
+use kernel::{Capabilities, Host, Named, Server, State};
 use wasmtime::component::{HasData, Linker};
 use wasmtime_wasi::ResourceTable;
 
 use self::generated::wasi::vault::vault;
 pub use crate::host::default_impl::VaultDefault;
+#[cfg(feature = "sqlite")]
+pub use crate::host::sqlite_impl::VaultSqlite;
 pub use crate::host::resource::*;
 
 #[derive(Debug)]
@@ -56,6 +61,15 @@ where
 
 impl<S> Server<S> for WasiVault where S: State {}
 
+/// The host's interfaces, added to the runtime's WIT world.
+pub const WIT: kernel::HostWit = kernel::HostWit {
+    world: &["include wasi:vault/imports@0.1.0-draft;"],
+    deps: &[
+        ("vault@0.1.0-draft/vault.wit", include_str!("../wit/deps/vault/vault.wit")),
+        ("vault@0.1.0-draft/world.wit", include_str!("../wit/deps/vault/world.wit")),
+    ],
+};
+
 /// A trait which provides internal WASI Key-Value context.
 ///
 /// This is implemented by the resource-specific provider of Key-Value
@@ -64,6 +78,13 @@ pub trait WasiVaultCtx: Debug + Send + Sync + 'static {
     fn open_locker(&self, identifier: String) -> FutureResult<Arc<dyn Locker>>;
 }
 
+/// Opens lockers using the backend instance named for the locker.
+impl WasiVaultCtx for Named<dyn WasiVaultCtx> {
+    fn open_locker(&self, identifier: String) -> FutureResult<Arc<dyn Locker>> {
+        self.get(&identifier).open_locker(identifier)
+    }
+}
+
 /// View into [`WasiVaultCtx`] implementation and [`ResourceTable`].
 pub struct WasiVaultCtxView<'a> {
     /// Mutable reference to the WASI Key-Value context.
@@ -71,6 +92,9 @@ pub struct WasiVaultCtxView<'a> {
 
     /// Mutable reference to table used to manage resources.
     pub table: &'a mut ResourceTable,
+
+    /// The interfaces and named resources the guest may use.
+    pub capabilities: &'a Capabilities,
 }
 
 /// A trait which provides internal WASI Key-Value state.
@@ -90,6 +114,7 @@ macro_rules! wasi_view {
                 wasi_vault::WasiVaultCtxView {
                     ctx: &mut self.$field_name,
                     table: &mut self.table,
+                    capabilities: &self.capabilities,
                 }
             }
         }
@@ -0,0 +1,54 @@
+//! Recording and replay of vault host calls.
+//!
+//! Lockers are wrapped so their calls are recorded, or replayed without an
+//! open locker, when host call recording is enabled. N.B. recordings include
+//! the secrets read and written by guests.
+
+use std::sync::Arc;
+
+use futures::FutureExt;
+use kernel::{Recorded, host_calls};
+
+use crate::host::WasiVaultCtx;
+use crate::host::resource::{FutureResult, Locker};
+
+const INTERFACE: &str = "wasi:vault/vault";
+
+/// Open the locker identified by `identifier`, recording its calls when
+/// recording is enabled.
+pub fn open_locker(ctx: &dyn WasiVaultCtx, identifier: String) -> FutureResult<Arc<dyn Locker>> {
+    if host_calls().is_live() {
+        return ctx.open_locker(identifier);
+    }
+    let opened =
+        Recorded::open(INTERFACE, "open", identifier.clone(), || ctx.open_locker(identifier));
+    async move { Ok(Arc::new(opened.await?) as Arc<dyn Locker>) }.boxed()
+}
+
+impl Locker for Recorded<Arc<dyn Locker>> {
+    fn identifier(&self) -> String {
+        self.id().to_string()
+    }
+
+    fn get(&self, secret_id: String) -> FutureResult<Option<Vec<u8>>> {
+        self.call("get", &secret_id, |locker| locker.get(secret_id.clone()))
+    }
+
+    fn set(&self, secret_id: String, value: Vec<u8>) -> FutureResult<()> {
+        self.call("set", (&secret_id, &value), |locker| {
+            locker.set(secret_id.clone(), value.clone())
+        })
+    }
+
+    fn delete(&self, secret_id: String) -> FutureResult<()> {
+        self.call("delete", &secret_id, |locker| locker.delete(secret_id.clone()))
+    }
+
+    fn exists(&self, secret_id: String) -> FutureResult<bool> {
+        self.call("exists", &secret_id, |locker| locker.exists(secret_id.clone()))
+    }
+
+    fn list_ids(&self) -> FutureResult<Vec<String>> {
+        self.call("list_ids", (), |locker| locker.list_ids())
+    }
+}
@@ -0,0 +1,194 @@
+//! `SQLite` implementation for wasi-vault
+//!
+//! Persists lockers to a local database file, for development without a
+//! secrets service. Secrets are stored unencrypted. Enabled using the
+//! `sqlite` feature.
+
+use std::env;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use futures::FutureExt;
+use kernel::Backend;
+use parking_lot::Mutex;
+use rusqlite::{Connection, OptionalExtension, params};
+use tracing::instrument;
+
+use crate::host::WasiVaultCtx;
+use crate::host::resource::{FutureResult, Locker};
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS secrets (
+    locker TEXT NOT NULL,
+    id TEXT NOT NULL,
+    value BLOB NOT NULL,
+    PRIMARY KEY (locker, id)
+)";
+
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    /// The database file, or `:memory:`.
+    pub database: String,
+}
+
+impl kernel::FromEnv for ConnectOptions {
+    /// The database is read from `VAULT_DATABASE`, defaulting to `vault.db`.
+    fn from_env() -> Result<Self> {
+        let database = env::var("VAULT_DATABASE").unwrap_or_else(|_| "vault.db".into());
+        Ok(Self { database })
+    }
+
+    /// Each instance's database is read from `<INSTANCE>_VAULT_DATABASE`,
+    /// falling back to `VAULT_DATABASE`, then `<instance>-vault.db`, so
+    /// that instances do not share a database unless configured to.
+    fn from_instance_env(instance: &str) -> Result<Self> {
+        let database = kernel::instance_var(instance, "VAULT_DATABASE")
+            .unwrap_or_else(|| format!("{instance}-vault.db"));
+        Ok(Self { database })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VaultSqlite {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl Backend for VaultSqlite {
+    type ConnectOptions = ConnectOptions;
+
+    #[instrument]
+    async fn connect_with(options: Self::ConnectOptions) -> Result<Self> {
+        tracing::debug!("initializing SQLite vault: {}", options.database);
+        let conn = Connection::open(&options.database)
+            .with_context(|| format!("failed to open SQLite database {}", options.database))?;
+        conn.execute_batch(SCHEMA).context("failed to create secrets table")?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+}
+
+impl WasiVaultCtx for VaultSqlite {
+    fn open_locker(&self, identifier: String) -> FutureResult<Arc<dyn Locker>> {
+        tracing::debug!("opening locker: {identifier}");
+
+        let locker = SqliteLocker {
+            identifier,
+            conn: Arc::clone(&self.conn),
+        };
+        async move { Ok(Arc::new(locker) as Arc<dyn Locker>) }.boxed()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SqliteLocker {
+    identifier: String,
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteLocker {
+    // Run `query` against the locker's database.
+    fn query<T: Send + 'static>(
+        &self, query: impl FnOnce(&Connection, &str) -> rusqlite::Result<T> + Send + 'static,
+    ) -> FutureResult<T> {
+        let conn = Arc::clone(&self.conn);
+        let identifier = self.identifier.clone();
+
+        async move {
+            let result = query(&conn.lock(), &identifier);
+            result.with_context(|| format!("querying locker {identifier}"))
+        }
+        .boxed()
+    }
+}
+
+impl Locker for SqliteLocker {
+    fn identifier(&self) -> String {
+        self.identifier.clone()
+    }
+
+    fn get(&self, secret_id: String) -> FutureResult<Option<Vec<u8>>> {
+        tracing::debug!("getting secret: {secret_id} from locker: {}", self.identifier);
+        self.query(move |conn, locker| {
+            conn.query_row(
+                "SELECT value FROM secrets WHERE locker = ?1 AND id = ?2",
+                params![locker, secret_id],
+                |row| row.get(0),
+            )
+            .optional()
+        })
+    }
+
+    fn set(&self, secret_id: String, value: Vec<u8>) -> FutureResult<()> {
+        tracing::debug!("setting secret: {secret_id} in locker: {}", self.identifier);
+        self.query(move |conn, locker| {
+            conn.execute(
+                "INSERT OR REPLACE INTO secrets (locker, id, value) VALUES (?1, ?2, ?3)",
+                params![locker, secret_id, value],
+            )
+            .map(drop)
+        })
+    }
+
+    fn delete(&self, secret_id: String) -> FutureResult<()> {
+        tracing::debug!("deleting secret: {secret_id} from locker: {}", self.identifier);
+        self.query(move |conn, locker| {
+            conn.execute(
+                "DELETE FROM secrets WHERE locker = ?1 AND id = ?2",
+                params![locker, secret_id],
+            )
+            .map(drop)
+        })
+    }
+
+    fn exists(&self, secret_id: String) -> FutureResult<bool> {
+        tracing::debug!("checking existence of secret: {secret_id} in locker: {}", self.identifier);
+        self.query(move |conn, locker| {
+            conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM secrets WHERE locker = ?1 AND id = ?2)",
+                params![locker, secret_id],
+                |row| row.get(0),
+            )
+        })
+    }
+
+    fn list_ids(&self) -> FutureResult<Vec<String>> {
+        tracing::debug!("listing secrets in locker: {}", self.identifier);
+        self.query(|conn, locker| {
+            let mut stmt = conn.prepare("SELECT id FROM secrets WHERE locker = ?1")?;
+            stmt.query_map([locker], |row| row.get(0))?.collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn locker_operations() {
+        let options = ConnectOptions {
+            database: ":memory:".to_string(),
+        };
+        let ctx = VaultSqlite::connect_with(options).await.expect("connect");
+
+        let locker = ctx.open_locker("test-locker".to_string()).await.expect("open locker");
+        locker.set("secret1".to_string(), b"value1".to_vec()).await.expect("set");
+        locker.set("secret2".to_string(), b"value2".to_vec()).await.expect("set");
+        let value = locker.get("secret1".to_string()).await.expect("get");
+        assert_eq!(value, Some(b"value1".to_vec()));
+        assert!(locker.exists("secret1".to_string()).await.expect("exists"));
+
+        // lockers share the database but not secrets
+        let other = ctx.open_locker("other-locker".to_string()).await.expect("open locker");
+        assert!(!other.exists("secret1".to_string()).await.expect("exists"));
+
+        let mut ids = locker.list_ids().await.expect("list_ids");
+        ids.sort();
+        assert_eq!(ids, vec!["secret1".to_string(), "secret2".to_string()]);
+
+        locker.delete("secret1".to_string()).await.expect("delete");
+        assert_eq!(locker.get("secret1".to_string()).await.expect("get"), None);
+        assert_eq!(locker.identifier(), "test-locker");
+    }
+}
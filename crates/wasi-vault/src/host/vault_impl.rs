@@ -1,10 +1,11 @@
 use anyhow::Context;
+use kernel::ResourceKind;
 use wasmtime::component::{Access, Accessor, Resource, ResourceTableError};
 
 use crate::host::generated::wasi::vault::vault::Error;
 use crate::host::resource::LockerProxy;
 use crate::host::vault::{Host, HostLocker, HostLockerWithStore, HostWithStore};
-use crate::host::{WasiVault, WasiVaultCtxView};
+use crate::host::{WasiVault, WasiVaultCtxView, replay_impl};
 
 pub type Result<T, E = Error> = anyhow::Result<T, E>;
 
@@ -12,7 +13,15 @@ impl HostWithStore for WasiVault {
     async fn open<T>(
         accessor: &Accessor<T, Self>, locker_id: String,
     ) -> Result<Resource<LockerProxy>> {
-        let locker = accessor.with(|mut store| store.get().ctx.open_locker(locker_id)).await?;
+        let locker = accessor
+            .with(|mut store| {
+                let view = store.get();
+                if !view.capabilities.allows(ResourceKind::Locker, &locker_id) {
+                    return Err(Error::AccessDenied);
+                }
+                Ok(replay_impl::open_locker(view.ctx, locker_id))
+            })?
+            .await?;
         let proxy = LockerProxy(locker);
         Ok(accessor.with(|mut store| store.get().table.push(proxy))?)
     }
@@ -70,6 +70,16 @@ where
     }
 }
 
+/// The host's interfaces, added to the runtime's WIT world.
+pub const WIT: kernel::HostWit = kernel::HostWit {
+    world: &["include wasi:websockets/imports@0.1.0;"],
+    deps: &[
+        ("websockets@0.1.0/store.wit", include_str!("../wit/deps/websockets/store.wit")),
+        ("websockets@0.1.0/types.wit", include_str!("../wit/deps/websockets/types.wit")),
+        ("websockets@0.1.0/world.wit", include_str!("../wit/deps/websockets/world.wit")),
+    ],
+};
+
 pub trait WebSocketsView: Send {
     fn websockets(&mut self) -> WasiWebSocketsCtxView<'_>;
 }
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::convert::Infallible;
 use std::env;
 use std::net::SocketAddr;
+use std::pin::pin;
 use std::sync::{Arc, Mutex as StdMutex};
 
 use anyhow::Result;
@@ -18,7 +19,7 @@ use hyper::service::service_fn;
 use hyper::upgrade::Upgraded;
 use hyper::{Method, Request, Response, StatusCode, Version};
 use hyper_util::rt::TokioIo;
-use kernel::State;
+use kernel::{InFlight, Shutdown, State};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{Mutex, OnceCell};
 use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
@@ -96,6 +97,7 @@ pub fn send_socket_message(message: &str) -> Result<()> {
 #[allow(clippy::significant_drop_tightening)]
 async fn accept_connection(
     peer_map: PeerMap, peer: SocketAddr, ws_stream: WebSocketStream<TokioIo<Upgraded>>,
+    shutdown: Shutdown,
 ) {
     let (tx, rx) = unbounded();
 
@@ -138,7 +140,16 @@ async fn accept_connection(
     let receive_from_others = rx.map(Ok).forward(outgoing);
 
     pin_mut!(broadcast_incoming, receive_from_others);
-    future::select(broadcast_incoming, receive_from_others).await;
+    tokio::select! {
+        _ = &mut broadcast_incoming => {}
+        _ = &mut receive_from_others => {}
+        () = shutdown.triggered() => {
+            // dropping the peer's sender ends the session once queued
+            // messages are sent, closing the socket
+            peer_map.lock().unwrap().remove(&peer);
+            _ = receive_from_others.await;
+        }
+    }
 
     tracing::info!("{} disconnected", &peer);
     peer_map.lock().unwrap().remove(&peer);
@@ -149,7 +160,8 @@ type Body = http_body_util::Full<hyper::body::Bytes>;
 #[allow(clippy::unused_async)]
 #[allow(clippy::map_unwrap_or)]
 async fn handle_request(
-    peer_map: PeerMap, mut req: Request<Incoming>, addr: SocketAddr,
+    peer_map: PeerMap, mut req: Request<Incoming>, addr: SocketAddr, in_flight: InFlight,
+    shutdown: Shutdown,
 ) -> Result<Response<Body>, Infallible> {
     let upgrade = HeaderValue::from_static("Upgrade");
     let websocket = HeaderValue::from_static("websocket");
@@ -178,7 +190,7 @@ async fn handle_request(
         return Ok(resp);
     }
     let ver = req.version();
-    tokio::task::spawn(async move {
+    in_flight.spawn(async move {
         match hyper::upgrade::on(&mut req).await {
             Ok(upgraded) => {
                 let upgraded = TokioIo::new(upgraded);
@@ -186,6 +198,7 @@ async fn handle_request(
                     peer_map,
                     addr,
                     WebSocketStream::from_raw_socket(upgraded, Role::Server, None).await,
+                    shutdown,
                 )
                 .await;
             }
@@ -202,11 +215,13 @@ async fn handle_request(
 }
 
 #[allow(clippy::missing_errors_doc)]
-pub async fn run_server<S>(_: &S) -> Result<()>
+pub async fn run_server<S>(state: &S) -> Result<()>
 where
     S: State,
     S::StoreCtx: WebSocketsView,
 {
+    let shutdown = state.shutdown().clone();
+    let grace_period = state.options().grace_period();
     let state = PeerMap::new(StdMutex::new(HashMap::new()));
     let _ = PEER_MAP.set(Arc::<StdMutex<HashMap<SocketAddr, PeerInfo>>>::clone(&state));
 
@@ -214,28 +229,53 @@ where
     let listener = TcpListener::bind(&addr).await?;
     tracing::info!("websocket server listening on: {}", listener.local_addr()?);
 
+    // accept connections until shutdown
+    let in_flight = InFlight::default();
     loop {
-        let (stream, peer_addr) = listener.accept().await?;
+        let (stream, peer_addr) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            () = shutdown.triggered() => break,
+        };
         let peer = stream.peer_addr().expect("connected streams should have a peer address");
         tracing::info!("Peer address: {}", peer);
         let state_ref = Arc::<StdMutex<HashMap<SocketAddr, PeerInfo>>>::clone(&state);
+        let sessions = in_flight.clone();
+        let shutdown = shutdown.clone();
 
-        tokio::spawn(async move {
+        in_flight.spawn(async move {
             let io = TokioIo::new(stream);
 
+            let session_shutdown = shutdown.clone();
             let service = service_fn(move |req| {
                 handle_request(
                     Arc::<StdMutex<HashMap<SocketAddr, PeerInfo>>>::clone(&state_ref),
                     req,
                     peer_addr,
+                    sessions.clone(),
+                    session_shutdown.clone(),
                 )
             });
 
             let conn = http1::Builder::new().serve_connection(io, service).with_upgrades();
+            let mut conn = pin!(conn);
 
-            if let Err(err) = conn.await {
+            // finish the upgrade (if any) then close the connection on shutdown
+            let result = tokio::select! {
+                result = conn.as_mut() => result,
+                () = shutdown.triggered() => {
+                    conn.as_mut().graceful_shutdown();
+                    conn.await
+                }
+            };
+            if let Err(err) = result {
                 tracing::error!("failed to serve connection: {err:?}");
             }
         });
     }
+
+    // close open sessions, waiting for each to finish
+    tracing::info!("websocket server shutting down");
+    in_flight.drain(grace_period).await;
+
+    Ok(())
 }
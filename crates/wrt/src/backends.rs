@@ -0,0 +1,345 @@
+//! # Backend Selection
+//!
+//! Each host with a selectable backend is given an enum of its backends,
+//! selected by name when the runtime starts. The name is read from
+//! `<HOST>_BACKEND` (e.g. `KEYVALUE_BACKEND=memory`) or, failing that, the
+//! runtime's config file, defaulting to the host's first backend.
+//!
+//! Hosts the component does not use are disabled: the host is still linked,
+//! but its backend is never connected and any use of it is an error.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::{env, fs};
+
+use anyhow::{Context, Result, bail};
+use futures::FutureExt;
+use kernel::FutureResult;
+use serde::Deserialize;
+#[cfg(feature = "blobstore-sqlite")]
+use wasi_blobstore::BlobstoreSqlite;
+use wasi_blobstore::{BlobstoreDefault, Container, WasiBlobstoreCtx};
+use wasi_identity::{Identity, IdentityDefault, WasiIdentityCtx};
+#[cfg(feature = "keyvalue-sqlite")]
+use wasi_keyvalue::KeyValueSqlite;
+use wasi_keyvalue::{Bucket, KeyValueDefault, WasiKeyValueCtx};
+use wasi_messaging::{Client, Message, MessagingDefault, Metadata, WasiMessagingCtx};
+use wasi_sql::{Connection, SqlDefault, WasiSqlCtx};
+#[cfg(feature = "vault-sqlite")]
+use wasi_vault::VaultSqlite;
+use wasi_vault::{Locker, VaultDefault, WasiVaultCtx};
+
+static SELECTION: OnceLock<Selection> = OnceLock::new();
+
+/// The runtime's config file.
+///
+/// ```json
+/// {
+///     "backends": {
+///         "keyvalue": "memory",
+///         "sql": "sqlite"
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The backend used by each host, keyed by host name.
+    #[serde(default)]
+    pub backends: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// Load the config file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid config.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("reading config {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("parsing config {}", path.display()))
+    }
+}
+
+/// The backend selected for each host, and the hosts the component uses.
+#[derive(Debug, Default)]
+struct Selection {
+    config: Config,
+    enabled: BTreeSet<&'static str>,
+}
+
+/// Select backends using `config`, enabling only the `enabled` hosts. Must be
+/// called before backends are connected.
+///
+/// # Errors
+///
+/// Returns an error if backends have already been selected.
+pub fn install(config: Config, enabled: impl IntoIterator<Item = &'static str>) -> Result<()> {
+    let selection = Selection {
+        config,
+        enabled: enabled.into_iter().collect(),
+    };
+    if SELECTION.set(selection).is_err() {
+        bail!("backends have already been selected");
+    }
+    Ok(())
+}
+
+/// The name of the backend selected for `host`, `default` when none is, or
+/// `None` when the host is disabled.
+fn selected(host: &str, default: &str) -> Option<String> {
+    let selection = SELECTION.get_or_init(Selection::default);
+    if !selection.enabled.contains(host) {
+        return None;
+    }
+    let var = format!("{}_BACKEND", host.to_uppercase());
+    let name = env::var(var).ok().or_else(|| selection.config.backends.get(host).cloned());
+    Some(name.unwrap_or_else(|| default.to_string()))
+}
+
+// Generates a host's backend enum, selecting the backend named at startup.
+// Variants can be gated on a cargo feature using `#[cfg(feature = "...")]`.
+macro_rules! backend {
+    (
+        $(#[$meta:meta])*
+        $backend:ident($host:literal): $ctx:path {
+            $($(#[$attr:meta])* $name:literal => $variant:ident($inner:ty),)+
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug)]
+        pub enum $backend {
+            /// The component does not use the host.
+            Disabled,
+            $($(#[$attr])* $variant($inner),)+
+        }
+
+        impl $backend {
+            /// The names of the backends compiled in.
+            const NAMES: &[&str] = &[$($(#[$attr])* $name,)+];
+
+            /// The selected backend's context.
+            fn ctx(&self) -> Result<&dyn $ctx> {
+                match self {
+                    Self::Disabled => bail!("{} is not enabled for this component", $host),
+                    $($(#[$attr])* Self::$variant(backend) => Ok(backend),)+
+                }
+            }
+        }
+
+        impl kernel::Backend for $backend {
+            type ConnectOptions = Selected;
+
+            async fn connect_with(_: Self::ConnectOptions) -> Result<Self> {
+                let Some(name) = selected($host, Self::NAMES[0]) else {
+                    return Ok(Self::Disabled);
+                };
+                match name.as_str() {
+                    $($(#[$attr])* $name => Ok(Self::$variant(<$inner as kernel::Backend>::connect().await?)),)+
+                    _ => bail!(
+                        "unknown {} backend `{name}`, expected one of: {}",
+                        $host,
+                        Self::NAMES.join(", ")
+                    ),
+                }
+            }
+
+            async fn health(&self) -> Result<()> {
+                match self {
+                    Self::Disabled => Ok(()),
+                    $($(#[$attr])* Self::$variant(backend) => backend.health().await,)+
+                }
+            }
+        }
+    };
+}
+
+/// Connection options for a selected backend. The selected backend reads its
+/// own options when connected.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Selected;
+
+impl kernel::FromEnv for Selected {
+    fn from_env() -> Result<Self> {
+        Ok(Self)
+    }
+}
+
+// Completes with `err` in place of a context's future.
+fn failed<T>(err: anyhow::Error) -> FutureResult<T> {
+    async move { Err(err) }.boxed()
+}
+
+backend! {
+    /// The `wasi:keyvalue` backend, selected using `KEYVALUE_BACKEND`.
+    KeyValueBackend("keyvalue"): WasiKeyValueCtx {
+        "memory" => Memory(KeyValueDefault),
+        #[cfg(feature = "keyvalue-sqlite")]
+        "sqlite" => Sqlite(KeyValueSqlite),
+    }
+}
+
+impl WasiKeyValueCtx for KeyValueBackend {
+    fn open_bucket(&self, identifier: String) -> FutureResult<Arc<dyn Bucket>> {
+        self.ctx().map_or_else(failed, |ctx| ctx.open_bucket(identifier))
+    }
+}
+
+backend! {
+    /// The `wasi:blobstore` backend, selected using `BLOBSTORE_BACKEND`.
+    BlobstoreBackend("blobstore"): WasiBlobstoreCtx {
+        "memory" => Memory(BlobstoreDefault),
+        #[cfg(feature = "blobstore-sqlite")]
+        "sqlite" => Sqlite(BlobstoreSqlite),
+    }
+}
+
+impl WasiBlobstoreCtx for BlobstoreBackend {
+    fn create_container(&self, name: String) -> FutureResult<Arc<dyn Container>> {
+        self.ctx().map_or_else(failed, |ctx| ctx.create_container(name))
+    }
+
+    fn get_container(&self, name: String) -> FutureResult<Arc<dyn Container>> {
+        self.ctx().map_or_else(failed, |ctx| ctx.get_container(name))
+    }
+
+    fn delete_container(&self, name: String) -> FutureResult<()> {
+        self.ctx().map_or_else(failed, |ctx| ctx.delete_container(name))
+    }
+
+    fn container_exists(&self, name: String) -> FutureResult<bool> {
+        self.ctx().map_or_else(failed, |ctx| ctx.container_exists(name))
+    }
+}
+
+backend! {
+    /// The `wasi:sql` backend, selected using `SQL_BACKEND`.
+    SqlBackend("sql"): WasiSqlCtx {
+        "sqlite" => Sqlite(SqlDefault),
+    }
+}
+
+impl WasiSqlCtx for SqlBackend {
+    fn open(&self, name: String) -> FutureResult<Arc<dyn Connection>> {
+        self.ctx().map_or_else(failed, |ctx| ctx.open(name))
+    }
+}
+
+backend! {
+    /// The `wasi:vault` backend, selected using `VAULT_BACKEND`.
+    VaultBackend("vault"): WasiVaultCtx {
+        "memory" => Memory(VaultDefault),
+        #[cfg(feature = "vault-sqlite")]
+        "sqlite" => Sqlite(VaultSqlite),
+    }
+}
+
+impl WasiVaultCtx for VaultBackend {
+    fn open_locker(&self, identifier: String) -> FutureResult<Arc<dyn Locker>> {
+        self.ctx().map_or_else(failed, |ctx| ctx.open_locker(identifier))
+    }
+}
+
+backend! {
+    /// The `wasi:identity` backend, selected using `IDENTITY_BACKEND`.
+    IdentityBackend("identity"): WasiIdentityCtx {
+        "default" => Default(IdentityDefault),
+    }
+}
+
+impl WasiIdentityCtx for IdentityBackend {
+    fn get_identity(&self, name: String) -> FutureResult<Arc<dyn Identity>> {
+        self.ctx().map_or_else(failed, |ctx| ctx.get_identity(name))
+    }
+}
+
+backend! {
+    /// The `wasi:messaging` backend, selected using `MESSAGING_BACKEND`.
+    MessagingBackend("messaging"): WasiMessagingCtx {
+        "memory" => Memory(MessagingDefault),
+    }
+}
+
+impl WasiMessagingCtx for MessagingBackend {
+    fn connect(&self) -> FutureResult<Arc<dyn Client>> {
+        self.ctx().map_or_else(failed, WasiMessagingCtx::connect)
+    }
+
+    fn new_message(&self, data: Vec<u8>) -> Result<Arc<dyn Message>> {
+        self.ctx()?.new_message(data)
+    }
+
+    fn set_content_type(
+        &self, message: Arc<dyn Message>, content_type: String,
+    ) -> Result<Arc<dyn Message>> {
+        self.ctx()?.set_content_type(message, content_type)
+    }
+
+    fn set_payload(&self, message: Arc<dyn Message>, data: Vec<u8>) -> Result<Arc<dyn Message>> {
+        self.ctx()?.set_payload(message, data)
+    }
+
+    fn add_metadata(
+        &self, message: Arc<dyn Message>, key: String, value: String,
+    ) -> Result<Arc<dyn Message>> {
+        self.ctx()?.add_metadata(message, key, value)
+    }
+
+    fn set_metadata(
+        &self, message: Arc<dyn Message>, metadata: Metadata,
+    ) -> Result<Arc<dyn Message>> {
+        self.ctx()?.set_metadata(message, metadata)
+    }
+
+    fn remove_metadata(&self, message: Arc<dyn Message>, key: String) -> Result<Arc<dyn Message>> {
+        self.ctx()?.remove_metadata(message, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kernel::Backend;
+
+    use super::*;
+
+    #[test]
+    fn config() {
+        let config: Config =
+            serde_json::from_str(r#"{"backends": {"keyvalue": "memory"}}"#).expect("config");
+        assert_eq!(config.backends.get("keyvalue").map(String::as_str), Some("memory"));
+        assert!(serde_json::from_str::<Config>(r#"{"backend": {}}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn select() {
+        let config = Config {
+            backends: [("vault".to_string(), "redis".to_string())].into(),
+        };
+        install(config, ["keyvalue", "vault"]).expect("install");
+
+        // defaults to the first backend
+        let keyvalue = KeyValueBackend::connect().await.expect("connect");
+        assert!(matches!(keyvalue, KeyValueBackend::Memory(_)));
+
+        // unused hosts are disabled
+        let blobstore = BlobstoreBackend::connect().await.expect("connect");
+        assert!(blobstore.ctx().is_err());
+
+        let Err(err) = VaultBackend::connect().await else {
+            panic!("unknown backend connected");
+        };
+        let expected = format!(
+            "unknown vault backend `redis`, expected one of: {}",
+            VaultBackend::NAMES.join(", ")
+        );
+        assert_eq!(err.to_string(), expected);
+    }
+
+    #[cfg(feature = "keyvalue-sqlite")]
+    #[test]
+    fn feature_backends() {
+        assert_eq!(KeyValueBackend::NAMES, ["memory", "sqlite"]);
+    }
+}
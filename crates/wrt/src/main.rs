@@ -0,0 +1,137 @@
+//! # WRT
+//!
+//! A generic runtime with every host compiled in. Rather than choosing each
+//! host's backend at build time using `buildgen::runtime!`, the backend is
+//! selected when the runtime starts.
+//!
+//! Hosts the component does not use are disabled, and their servers are not
+//! started. Disabled hosts are still linked; only their backends are
+//! `Disabled`.
+
+cfg_if::cfg_if! {
+    if #[cfg(not(target_arch = "wasm32"))] {
+        mod backends;
+
+        use std::path::PathBuf;
+
+        use anyhow::{Context as _, Result};
+        use clap::Parser;
+        use kernel::futures::future::try_join_all;
+        use kernel::{Command, RuntimeOptions, ServerHandle, State};
+
+        use crate::backends::Config;
+
+        mod host {
+            use wasi_blobstore::WasiBlobstore;
+            use wasi_config::{ConfigDefault, WasiConfig};
+            use wasi_http::{HttpDefault, WasiHttp};
+            use wasi_identity::WasiIdentity;
+            use wasi_keyvalue::WasiKeyValue;
+            use wasi_messaging::WasiMessaging;
+            use wasi_otel::{OtelDefault, WasiOtel};
+            use wasi_sql::WasiSql;
+            use wasi_vault::WasiVault;
+            use wasi_websockets::{WasiWebSockets, WebSocketsDefault};
+
+            use crate::backends::{
+                BlobstoreBackend, IdentityBackend, KeyValueBackend, MessagingBackend, SqlBackend,
+                VaultBackend,
+            };
+
+            buildgen::runtime!({
+                WasiHttp: HttpDefault,
+                WasiOtel: OtelDefault,
+                WasiConfig: ConfigDefault,
+                WasiIdentity: IdentityBackend,
+                WasiKeyValue: KeyValueBackend,
+                WasiBlobstore: BlobstoreBackend,
+                WasiSql: SqlBackend,
+                WasiVault: VaultBackend,
+                WasiMessaging: MessagingBackend,
+                WasiWebSockets: WebSocketsDefault,
+            });
+
+            pub use self::runtime::{Context, inspect, wit};
+        }
+
+        /// Hosts with a selectable backend, enabled when the component uses
+        /// an interface named with the prefix.
+        const HOSTS: [(&str, &str); 6] = [
+            ("blobstore", "wasi:blobstore/"),
+            ("identity", "wasi:identity/"),
+            ("keyvalue", "wasi:keyvalue/"),
+            ("messaging", "wasi:messaging/"),
+            ("sql", "wasi:sql/"),
+            ("vault", "wasi:vault/"),
+        ];
+
+        #[derive(Parser)]
+        #[command(version, about, long_about = None)]
+        struct Cli {
+            /// The runtime's config file, selecting the backend used by each
+            /// host.
+            #[arg(long, env = "WRT_CONFIG", global = true)]
+            config: Option<PathBuf>,
+
+            /// The command to execute.
+            #[command(subcommand)]
+            command: Command,
+        }
+
+        /// Main function
+        #[tokio::main]
+        async fn main() -> Result<()> {
+            let cli = Cli::parse();
+            match cli.command {
+                Command::Run { wasm, options } => {
+                    let config = cli.config.as_deref().map(Config::from_path).transpose()?;
+                    run(wasm, options, config.unwrap_or_default()).await
+                }
+                Command::Inspect { wasm, json } => host::inspect(wasm, json),
+                Command::Wit { dir } => host::wit().write(&dir),
+                command => command.execute(),
+            }
+        }
+
+        /// Run the specified wasm guest (or manifest of guests), connecting
+        /// the selected backend of each host it uses.
+        async fn run(wasm: PathBuf, options: RuntimeOptions, config: Config) -> Result<()> {
+            let mut compiled = kernel::create(&wasm, &options)
+                .with_context(|| format!("compiling {}", wasm.display()))?;
+
+            let uses = |prefix: &str| compiled.imports(prefix) || compiled.exports(prefix);
+            let enabled: Vec<_> =
+                HOSTS.iter().filter(|(_, prefix)| uses(prefix)).map(|(host, _)| *host).collect();
+            tracing::info!("enabled hosts: {}", enabled.join(", "));
+            backends::install(config, enabled).context("selecting backends")?;
+
+            let mut servers = vec!["admin"];
+            if compiled.exports("wasi:http/handler") {
+                servers.push("WasiHttp");
+            }
+            if uses("wasi:messaging/") {
+                servers.push("WasiMessaging");
+            }
+            if compiled.imports("wasi:websockets/") {
+                servers.push("WasiWebSockets");
+            }
+
+            let state = host::Context::new(&mut compiled)
+                .await
+                .context("preparing runtime state")?;
+            if options.watch {
+                kernel::watch(compiled, state.guests().clone(), state.shutdown().clone())
+                    .context("watching wasm guests")?;
+            }
+
+            state.shutdown().listen();
+            let handles = state.start_servers(&servers).context("starting runtime services")?;
+            let result = try_join_all(handles.into_iter().map(ServerHandle::wait)).await;
+
+            kernel::flush_telemetry();
+            result.map(|_| ())
+        }
+    } else {
+        fn main() {}
+    }
+}
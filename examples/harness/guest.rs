@@ -0,0 +1,71 @@
+//! # Test Harness Wasm Guest
+//!
+//! This module is the guest exercised by the test harness tests (see
+//! `test.rs`). It handles both HTTP requests and messages, recording each in
+//! the key-value store so tests can check the guest's effects:
+//! - `POST /orders/{id}` stores the request body under the order's id
+//! - messages on `orders.shipped`, carrying an order id, mark the order shipped
+
+#![cfg(target_arch = "wasm32")]
+
+use anyhow::Context;
+use axum::Router;
+use axum::extract::Path;
+use axum::routing::post;
+use bytes::Bytes;
+use http::StatusCode;
+use wasi_http::HttpResult;
+use wasi_keyvalue::store;
+use wasi_messaging::types::{Error, Message};
+use wasip3::exports::http::handler::Guest;
+use wasip3::http::types::{ErrorCode, Request, Response};
+
+// ----------------------------------------------------------------------------
+// HTTP Interface
+// ----------------------------------------------------------------------------
+
+pub struct Http;
+wasip3::http::proxy::export!(Http);
+
+impl Guest for Http {
+    /// Routes HTTP requests to the order handler.
+    async fn handle(request: Request) -> Result<Response, ErrorCode> {
+        let router = Router::new().route("/orders/{id}", post(create_order));
+        wasi_http::serve(router, request).await
+    }
+}
+
+/// Stores the order in the `orders` bucket.
+async fn create_order(Path(id): Path<String>, body: Bytes) -> HttpResult<(StatusCode, String)> {
+    let bucket = store::open("orders".to_string()).await.context("opening bucket")?;
+    bucket.set(id.clone(), body.to_vec()).await.context("storing order")?;
+    Ok((StatusCode::CREATED, id))
+}
+
+// ----------------------------------------------------------------------------
+// Messaging Interface
+// ----------------------------------------------------------------------------
+
+pub struct Messaging;
+wasi_messaging::export!(Messaging with_types_in wasi_messaging);
+
+impl wasi_messaging::incoming_handler::Guest for Messaging {
+    /// Marks the order named by the message as shipped.
+    async fn handle(message: Message) -> anyhow::Result<(), Error> {
+        if message.topic().as_deref() != Some("orders.shipped") {
+            return Ok(());
+        }
+
+        let id = String::from_utf8(message.data())
+            .map_err(|e| Error::Other(format!("order id is not utf8: {e}")))?;
+        let bucket = store::open("shipped".to_string())
+            .await
+            .map_err(|e| Error::Other(format!("opening bucket: {e}")))?;
+        bucket
+            .set(id, b"true".to_vec())
+            .await
+            .map_err(|e| Error::Other(format!("storing shipment: {e}")))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+//! Tests guests using the in-process test harness (`kernel::testing`),
+//! invoking the `harness` example guest over HTTP and messaging and checking
+//! its effects on the (in-memory) key-value backend.
+//!
+//! The guest is built for `wasm32-wasip2` when the tests first run:
+//!
+//! ```bash
+//! cargo test --test harness
+//! ```
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+use http::StatusCode;
+use wasi_http::{HttpDefault, HttpHarness, WasiHttp};
+use wasi_keyvalue::{KeyValueDefault, WasiKeyValue};
+use wasi_messaging::{MessagingDefault, MessagingHarness, WasiMessaging};
+
+buildgen::runtime!({
+    WasiHttp: HttpDefault,
+    WasiKeyValue: KeyValueDefault,
+    WasiMessaging: MessagingDefault,
+});
+
+// Build the guest once for all tests, using its own target directory so the
+// build does not wait on the one running the tests.
+fn guest() -> &'static Path {
+    static GUEST: OnceLock<PathBuf> = OnceLock::new();
+
+    GUEST.get_or_init(|| {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..");
+        let target_dir = root.join("target").join("harness");
+        let status = Command::new(env!("CARGO"))
+            .current_dir(&root)
+            .args(["build", "--example", "harness-wasm", "--target", "wasm32-wasip2"])
+            .arg("--target-dir")
+            .arg(&target_dir)
+            .status()
+            .expect("should run cargo");
+        assert!(status.success(), "should build the harness guest");
+
+        target_dir.join("wasm32-wasip2/debug/examples/harness_wasm.wasm")
+    })
+}
+
+#[tokio::test]
+async fn http_request() {
+    let harness = runtime::harness(guest()).await.expect("should load guest");
+
+    let request = http::Request::post("http://localhost/orders/o-1")
+        .body(r#"{"item":"widget"}"#)
+        .expect("should build request");
+    let response = harness.http(request).await.expect("should handle request");
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(response.body().as_ref(), b"o-1");
+
+    let kv = harness.state().key_value_default.get().await.expect("should connect");
+    assert_eq!(kv.value("orders", "o-1").as_deref(), Some(br#"{"item":"widget"}"#.as_slice()));
+
+    // unrouted requests do not reach the key-value store
+    let request =
+        http::Request::get("http://localhost/orders/o-2").body("").expect("should build request");
+    let response = harness.http(request).await.expect("should handle request");
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(kv.value("orders", "o-2"), None);
+}
+
+#[tokio::test]
+async fn deliver_message() {
+    let harness = runtime::harness(guest()).await.expect("should load guest");
+    let kv = harness.state().key_value_default.get().await.expect("should connect");
+
+    harness.deliver("orders.shipped", "o-1").await.expect("should handle message");
+    assert_eq!(kv.value("shipped", "o-1").as_deref(), Some(b"true".as_slice()));
+
+    // messages on other topics are ignored by the guest
+    harness.deliver("orders.cancelled", "o-2").await.expect("should handle message");
+    assert_eq!(kv.value("shipped", "o-2"), None);
+}
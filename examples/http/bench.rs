@@ -0,0 +1,62 @@
+//! Compares the cost of instantiating the `http` example guest using the
+//! on-demand and pooling instance allocators.
+//!
+//! ```bash
+//! cargo build --example http-wasm --target wasm32-wasip2 --release
+//! cargo bench --bench http-instantiate
+//! ```
+
+cfg_if::cfg_if! {
+    if #[cfg(not(target_arch = "wasm32"))] {
+        use std::path::PathBuf;
+
+        use criterion::{Criterion, criterion_group, criterion_main};
+        use kernel::{RuntimeOptions, State};
+        use tokio::runtime::Runtime;
+        use wasi_http::{WasiHttp, HttpDefault};
+        use wasi_otel::{WasiOtel, OtelDefault};
+
+        buildgen::runtime!({
+            WasiHttp: HttpDefault,
+            WasiOtel: OtelDefault,
+        });
+
+        const WASM: &str = "target/wasm32-wasip2/release/examples/http_wasm.wasm";
+
+        fn instantiate(c: &mut Criterion) {
+            let rt = Runtime::new().expect("should create runtime");
+            let wasm = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..").join(WASM);
+
+            let mut group = c.benchmark_group("instantiate");
+            for (name, pooling) in [("on-demand", false), ("pooling", true)] {
+                let options = RuntimeOptions {
+                    pooling,
+                    ..RuntimeOptions::default()
+                };
+                let mut compiled = kernel::create(&wasm, &options).expect("should compile guest");
+                let state = rt
+                    .block_on(runtime::Context::new(&mut compiled))
+                    .expect("should prepare runtime state");
+
+                let guest = state.guests().primary();
+
+                group.bench_function(name, |b| {
+                    b.to_async(&rt).iter(|| async {
+                        let mut store = state.new_store(guest).await.expect("should create store");
+                        guest
+                            .instance_pre()
+                            .instantiate_async(&mut store)
+                            .await
+                            .expect("should instantiate guest")
+                    });
+                });
+            }
+            group.finish();
+        }
+
+        criterion_group!(benches, instantiate);
+        criterion_main!(benches);
+    } else {
+        fn main() {}
+    }
+}